@@ -0,0 +1,132 @@
+//! Random insert/remove/tick sequences against TimerQueue, checked
+//! against a sorted list of absolute deadlines. Run with
+//! `cargo +nightly fuzz run timer_queue`, add `--features nightly` for the
+//! linked list store.
+#![no_main]
+
+use std::time::Duration;
+
+use event_scheduler::{TaskId, TimerQueue};
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    /* Short delays in microseconds, so deadlines collide */
+    Schedule(u16),
+    /* Delays in seconds, up to saturating the deadline */
+    ScheduleFar(u64),
+    /* Index into every id handed out, fired or not */
+    Cancel(u16),
+    /* Move time forward and take at most `take` of the expired items */
+    Tick { advance: u16, take: u8 },
+    Jump(u64),
+    /* Tick to a time before the last one */
+    Rewind(u16),
+}
+
+/* Pending items in firing order: by deadline, then by submission */
+#[derive(Default)]
+struct Model {
+    now: Duration,
+    pending: Vec<(Duration, u64, TaskId)>,
+}
+
+impl Model {
+    fn schedule(&mut self, delay: Duration, seq: u64, id: TaskId) {
+        let deadline = self.now.saturating_add(delay);
+        let at = self
+            .pending
+            .iter()
+            .position(|&(d, _, _)| d > deadline)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(at, (deadline, seq, id));
+    }
+
+    fn cancel(&mut self, id: TaskId) -> Option<u64> {
+        let at = self.pending.iter().position(|&(_, _, i)| i == id)?;
+        Some(self.pending.remove(at).1)
+    }
+
+    fn expire(&mut self, now: Duration) {
+        self.now = self.now.max(now);
+    }
+
+    fn next_expired(&mut self) -> Option<u64> {
+        match self.pending.first() {
+            Some(&(deadline, seq, _)) if deadline <= self.now => {
+                self.pending.remove(0);
+                Some(seq)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn tick(queue: &mut TimerQueue<u64>, model: &mut Model, now: Duration, take: usize) {
+    model.expire(now);
+    let mut expired = queue.tick(now);
+
+    for _ in 0..take {
+        let fired = expired.next();
+        assert_eq!(fired, model.next_expired(), "fired out of order");
+        if fired.is_none() {
+            break;
+        }
+    }
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut queue = TimerQueue::new(Duration::ZERO);
+    let mut model = Model::default();
+    let mut ids = Vec::new();
+    let mut seq = 0;
+
+    for op in ops {
+        match op {
+            Op::Schedule(micros) => {
+                let delay = Duration::from_micros(micros as u64);
+                let id = queue.insert(delay, seq);
+                model.schedule(delay, seq, id);
+                ids.push(id);
+                seq += 1;
+            }
+            Op::ScheduleFar(secs) => {
+                let delay = Duration::from_secs(secs);
+                let id = queue.insert(delay, seq);
+                model.schedule(delay, seq, id);
+                ids.push(id);
+                seq += 1;
+            }
+            Op::Cancel(i) => {
+                if ids.is_empty() {
+                    continue;
+                }
+                let id = ids[i as usize % ids.len()];
+                assert_eq!(queue.remove(id), model.cancel(id), "cancel of {}", id);
+            }
+            Op::Tick { advance, take } => {
+                let now = model
+                    .now
+                    .saturating_add(Duration::from_micros(advance as u64));
+                tick(&mut queue, &mut model, now, take as usize);
+            }
+            Op::Jump(secs) => {
+                let now = model.now.saturating_add(Duration::from_secs(secs));
+                tick(&mut queue, &mut model, now, usize::MAX);
+            }
+            Op::Rewind(micros) => {
+                let now = model
+                    .now
+                    .saturating_sub(Duration::from_micros(micros as u64));
+                tick(&mut queue, &mut model, now, usize::MAX);
+            }
+        }
+
+        assert_eq!(queue.len(), model.pending.len());
+        assert_eq!(
+            queue.next_deadline(),
+            model.pending.first().map(|&(deadline, _, _)| deadline)
+        );
+    }
+});
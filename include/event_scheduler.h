@@ -0,0 +1,50 @@
+/* C interface to event_scheduler, built with the capi feature */
+#ifndef EVENT_SCHEDULER_H
+#define EVENT_SCHEDULER_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SCHED_OK 0
+#define SCHED_ERR_INVALID -1
+#define SCHED_ERR_SHUT_DOWN -2
+#define SCHED_ERR_QUEUE_FULL -3
+#define SCHED_ERR_TOO_MANY_TIMERS -4
+#define SCHED_ERR_CLOCK -5
+#define SCHED_ERR_WORKER_PANICKED -6
+#define SCHED_ERR_OUT_OF_MEMORY -7
+#define SCHED_ERR_CANCELLED -8
+
+typedef struct sched sched;
+
+/* Runs on the scheduler's worker thread */
+typedef void (*sched_callback)(void *user_data);
+
+/* A scheduler with the default configuration, free it with sched_shutdown */
+sched *sched_create(void);
+
+/* Call cb(user_data) once millis have passed. Returns the timeout's id
+ * for sched_cancel, or a negative SCHED_ERR_ code. user_data must stay
+ * valid until the callback ran or was cancelled.
+ */
+int64_t sched_schedule_delayed(sched *sched, sched_callback cb, void *user_data,
+                               uint64_t millis);
+
+/* Cancel timeout id if it has not fired yet, SCHED_OK also if it has */
+int64_t sched_cancel(sched *sched, int64_t id);
+
+/* Shut down, wait for the callbacks already handed to the worker and free
+ * the scheduler. Pending timeouts never fire. SCHED_ERR_WORKER_PANICKED
+ * only if the worker thread died and could not be replaced, not for a
+ * callback that panicked.
+ */
+int64_t sched_shutdown(sched *sched);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,174 @@
+/* Actors on the scheduler: a mailbox per actor, drained by one task at a
+ * time so messages are handled serially and in the order they arrived,
+ * also when a runtime runs tasks on many threads. A message sent later is a
+ * timeout delivering it to the mailbox, draining right there if idle.
+ */
+use std::collections::VecDeque;
+use std::sync::{Arc, PoisonError, Weak};
+use std::time::Duration;
+
+use crate::error::{Result, SchedError};
+use crate::handle::TimeoutHandle;
+use crate::scheduler::Scheduler;
+use crate::sync::Mutex;
+
+/// State handling messages one at a time, see `Scheduler::start_actor`.
+pub trait Actor: Send + 'static {
+    type Message: Send + 'static;
+
+    fn handle(&mut self, message: Self::Message);
+}
+
+/// Sends messages to an actor. Addresses don't keep the scheduler alive,
+/// once it is gone sending returns `ShutDown`.
+pub struct Addr<A: Actor> {
+    scheduler: Weak<Scheduler>,
+    mailbox: Arc<Mailbox<A>>,
+}
+
+impl<A: Actor> Addr<A> {
+    pub(crate) fn start(scheduler: &Arc<Scheduler>, actor: A) -> Addr<A> {
+        Addr {
+            scheduler: Arc::downgrade(scheduler),
+            mailbox: Arc::new(Mailbox {
+                actor: Mutex::new(actor),
+                queue: Mutex::new(Queue {
+                    messages: VecDeque::new(),
+                    draining: false,
+                }),
+            }),
+        }
+    }
+
+    /// Have the actor handle `message` on the worker, after the messages
+    /// sent before it.
+    pub fn send(&self, message: A::Message) -> Result<()> {
+        let scheduler = self.scheduler.upgrade().ok_or(SchedError::ShutDown)?;
+        if !self.mailbox.push(message) {
+            return Ok(());
+        }
+
+        let mailbox = self.mailbox.clone();
+        let weak = self.scheduler.clone();
+        let result = scheduler.schedule(move || mailbox.drain(&weak));
+        if result.is_err() {
+            self.mailbox.abandon();
+        }
+        result
+    }
+
+    /// Send `message` once `delay` has passed. Cancel the handle to take it
+    /// back before then.
+    pub fn send_later(&self, delay: Duration, message: A::Message) -> Result<TimeoutHandle> {
+        let scheduler = self.scheduler.upgrade().ok_or(SchedError::ShutDown)?;
+        let mailbox = self.mailbox.clone();
+        let weak = self.scheduler.clone();
+        scheduler.schedule_delayed(delay, move || {
+            if mailbox.push(message) {
+                mailbox.drain(&weak);
+            }
+        })
+    }
+}
+
+impl<A: Actor> Clone for Addr<A> {
+    fn clone(&self) -> Addr<A> {
+        Addr {
+            scheduler: self.scheduler.clone(),
+            mailbox: self.mailbox.clone(),
+        }
+    }
+}
+
+impl<A: Actor> std::fmt::Debug for Addr<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let queue = self
+            .mailbox
+            .queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        f.debug_struct("Addr")
+            .field("queued", &queue.messages.len())
+            .field("draining", &queue.draining)
+            .finish()
+    }
+}
+
+struct Mailbox<A: Actor> {
+    actor: Mutex<A>,
+    queue: Mutex<Queue<A::Message>>,
+}
+
+struct Queue<M> {
+    messages: VecDeque<M>,
+    /* Set while a task owns the actor, cleared once it finds the queue empty */
+    draining: bool,
+}
+
+impl<A: Actor> Mailbox<A> {
+    /* Queue `message`, true if the caller is now the one to drain */
+    fn push(&self, message: A::Message) -> bool {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        queue.messages.push_back(message);
+        !std::mem::replace(&mut queue.draining, true)
+    }
+
+    /* The drain task never got scheduled, the next send tries again */
+    fn abandon(&self) {
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .draining = false;
+    }
+
+    fn drain(self: &Arc<Self>, scheduler: &Weak<Scheduler>) {
+        let mut draining = Draining {
+            mailbox: self,
+            scheduler,
+            done: false,
+        };
+        loop {
+            let message = {
+                let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+                match queue.messages.pop_front() {
+                    Some(message) => message,
+                    None => {
+                        queue.draining = false;
+                        break;
+                    }
+                }
+            };
+            /* A handler that panicked before left the actor however far it
+             * got, the next messages are handled from that state all the same
+             */
+            let mut actor = self.actor.lock().unwrap_or_else(PoisonError::into_inner);
+            actor.handle(message);
+        }
+        draining.done = true;
+    }
+}
+
+/* Should a handler panic its message is lost, the rest get a new task */
+struct Draining<'a, A: Actor> {
+    mailbox: &'a Arc<Mailbox<A>>,
+    scheduler: &'a Weak<Scheduler>,
+    done: bool,
+}
+
+impl<A: Actor> Drop for Draining<'_, A> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        let mailbox = self.mailbox.clone();
+        let weak = self.scheduler.clone();
+        let scheduled = self
+            .scheduler
+            .upgrade()
+            .map(|scheduler| scheduler.schedule(move || mailbox.drain(&weak)));
+        if !matches!(scheduled, Some(Ok(()))) {
+            self.mailbox.abandon();
+        }
+    }
+}
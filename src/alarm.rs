@@ -0,0 +1,179 @@
+/* Alarm timeouts, which wake a suspended machine to fire. They live apart
+ * from the timekeeper, in a TimerQueue on CLOCK_BOOTTIME with a timerfd on
+ * CLOCK_BOOTTIME_ALARM armed, at an absolute time, for the earliest. Alarm
+ * clocks take CAP_WAKE_ALARM, without it the timerfd is on CLOCK_BOOTTIME,
+ * which counts the time suspended just the same but only fires once
+ * something else woke the machine.
+ *
+ * A thread per scheduler, started with the first alarm, waits on the
+ * timerfd and hands due work to the scheduler as immediate tasks. Whoever
+ * changes the queue re-arms the timerfd, the thread is only poked through
+ * an eventfd to stop.
+ */
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::time::Duration;
+
+use crate::backend::eventfd::EventFd;
+use crate::clock::{BootTimeClock, Clock};
+use crate::delta::TimerQueue;
+use crate::scheduler::Scheduler;
+use crate::{TaskId, Work};
+
+pub(crate) struct Alarms {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    timers: Mutex<TimerQueue<Work>>,
+    timerfd: OwnedFd,
+    stop: EventFd,
+    stopped: AtomicBool,
+}
+
+fn timerfd() -> OwnedFd {
+    let create =
+        |clock| unsafe { libc::timerfd_create(clock, libc::TFD_CLOEXEC | libc::TFD_NONBLOCK) };
+    let mut fd = create(libc::CLOCK_BOOTTIME_ALARM);
+    if fd < 0 {
+        warn!(
+            "alarms won't wake the system from suspend: {}",
+            io::Error::last_os_error()
+        );
+        fd = create(libc::CLOCK_BOOTTIME);
+    }
+    assert!(fd >= 0, "timerfd_create: {}", io::Error::last_os_error());
+
+    unsafe { OwnedFd::from_raw_fd(fd) }
+}
+
+impl Alarms {
+    pub(crate) fn start(scheduler: Weak<Scheduler>) -> Alarms {
+        let shared = Arc::new(Shared {
+            timers: Mutex::new(TimerQueue::new(BootTimeClock.now())),
+            timerfd: timerfd(),
+            stop: EventFd::new(),
+            stopped: AtomicBool::new(false),
+        });
+
+        let thread = shared.clone();
+        std::thread::Builder::new()
+            .name("event_scheduler alarms".into())
+            .spawn(move || thread.run(scheduler))
+            .expect("failed to spawn the alarm thread");
+        Alarms { shared }
+    }
+
+    pub(crate) fn insert(&self, delay: Duration, work: Work) -> TaskId {
+        let mut timers = self.shared.lock();
+        /* Inserts count from the last tick, what is due stays queued */
+        let _ = timers.tick(BootTimeClock.now());
+        let id = timers.insert(delay, work);
+        self.shared.arm(&timers);
+        id
+    }
+
+    pub(crate) fn cancel(&self, id: TaskId) -> bool {
+        let mut timers = self.shared.lock();
+        let removed = timers.remove(id).is_some();
+        self.shared.arm(&timers);
+        removed
+    }
+}
+
+impl Drop for Alarms {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        self.shared.stop.signal();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, TimerQueue<Work>> {
+        self.timers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /* For the earliest deadline, or disarmed with none. Deadlines in the
+     * past fire right away.
+     */
+    fn arm(&self, timers: &TimerQueue<Work>) {
+        let at = timers
+            .next_deadline()
+            .map_or(Duration::ZERO, |at| at.max(Duration::from_nanos(1)));
+        let spec = libc::itimerspec {
+            it_interval: libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            it_value: libc::timespec {
+                tv_sec: at.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+                tv_nsec: at.subsec_nanos() as libc::c_long,
+            },
+        };
+
+        let ret = unsafe {
+            libc::timerfd_settime(
+                self.timerfd.as_raw_fd(),
+                libc::TFD_TIMER_ABSTIME,
+                &spec,
+                std::ptr::null_mut(),
+            )
+        };
+        assert_eq!(ret, 0, "timerfd_settime: {}", io::Error::last_os_error());
+    }
+
+    fn run(&self, scheduler: Weak<Scheduler>) {
+        loop {
+            let mut fds = [
+                libc::pollfd {
+                    fd: self.timerfd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: self.stop.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            let ret = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                assert_eq!(err.kind(), io::ErrorKind::Interrupted, "poll: {}", err);
+                continue;
+            }
+            if self.stopped.load(Ordering::SeqCst) {
+                return;
+            }
+            if fds[0].revents & libc::POLLIN == 0 {
+                continue;
+            }
+
+            let mut expirations: u64 = 0;
+            unsafe {
+                libc::read(
+                    self.timerfd.as_raw_fd(),
+                    &mut expirations as *mut u64 as *mut libc::c_void,
+                    8,
+                )
+            };
+
+            let due = {
+                let mut timers = self.lock();
+                let due: Vec<Work> = timers.tick(BootTimeClock.now()).collect();
+                self.arm(&timers);
+                due
+            };
+            for work in due {
+                let Some(scheduler) = scheduler.upgrade() else {
+                    return;
+                };
+                if let Err(e) = scheduler.run(None, None, None, work) {
+                    warn!("alarm dropped: {}", e);
+                }
+            }
+        }
+    }
+}
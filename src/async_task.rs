@@ -0,0 +1,171 @@
+/* A future run as scheduled work. Its first poll is the work of a timeout,
+ * every wake after that queues another poll on the worker like immediate
+ * work. There is one worker thread, so polls never overlap and a wake
+ * during a poll is simply the next task. With a runtime configured the
+ * timeout's work spawns the future there instead.
+ */
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError};
+use std::task::{Context, Poll, Wake, Waker};
+
+use crate::error::SchedError;
+use crate::handle::Completion;
+use crate::spawn::Spawner;
+use crate::stats::Counters;
+use crate::sync::atomic::Ordering;
+use crate::sync::mpsc::Sender;
+use crate::sync::Mutex;
+use crate::timeout::Label;
+use crate::worker::{Command, Task};
+use crate::Work;
+
+pub(crate) type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+pub(crate) struct AsyncTask<T> {
+    /* None once it completed or while it is being polled */
+    future: Mutex<Option<BoxFuture<T>>>,
+    worker: Sender<Command>,
+    counters: Arc<Counters>,
+    label: Label,
+    done: Arc<Completion<T>>,
+}
+
+impl<T: Send + 'static> AsyncTask<T> {
+    /* The work of the timeout giving `future` its first poll */
+    pub(crate) fn start(
+        future: BoxFuture<T>,
+        worker: Sender<Command>,
+        counters: Arc<Counters>,
+        label: Label,
+        done: Arc<Completion<T>>,
+    ) -> Work {
+        let task = Arc::new(AsyncTask {
+            future: Mutex::new(Some(future)),
+            worker,
+            counters,
+            label,
+            done,
+        });
+
+        let mut first = Some(task);
+        Box::new(move || {
+            if let Some(task) = first.take() {
+                task.poll();
+            }
+        })
+    }
+
+    /* The work of the timeout spawning `future` onto the runtime */
+    pub(crate) fn spawn(
+        future: BoxFuture<T>,
+        spawner: Arc<dyn Spawner>,
+        done: Arc<Completion<T>>,
+    ) -> Work {
+        let mut first = Some((future, done));
+        Box::new(move || {
+            let Some((future, done)) = first.take() else {
+                return;
+            };
+            spawner.spawn(Box::pin(Spawned { future, done }));
+        })
+    }
+
+    fn poll(self: Arc<Self>) {
+        let future = self
+            .future
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        let Some(mut future) = future else {
+            return;
+        };
+
+        let waker = Waker::from(self.clone());
+        let mut panicked = Panicked(Some(&self.done));
+        let poll = future.as_mut().poll(&mut Context::from_waker(&waker));
+        panicked.0 = None;
+
+        match poll {
+            Poll::Ready(output) => self.done.complete(Ok(output)),
+            Poll::Pending => {
+                *self.future.lock().unwrap_or_else(PoisonError::into_inner) = Some(future);
+            }
+        }
+    }
+}
+
+impl<T: Send + 'static> Wake for AsyncTask<T> {
+    fn wake(self: Arc<Self>) {
+        let task = Task {
+            work: Box::new({
+                let task = self.clone();
+                move || task.clone().poll()
+            }),
+            label: self.label,
+            tags: None,
+            node: None,
+        };
+
+        self.counters.queue();
+        if self.worker.send(Command::Run(task)).is_err() {
+            /* The worker is gone, nothing will poll it again */
+            self.counters.in_flight.fetch_sub(1, Ordering::AcqRel);
+            self.future
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            self.done.complete(Err(SchedError::ShutDown));
+        }
+    }
+}
+
+impl<T> Drop for AsyncTask<T> {
+    /* Cancelled before its first poll, or never woken again. A no-op if it
+     * completed.
+     */
+    fn drop(&mut self) {
+        self.done.complete(Err(SchedError::Cancelled));
+    }
+}
+
+/* A future as spawned onto a runtime. Panics stop here, some runtimes
+ * would abort on them.
+ */
+struct Spawned<T> {
+    future: BoxFuture<T>,
+    done: Arc<Completion<T>>,
+}
+
+impl<T> Future for Spawned<T> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let poll = panic::catch_unwind(AssertUnwindSafe(|| self.future.as_mut().poll(cx)));
+        match poll {
+            Ok(Poll::Pending) => return Poll::Pending,
+            Ok(Poll::Ready(output)) => self.done.complete(Ok(output)),
+            Err(_) => self.done.complete(Err(SchedError::WorkerPanicked)),
+        }
+        Poll::Ready(())
+    }
+}
+
+/* Dropped unfinished with the runtime */
+impl<T> Drop for Spawned<T> {
+    fn drop(&mut self) {
+        self.done.complete(Err(SchedError::Cancelled));
+    }
+}
+
+/* Completes the handle if a poll unwinds, the future is dropped with it */
+struct Panicked<'a, T>(Option<&'a Completion<T>>);
+
+impl<T> Drop for Panicked<'_, T> {
+    fn drop(&mut self) {
+        if let Some(done) = self.0 {
+            done.complete(Err(SchedError::WorkerPanicked));
+        }
+    }
+}
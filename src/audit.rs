@@ -0,0 +1,145 @@
+/* Append-only record of every task run, one JSON object per line:
+ *
+ *   {"started_unix_ns":..,"finished_unix_ns":..,"task":3,"name":"flush",
+ *    "lateness_ns":120000,"duration_ns":5000,"result":"ok"}
+ *
+ * Timestamps are wall clock, lateness is that of the fire that queued the
+ * run and null for immediate work. Every line is flushed as it is written.
+ */
+use std::collections::{HashMap, VecDeque};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::json::{self, Object};
+use crate::observer::{ExecuteEvent, FireEvent, SchedulerObserver, StartEvent};
+use crate::TaskId;
+
+type Rotate = Box<dyn FnMut() -> io::Result<Box<dyn Write + Send>> + Send>;
+
+/// An observer writing a line per task run to a file or any `Write`, see
+/// `Builder::observer`.
+pub struct AuditLog {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    writer: Box<dyn Write + Send>,
+    written: u64,
+    rotation: Option<(u64, Rotate)>,
+    /* Lateness of fires whose runs haven't finished yet, in order */
+    lateness: HashMap<TaskId, VecDeque<Duration>>,
+    started: Option<SystemTime>,
+}
+
+impl AuditLog {
+    pub fn new<W: Write + Send + 'static>(writer: W) -> AuditLog {
+        AuditLog {
+            inner: Mutex::new(Inner {
+                writer: Box::new(writer),
+                written: 0,
+                rotation: None,
+                lateness: HashMap::new(),
+                started: None,
+            }),
+        }
+    }
+
+    /// Append to the file at `path`, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<AuditLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog::new(file))
+    }
+
+    /// Once more than `max_bytes` have been written to the current
+    /// writer, call `rotate` for the next one, e.g. after renaming the
+    /// file away. Should it fail, writing carries on where it was.
+    pub fn rotate_at<F, W>(self, max_bytes: u64, mut rotate: F) -> AuditLog
+    where
+        F: FnMut() -> io::Result<W> + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let mut inner = self
+            .inner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        inner.rotation = Some((
+            max_bytes,
+            Box::new(move || rotate().map(|w| Box::new(w) as Box<dyn Write + Send>)),
+        ));
+        AuditLog {
+            inner: Mutex::new(inner),
+        }
+    }
+}
+
+impl Inner {
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.flush()?;
+        self.written += line.len() as u64;
+
+        if let Some((max_bytes, rotate)) = self.rotation.as_mut() {
+            if self.written > *max_bytes {
+                self.writer = rotate()?;
+                self.written = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(json::nanos)
+        .unwrap_or(0)
+}
+
+impl SchedulerObserver for AuditLog {
+    fn on_fire(&self, event: &FireEvent) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner
+            .lateness
+            .entry(event.task)
+            .or_default()
+            .push_back(event.actual.saturating_sub(event.expected));
+    }
+
+    fn on_start(&self, _event: &StartEvent) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.started = Some(SystemTime::now());
+    }
+
+    fn on_execute(&self, event: &ExecuteEvent) {
+        let finished = SystemTime::now();
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let started = inner.started.take().unwrap_or(finished);
+
+        let lateness = event.task.and_then(|task| {
+            let fires = inner.lateness.get_mut(&task)?;
+            let lateness = fires.pop_front();
+            if fires.is_empty() {
+                inner.lateness.remove(&task);
+            }
+            lateness
+        });
+
+        let mut line = String::new();
+        Object::new(&mut line)
+            .num("started_unix_ns", unix_nanos(started))
+            .num("finished_unix_ns", unix_nanos(finished))
+            .opt_num("task", event.task.map(TaskId::as_u64))
+            .opt_str("name", event.name)
+            .opt_num("lateness_ns", lateness.map(json::nanos))
+            .num("duration_ns", json::nanos(event.duration))
+            .str("result", if event.panicked { "panicked" } else { "ok" })
+            .finish();
+        line.push('\n');
+
+        if let Err(e) = inner.write(&line) {
+            error!("audit log write failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::sync::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(loom))]
+use std::time::Instant;
+
+use crate::clock::Wake;
+use crate::error::SchedError;
+use crate::sync::mpsc::{self, Receiver, Sender};
+use crate::sync::{Condvar, Mutex};
+
+use crate::timekeeper::Message;
+
+#[cfg(target_os = "linux")]
+pub(crate) mod eventfd;
+#[cfg(target_os = "linux")]
+mod timerfd;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring;
+#[cfg(windows)]
+mod waitable;
+
+/// How the timekeeper waits for its next deadline, the built-in
+/// `TimerBackend`s.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Block on a condition variable with a timeout, works everywhere.
+    #[default]
+    Channel,
+    /// Arm a timerfd for the earliest deadline and poll it together with an
+    /// eventfd signalled on every submission.
+    #[cfg(target_os = "linux")]
+    TimerFd,
+    /// Arm an io_uring timeout for the earliest deadline, moving it with
+    /// timeout updates as submissions arrive.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    IoUring,
+    /// Wait on a high resolution waitable timer armed for the earliest
+    /// deadline together with an event set on every submission. Accurate
+    /// without raising the system wide timer period like `windows-hires`.
+    #[cfg(windows)]
+    WaitableTimer,
+}
+
+/// What the timekeeper sleeps on between deadlines, the timer half of the
+/// scheduler. The built-in `Backend`s implement it, for platforms and
+/// event loops they don't cover see `Builder::timer_backend`.
+pub trait TimerBackend: Send + 'static {
+    /// Block until `timeout` has passed, forever for None, or until the
+    /// `waker` is called. Returning early is harmless, the timekeeper
+    /// checks for what woke it.
+    fn wait(&mut self, timeout: Option<Duration>);
+
+    /// Interrupts a `wait`, called after every submission from any thread.
+    /// A call while nothing waits must still cut the next `wait` short.
+    fn waker(&self) -> Wake;
+}
+
+impl Backend {
+    pub(crate) fn start(self) -> Box<dyn TimerBackend> {
+        match self {
+            Backend::Channel => Box::new(ChannelBackend::default()),
+            #[cfg(target_os = "linux")]
+            Backend::TimerFd => Box::new(timerfd::TimerFdBackend::new()),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Backend::IoUring => Box::new(uring::UringBackend::new()),
+            #[cfg(windows)]
+            Backend::WaitableTimer => Box::new(waitable::WaitableTimerBackend::new()),
+        }
+    }
+}
+
+/* Sending end, cheap to clone into the scheduler and its helpers. Closing
+ * takes the sender away from every clone at once, sends hold the lock so
+ * none can slip in behind the shutdown message.
+ */
+#[derive(Clone)]
+pub(crate) struct Submitter {
+    sender: Arc<Mutex<Option<Sender<Message>>>>,
+    waker: Arc<Wake>,
+}
+
+impl Submitter {
+    /* Fails with ShutDown once closed or the timekeeper is gone */
+    pub(crate) fn send(&self, message: Message) -> crate::Result<()> {
+        match self.sender.lock().unwrap().as_ref() {
+            Some(sender) => sender.send(message).map_err(|_| SchedError::ShutDown)?,
+            None => return Err(SchedError::ShutDown),
+        }
+
+        self.signal();
+        Ok(())
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.sender.lock().unwrap().is_none()
+    }
+
+    /* Refuse further submissions and tell the timekeeper to stop */
+    pub(crate) fn close(&self) {
+        if let Some(sender) = self.sender.lock().unwrap().take() {
+            let _ = sender.send(Message::Shutdown);
+            self.signal();
+        }
+    }
+
+    fn signal(&self) {
+        (self.waker)();
+    }
+}
+
+/* The timekeeper's submissions, sleeping on `backend` between them */
+pub(crate) fn intake(backend: Box<dyn TimerBackend>) -> (Submitter, Intake) {
+    let (sender, receiver) = mpsc::channel();
+    let submitter = Submitter {
+        sender: Arc::new(Mutex::new(Some(sender))),
+        waker: Arc::new(backend.waker()),
+    };
+
+    (submitter, Intake { receiver, backend })
+}
+
+/* Messages are queued before the backend is woken, so checking the
+ * channel after every wait sees them
+ */
+pub(crate) struct Intake {
+    receiver: Receiver<Message>,
+    backend: Box<dyn TimerBackend>,
+}
+
+impl Intake {
+    pub(crate) fn recv(&mut self) -> Result<Message, RecvError> {
+        loop {
+            match self.receiver.try_recv() {
+                Err(TryRecvError::Empty) => self.backend.wait(None),
+                result => return result.map_err(|_| RecvError),
+            }
+        }
+    }
+
+    #[cfg(not(loom))]
+    pub(crate) fn recv_timeout(&mut self, timeout: Duration) -> Result<Message, RecvTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => return Ok(message),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+
+            let left = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if left.is_some_and(|left| left.is_zero()) {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            self.backend.wait(left);
+        }
+    }
+
+    /* Loom has no notion of time, any wait may as well run out */
+    #[cfg(loom)]
+    pub(crate) fn recv_timeout(&mut self, _timeout: Duration) -> Result<Message, RecvTimeoutError> {
+        self.receiver.try_recv().map_err(|e| match e {
+            TryRecvError::Empty => RecvTimeoutError::Timeout,
+            TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+        })
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Result<Message, TryRecvError> {
+        self.receiver.try_recv()
+    }
+}
+
+/* Backend::Channel, a condition variable the submitters notify. The flag
+ * keeps a wakeup that came while nothing waited for the next wait.
+ */
+#[derive(Default)]
+struct ChannelBackend {
+    woken: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl TimerBackend for ChannelBackend {
+    fn wait(&mut self, timeout: Option<Duration>) {
+        let (woken, wakeup) = &*self.woken;
+        let mut guard = woken.lock().unwrap();
+        if !*guard {
+            guard = match timeout {
+                Some(timeout) => wakeup.wait_timeout(guard, timeout).unwrap().0,
+                None => wakeup.wait(guard).unwrap(),
+            };
+        }
+        *guard = false;
+    }
+
+    fn waker(&self) -> Wake {
+        let woken = self.woken.clone();
+        Box::new(move || {
+            let (woken, wakeup) = &*woken;
+            *woken.lock().unwrap() = true;
+            wakeup.notify_one();
+        })
+    }
+}
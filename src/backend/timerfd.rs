@@ -0,0 +1,117 @@
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::eventfd::EventFd;
+use super::TimerBackend;
+use crate::clock::Wake;
+
+struct TimerFd(OwnedFd);
+
+impl TimerFd {
+    fn new() -> TimerFd {
+        let fd = unsafe {
+            libc::timerfd_create(
+                libc::CLOCK_MONOTONIC,
+                libc::TFD_CLOEXEC | libc::TFD_NONBLOCK,
+            )
+        };
+        assert!(fd >= 0, "timerfd_create: {}", io::Error::last_os_error());
+
+        TimerFd(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    /* A zero timeout would disarm the timer, the caller doesn't wait then.
+     * Anything past time_t is clamped, the kernel saturates long before.
+     */
+    fn arm(&self, timeout: Option<Duration>) {
+        let value = timeout.unwrap_or(Duration::ZERO);
+        let spec = libc::itimerspec {
+            it_interval: libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            it_value: libc::timespec {
+                tv_sec: value.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+                tv_nsec: value.subsec_nanos() as libc::c_long,
+            },
+        };
+
+        let ret =
+            unsafe { libc::timerfd_settime(self.0.as_raw_fd(), 0, &spec, std::ptr::null_mut()) };
+        assert_eq!(ret, 0, "timerfd_settime: {}", io::Error::last_os_error());
+    }
+
+    fn drain(&self) {
+        let mut expirations: u64 = 0;
+        unsafe {
+            libc::read(
+                self.0.as_raw_fd(),
+                &mut expirations as *mut u64 as *mut libc::c_void,
+                8,
+            )
+        };
+    }
+}
+
+/* Waits in poll() on a timerfd armed for the deadline and an eventfd the
+ * submitters signal after queueing a message.
+ */
+pub(crate) struct TimerFdBackend {
+    timer: TimerFd,
+    eventfd: Arc<EventFd>,
+}
+
+impl TimerFdBackend {
+    pub(crate) fn new() -> TimerFdBackend {
+        TimerFdBackend {
+            timer: TimerFd::new(),
+            eventfd: Arc::new(EventFd::new()),
+        }
+    }
+}
+
+impl TimerBackend for TimerFdBackend {
+    fn wait(&mut self, timeout: Option<Duration>) {
+        if timeout == Some(Duration::ZERO) {
+            return;
+        }
+        self.timer.arm(timeout);
+
+        loop {
+            let mut fds = [
+                libc::pollfd {
+                    fd: self.timer.0.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: self.eventfd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+
+            let ret = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                assert_eq!(err.kind(), io::ErrorKind::Interrupted, "poll: {}", err);
+                continue;
+            }
+
+            if fds[0].revents & libc::POLLIN != 0 {
+                self.timer.drain();
+            }
+            if fds[1].revents & libc::POLLIN != 0 {
+                self.eventfd.drain();
+            }
+            return;
+        }
+    }
+
+    fn waker(&self) -> Wake {
+        let eventfd = self.eventfd.clone();
+        Box::new(move || eventfd.signal())
+    }
+}
@@ -0,0 +1,189 @@
+use std::io;
+use std::os::fd::AsRawFd;
+use std::sync::Arc;
+use std::time::Duration;
+
+use io_uring::{opcode, types, IoUring};
+
+use super::eventfd::EventFd;
+use super::TimerBackend;
+use crate::clock::Wake;
+
+/* The kernel reads the seconds as signed, longer waits are clamped */
+const MAX_TIMEOUT: Duration = Duration::from_secs(i64::MAX as u64);
+
+/* user_data layout: low byte tags the operation, the rest carries the
+ * generation of the armed timeout so stale expiries can be told apart.
+ */
+const TAG_TIMEOUT: u64 = 1;
+const TAG_UPDATE: u64 = 2;
+const TAG_REMOVE: u64 = 3;
+const TAG_POLL: u64 = 4;
+
+const ENTRIES: u32 = 8;
+
+enum Woken {
+    Submission,
+    Expired,
+}
+
+/* Keeps one IORING_OP_TIMEOUT in flight for the earliest deadline, moved
+ * with IORING_TIMEOUT_UPDATE instead of being cancelled and re-queued, and
+ * a poll on the submission eventfd.
+ */
+pub(crate) struct UringBackend {
+    ring: IoUring,
+    eventfd: Arc<EventFd>,
+    /* Read by the kernel when a timeout sqe is submitted */
+    timespec: Box<types::Timespec>,
+    generation: u64,
+    armed: bool,
+    polling: bool,
+}
+
+impl UringBackend {
+    pub(crate) fn new() -> UringBackend {
+        let ring = IoUring::new(ENTRIES).expect("Failed to set up io_uring");
+
+        UringBackend {
+            ring,
+            eventfd: Arc::new(EventFd::new()),
+            timespec: Box::new(types::Timespec::new()),
+            generation: 0,
+            armed: false,
+            polling: false,
+        }
+    }
+
+    fn push(&mut self, entry: io_uring::squeue::Entry) {
+        while unsafe { self.ring.submission().push(&entry) }.is_err() {
+            self.ring.submit().expect("Failed to submit to io_uring");
+        }
+    }
+
+    fn timeout_data(&self, tag: u64) -> u64 {
+        self.generation << 8 | tag
+    }
+
+    fn arm(&mut self, timeout: Option<Duration>) {
+        match timeout {
+            Some(timeout) => {
+                *self.timespec = timeout.min(MAX_TIMEOUT).into();
+
+                let entry = if self.armed {
+                    opcode::TimeoutUpdate::new(self.timeout_data(TAG_TIMEOUT), &*self.timespec)
+                        .build()
+                        .user_data(self.timeout_data(TAG_UPDATE))
+                } else {
+                    self.generation += 1;
+                    self.armed = true;
+                    opcode::Timeout::new(&*self.timespec)
+                        .build()
+                        .user_data(self.timeout_data(TAG_TIMEOUT))
+                };
+                self.push(entry);
+            }
+
+            None if self.armed => {
+                let entry = opcode::TimeoutRemove::new(self.timeout_data(TAG_TIMEOUT))
+                    .build()
+                    .user_data(self.timeout_data(TAG_REMOVE));
+                self.push(entry);
+                self.armed = false;
+            }
+
+            None => {}
+        }
+    }
+
+    fn poll_submissions(&mut self) {
+        if self.polling {
+            return;
+        }
+
+        let entry = opcode::PollAdd::new(types::Fd(self.eventfd.as_raw_fd()), libc::POLLIN as u32)
+            .build()
+            .user_data(TAG_POLL);
+        self.push(entry);
+        self.polling = true;
+    }
+
+    fn reap(&mut self, timeout: Option<Duration>) -> Option<Woken> {
+        let completions: Vec<(u64, i32)> = self
+            .ring
+            .completion()
+            .map(|cqe| (cqe.user_data(), cqe.result()))
+            .collect();
+
+        /* The timeout completed before an update reached it, that expiry
+         * was for the old deadline
+         */
+        let generation = self.generation;
+        let superseded = completions.iter().any(|&(user_data, result)| {
+            user_data == (generation << 8 | TAG_UPDATE) && result == -libc::ENOENT
+        });
+
+        let mut woken = None;
+        for (user_data, result) in completions {
+            let current = user_data >> 8 == self.generation;
+
+            match user_data & 0xff {
+                TAG_POLL => {
+                    self.polling = false;
+                    self.eventfd.drain();
+                    woken = woken.or(Some(Woken::Submission));
+                }
+
+                /* -ETIME is a real expiry, -ECANCELED a removal */
+                TAG_TIMEOUT if current => {
+                    self.armed = false;
+                    if result == -libc::ETIME && !superseded {
+                        woken = Some(Woken::Expired);
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        if superseded {
+            self.arm(timeout);
+        }
+
+        woken
+    }
+}
+
+impl TimerBackend for UringBackend {
+    fn wait(&mut self, timeout: Option<Duration>) {
+        if timeout == Some(Duration::ZERO) {
+            return;
+        }
+
+        /* Settle whatever completed since the last wait. A submission may
+         * have come in after the intake last looked, an expiry left in the
+         * queue belongs to a deadline that is no longer wanted.
+         */
+        if let Some(Woken::Submission) = self.reap(None) {
+            return;
+        }
+        self.arm(timeout);
+
+        loop {
+            self.poll_submissions();
+            if let Err(err) = self.ring.submit_and_wait(1) {
+                assert_eq!(err.kind(), io::ErrorKind::Interrupted, "io_uring: {}", err);
+                continue;
+            }
+
+            if self.reap(timeout).is_some() {
+                return;
+            }
+        }
+    }
+
+    fn waker(&self) -> Wake {
+        let eventfd = self.eventfd.clone();
+        Box::new(move || eventfd.signal())
+    }
+}
@@ -0,0 +1,142 @@
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::TimerBackend;
+use crate::clock::Wake;
+
+type Handle = *mut c_void;
+
+const CREATE_WAITABLE_TIMER_HIGH_RESOLUTION: u32 = 0x0000_0002;
+const TIMER_ALL_ACCESS: u32 = 0x001f_0003;
+const INFINITE: u32 = 0xffff_ffff;
+const WAIT_FAILED: u32 = 0xffff_ffff;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn CreateWaitableTimerExW(
+        attributes: *mut c_void,
+        name: *const u16,
+        flags: u32,
+        access: u32,
+    ) -> Handle;
+    fn SetWaitableTimer(
+        timer: Handle,
+        due: *const i64,
+        period: i32,
+        completion: *const c_void,
+        argument: *mut c_void,
+        resume: i32,
+    ) -> i32;
+    fn CancelWaitableTimer(timer: Handle) -> i32;
+    fn CreateEventW(
+        attributes: *mut c_void,
+        manual_reset: i32,
+        initial_state: i32,
+        name: *const u16,
+    ) -> Handle;
+    fn SetEvent(event: Handle) -> i32;
+    fn WaitForMultipleObjects(count: u32, handles: *const Handle, all: i32, millis: u32) -> u32;
+    fn CloseHandle(handle: Handle) -> i32;
+    fn GetLastError() -> u32;
+}
+
+/* Kernel object handles may be used and closed from any thread */
+struct Owned(Handle);
+
+unsafe impl Send for Owned {}
+unsafe impl Sync for Owned {}
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+/* High resolution timers need Windows 10 1803, older ones get the regular
+ * kind, which waits with the system timer period
+ */
+fn timer() -> Owned {
+    let mut timer = unsafe {
+        CreateWaitableTimerExW(
+            ptr::null_mut(),
+            ptr::null(),
+            CREATE_WAITABLE_TIMER_HIGH_RESOLUTION,
+            TIMER_ALL_ACCESS,
+        )
+    };
+    if timer.is_null() {
+        timer =
+            unsafe { CreateWaitableTimerExW(ptr::null_mut(), ptr::null(), 0, TIMER_ALL_ACCESS) };
+    }
+    assert!(
+        !timer.is_null(),
+        "CreateWaitableTimerExW: error {}",
+        unsafe { GetLastError() }
+    );
+
+    Owned(timer)
+}
+
+/* Waits on a high resolution waitable timer armed for the deadline and an
+ * auto-reset event the submitters set after queueing a message, without
+ * touching the system wide timer period.
+ */
+pub(crate) struct WaitableTimerBackend {
+    timer: Owned,
+    event: Arc<Owned>,
+}
+
+impl WaitableTimerBackend {
+    pub(crate) fn new() -> WaitableTimerBackend {
+        let event = unsafe { CreateEventW(ptr::null_mut(), 0, 0, ptr::null()) };
+        assert!(!event.is_null(), "CreateEventW: error {}", unsafe {
+            GetLastError()
+        });
+
+        WaitableTimerBackend {
+            timer: timer(),
+            event: Arc::new(Owned(event)),
+        }
+    }
+
+    /* Due times are relative when negative, in 100ns units */
+    fn arm(&self, timeout: Duration) {
+        let ticks = (timeout.as_nanos() / 100).clamp(1, i64::MAX as u128) as i64;
+        let due = -ticks;
+        let ret =
+            unsafe { SetWaitableTimer(self.timer.0, &due, 0, ptr::null(), ptr::null_mut(), 0) };
+        assert_ne!(ret, 0, "SetWaitableTimer: error {}", unsafe {
+            GetLastError()
+        });
+    }
+}
+
+impl TimerBackend for WaitableTimerBackend {
+    fn wait(&mut self, timeout: Option<Duration>) {
+        match timeout {
+            Some(timeout) if timeout.is_zero() => return,
+            Some(timeout) => self.arm(timeout),
+            None => unsafe {
+                CancelWaitableTimer(self.timer.0);
+            },
+        }
+
+        let handles = [self.event.0, self.timer.0];
+        let ret = unsafe { WaitForMultipleObjects(2, handles.as_ptr(), 0, INFINITE) };
+        assert_ne!(
+            ret,
+            WAIT_FAILED,
+            "WaitForMultipleObjects: error {}",
+            unsafe { GetLastError() }
+        );
+    }
+
+    fn waker(&self) -> Wake {
+        let event = self.event.clone();
+        Box::new(move || {
+            unsafe { SetEvent(event.0) };
+        })
+    }
+}
@@ -0,0 +1,23 @@
+use std::sync::mpsc::channel;
+use std::time::{Duration, SystemTime};
+
+const ROUNDS: u32 = 8;
+const PROBE: Duration = Duration::from_micros(500);
+
+/* Measure how far past a requested timeout recv_timeout actually returns on
+ * this host. Nothing is ever sent, so every round runs into the timeout.
+ */
+pub(crate) fn calibrate() -> Duration {
+    let (_sender, receiver) = channel::<()>();
+    let mut overshoot = Duration::ZERO;
+
+    for _ in 0..ROUNDS {
+        let start = SystemTime::now();
+        let _ = receiver.recv_timeout(PROBE);
+        let slept = start.elapsed().unwrap_or(PROBE);
+
+        overshoot += slept.saturating_sub(PROBE);
+    }
+
+    overshoot / ROUNDS
+}
@@ -0,0 +1,126 @@
+/* C bindings, declared in include/event_scheduler.h. Build the shared
+ * library with
+ *
+ *     cargo rustc --lib --release --features capi --crate-type cdylib
+ *
+ * A scheduler is a boxed Scheduler behind an opaque pointer, timeouts are
+ * referred to by their task id. Errors come back as negative codes, one
+ * per SchedError.
+ */
+use std::ffi::c_void;
+use std::time::Duration;
+
+use crate::error::SchedError;
+use crate::scheduler::Scheduler;
+use crate::task::TaskId;
+
+/// Called on the scheduler's worker thread with the `user_data` it was
+/// scheduled with.
+pub type SchedCallback = extern "C" fn(user_data: *mut c_void);
+
+pub const SCHED_OK: i64 = 0;
+pub const SCHED_ERR_INVALID: i64 = -1;
+pub const SCHED_ERR_SHUT_DOWN: i64 = -2;
+pub const SCHED_ERR_QUEUE_FULL: i64 = -3;
+pub const SCHED_ERR_TOO_MANY_TIMERS: i64 = -4;
+pub const SCHED_ERR_CLOCK: i64 = -5;
+pub const SCHED_ERR_WORKER_PANICKED: i64 = -6;
+pub const SCHED_ERR_OUT_OF_MEMORY: i64 = -7;
+pub const SCHED_ERR_CANCELLED: i64 = -8;
+
+fn code(e: SchedError) -> i64 {
+    match e {
+        SchedError::ShutDown => SCHED_ERR_SHUT_DOWN,
+        SchedError::QueueFull => SCHED_ERR_QUEUE_FULL,
+        SchedError::TooManyTimers => SCHED_ERR_TOO_MANY_TIMERS,
+        SchedError::ClockError => SCHED_ERR_CLOCK,
+        SchedError::WorkerPanicked => SCHED_ERR_WORKER_PANICKED,
+        SchedError::OutOfMemory => SCHED_ERR_OUT_OF_MEMORY,
+        SchedError::Cancelled => SCHED_ERR_CANCELLED,
+    }
+}
+
+/* Whoever schedules vouches for the callback being fine with user_data on
+ * another thread
+ */
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+/// A scheduler with the default configuration, to be freed with
+/// `sched_shutdown`.
+#[no_mangle]
+pub extern "C" fn sched_create() -> *mut Scheduler {
+    Box::into_raw(Box::new(Scheduler::new()))
+}
+
+/// Call `cb(user_data)` once `millis` have passed. Returns the timeout's
+/// id for `sched_cancel`, or a negative error code.
+///
+/// # Safety
+///
+/// `sched` must come from `sched_create` and not be shut down yet.
+/// `user_data` must stay valid for `cb` until it ran or was cancelled.
+#[no_mangle]
+pub unsafe extern "C" fn sched_schedule_delayed(
+    sched: *mut Scheduler,
+    cb: Option<SchedCallback>,
+    user_data: *mut c_void,
+    millis: u64,
+) -> i64 {
+    let (Some(sched), Some(cb)) = (sched.as_ref(), cb) else {
+        return SCHED_ERR_INVALID;
+    };
+
+    let user_data = UserData(user_data);
+    let scheduled = sched.schedule_delayed(Duration::from_millis(millis), move || {
+        let user_data = user_data;
+        cb(user_data.0)
+    });
+    match scheduled {
+        Ok(handle) => handle.id().as_u64().min(i64::MAX as u64) as i64,
+        Err(e) => code(e),
+    }
+}
+
+/// Cancel timeout `id` if it has not fired yet. Returns `SCHED_OK`, also
+/// when it already fired, or a negative error code.
+///
+/// # Safety
+///
+/// `sched` must come from `sched_create` and not be shut down yet.
+#[no_mangle]
+pub unsafe extern "C" fn sched_cancel(sched: *mut Scheduler, id: i64) -> i64 {
+    let Some(sched) = sched.as_ref() else {
+        return SCHED_ERR_INVALID;
+    };
+    if id < 0 {
+        return SCHED_ERR_INVALID;
+    }
+
+    match sched.cancel(TaskId::from_u64(id as u64)) {
+        Ok(()) => SCHED_OK,
+        Err(e) => code(e),
+    }
+}
+
+/// Shut the scheduler down, wait for the worker to finish the callbacks
+/// already handed to it, and free it. Pending timeouts never fire.
+/// Returns `SCHED_OK`, or `SCHED_ERR_WORKER_PANICKED` if the worker thread
+/// died and could not be replaced. A callback that panics is not reported
+/// here, the worker catches it and carries on.
+///
+/// # Safety
+///
+/// `sched` must come from `sched_create` and is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn sched_shutdown(sched: *mut Scheduler) -> i64 {
+    if sched.is_null() {
+        return SCHED_ERR_INVALID;
+    }
+
+    match Box::from_raw(sched).join() {
+        Ok(()) => SCHED_OK,
+        Err(e) => code(e),
+    }
+}
@@ -0,0 +1,168 @@
+/* Chrome trace event format, as loaded by about://tracing and Perfetto.
+ * Timestamps are microseconds since the trace was created, read off the
+ * real monotonic clock whatever clock the scheduler runs on, so the
+ * timeline shows when things actually happened.
+ */
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+use crate::json::{self, Object};
+use crate::observer::{CancelEvent, ExecuteEvent, FireEvent, ScheduleEvent, SchedulerObserver};
+use crate::timeout::Label;
+use crate::TaskId;
+
+/* One track per stage */
+const CALLERS: u32 = 1;
+const TIMEKEEPER: u32 = 2;
+const WORKER: u32 = 3;
+
+/// An observer keeping a timeline of every schedule, fire, cancel and run,
+/// exportable as Chrome trace JSON. Clones share the timeline, so keep one
+/// to export from and hand another to `Builder::observer`.
+///
+/// Everything is kept in memory until `clear`, meant for test runs rather
+/// than production.
+#[derive(Clone)]
+pub struct ChromeTrace {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    origin: Instant,
+    /* Encoded trace events */
+    events: Mutex<Vec<String>>,
+}
+
+impl ChromeTrace {
+    pub fn new() -> ChromeTrace {
+        ChromeTrace {
+            inner: Arc::new(Inner {
+                origin: Instant::now(),
+                events: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// The timeline so far as a JSON trace file.
+    pub fn to_json(&self) -> String {
+        let events = self.events();
+        let tracks = [
+            (CALLERS, "schedule"),
+            (TIMEKEEPER, "timekeeper"),
+            (WORKER, "worker"),
+        ];
+        let metadata = tracks.iter().map(|&(tid, name)| {
+            let mut args = String::new();
+            Object::new(&mut args).str("name", name).finish();
+            let mut out = String::new();
+            Object::new(&mut out)
+                .str("name", "thread_name")
+                .str("ph", "M")
+                .num("pid", 1)
+                .num("tid", tid)
+                .raw("args", &args)
+                .finish();
+            out
+        });
+
+        let mut out = String::new();
+        Object::new(&mut out)
+            .raw("traceEvents", &json::array(metadata.chain(events)))
+            .str("displayTimeUnit", "ms")
+            .finish();
+        out
+    }
+
+    /// Drop everything recorded so far.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn events(&self) -> Vec<String> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<String>> {
+        self.inner
+            .events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn micros(&self, at: Instant) -> f64 {
+        at.saturating_duration_since(self.inner.origin).as_nanos() as f64 / 1000.0
+    }
+
+    fn instant(&self, tid: u32, name: &str, args: String) {
+        let mut out = String::new();
+        Object::new(&mut out)
+            .str("name", name)
+            .str("ph", "i")
+            .str("s", "t")
+            .num("ts", self.micros(Instant::now()))
+            .num("pid", 1)
+            .num("tid", tid)
+            .raw("args", &args)
+            .finish();
+        self.lock().push(out);
+    }
+}
+
+impl Default for ChromeTrace {
+    fn default() -> ChromeTrace {
+        ChromeTrace::new()
+    }
+}
+
+fn label(id: Option<TaskId>, name: Option<&'static str>) -> String {
+    Label { id, name }.to_string()
+}
+
+impl SchedulerObserver for ChromeTrace {
+    fn on_schedule(&self, event: &ScheduleEvent) {
+        let mut args = String::new();
+        Object::new(&mut args)
+            .opt_num("deadline_ns", event.deadline.map(json::nanos))
+            .bool("periodic", event.periodic)
+            .finish();
+        self.instant(CALLERS, &label(event.task, event.name), args);
+    }
+
+    fn on_fire(&self, event: &FireEvent) {
+        let mut args = String::new();
+        Object::new(&mut args)
+            .num(
+                "lateness_ns",
+                json::nanos(event.actual.saturating_sub(event.expected)),
+            )
+            .finish();
+        self.instant(TIMEKEEPER, &label(Some(event.task), event.name), args);
+    }
+
+    /* A complete event spanning the run */
+    fn on_execute(&self, event: &ExecuteEvent) {
+        let end = Instant::now();
+        let start = end.checked_sub(event.duration).unwrap_or(end);
+        let mut args = String::new();
+        Object::new(&mut args)
+            .bool("panicked", event.panicked)
+            .finish();
+
+        let mut out = String::new();
+        Object::new(&mut out)
+            .str("name", &label(event.task, event.name))
+            .str("ph", "X")
+            .num("ts", self.micros(start))
+            .num("dur", event.duration.as_nanos() as f64 / 1000.0)
+            .num("pid", 1)
+            .num("tid", WORKER)
+            .raw("args", &args)
+            .finish();
+        self.lock().push(out);
+    }
+
+    fn on_cancel(&self, event: &CancelEvent) {
+        let name = format!("cancel {}", label(Some(event.task), event.name));
+        self.instant(TIMEKEEPER, &name, String::from("{}"));
+    }
+}
@@ -0,0 +1,304 @@
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Result, SchedError};
+use crate::power::PowerMode;
+use crate::sync::{Condvar, Mutex};
+
+/* Below this much remaining time sleep_until spins instead of sleeping */
+const SPIN_THRESHOLD: Duration = Duration::from_micros(50);
+
+/// Time source used by the timekeeper.
+///
+/// Times are durations since an epoch of the clock's choosing, deadlines
+/// handed back to the clock are always derived from its own `now()`.
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> Duration;
+
+    /// Like `now`, but reports a clock that cannot be read instead of
+    /// making something up. Used when accepting new timeouts.
+    fn try_now(&self) -> Result<Duration> {
+        Ok(self.now())
+    }
+
+    /// Block the calling thread until the clock reaches `deadline`, as
+    /// closely as `mode` asks for.
+    fn sleep_until(&self, deadline: Duration, mode: PowerMode);
+
+    /// Whether time only moves when the clock is advanced by hand. The
+    /// timekeeper then never waits on real time and relies on `on_advance`.
+    fn is_manual(&self) -> bool {
+        false
+    }
+
+    /// Register a callback to run whenever a manual clock is advanced.
+    fn on_advance(&self, _wake: Wake) {}
+
+    /// Jump straight to `deadline` instead of waiting for it. Called by the
+    /// timekeeper when nothing is running, returns false if the clock does
+    /// not fast-forward.
+    fn fast_forward(&self, _deadline: Duration) -> bool {
+        false
+    }
+
+    /// Longest stretch of real time to block before reading the clock
+    /// again, for clocks that keep running while sleeping threads don't.
+    fn recheck_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// For clocks that can be stepped, like the wall clock by NTP or by
+    /// hand: `now` together with the time on a clock that never is, from
+    /// any epoch. The timekeeper takes how far `now` moved beyond it as a
+    /// step, which relative delays don't move with.
+    fn now_with_real_time(&self) -> Option<(Duration, Duration)> {
+        None
+    }
+}
+
+/* How far a clock was stepped between two readings */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+    Forward(Duration),
+    Back(Duration),
+}
+
+impl Step {
+    /* Where `time` read before the step reads after it */
+    pub(crate) fn apply(self, time: Duration) -> Duration {
+        match self {
+            Step::Forward(step) => time.saturating_add(step),
+            Step::Back(step) => time.saturating_sub(step),
+        }
+    }
+}
+
+pub type Wake = Box<dyn Fn() + Send + Sync + 'static>;
+
+/// Wall-clock time since the UNIX epoch, the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    /* A wall clock set before the epoch reads as the epoch */
+    fn now(&self) -> Duration {
+        self.try_now().unwrap_or(Duration::ZERO)
+    }
+
+    fn try_now(&self) -> Result<Duration> {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| SchedError::ClockError)
+    }
+
+    fn sleep_until(&self, deadline: Duration, mode: PowerMode) {
+        precise_sleep_until(self, deadline, mode)
+    }
+
+    /* Real time is monotonic since first asked */
+    fn now_with_real_time(&self) -> Option<(Duration, Duration)> {
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        let real = EPOCH.get_or_init(Instant::now).elapsed();
+        Some((self.now(), real))
+    }
+}
+
+/// Monotonic time since the clock was created, unaffected by wall-clock
+/// adjustments.
+#[derive(Clone, Copy, Debug)]
+pub struct MonotonicClock {
+    epoch: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> MonotonicClock {
+        MonotonicClock {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> MonotonicClock {
+        MonotonicClock::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    fn sleep_until(&self, deadline: Duration, mode: PowerMode) {
+        precise_sleep_until(self, deadline, mode)
+    }
+}
+
+/// Linux `CLOCK_BOOTTIME`, which keeps counting while the system is
+/// suspended, so a delay of two hours spans two hours of real time even if
+/// the machine sleeps through part of it.
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BootTimeClock;
+
+/* Sleeps are measured on CLOCK_MONOTONIC, which stops during suspend.
+ * Waking up this often bounds how late a resume is noticed.
+ */
+#[cfg(target_os = "linux")]
+const BOOTTIME_RECHECK: Duration = Duration::from_secs(1);
+
+#[cfg(target_os = "linux")]
+impl Clock for BootTimeClock {
+    fn now(&self) -> Duration {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+
+        let ret = unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) };
+        assert_eq!(ret, 0, "clock_gettime(CLOCK_BOOTTIME) failed");
+
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+
+    fn sleep_until(&self, deadline: Duration, mode: PowerMode) {
+        precise_sleep_until(self, deadline, mode)
+    }
+
+    fn recheck_interval(&self) -> Option<Duration> {
+        Some(BOOTTIME_RECHECK)
+    }
+}
+
+/* Sleep most of the way and spin the last stretch, thread::sleep alone
+ * tends to overshoot by tens of microseconds. Efficient takes the
+ * overshoot over keeping a core busy.
+ */
+fn precise_sleep_until(clock: &dyn Clock, deadline: Duration, mode: PowerMode) {
+    let chunk = clock.recheck_interval().unwrap_or(Duration::MAX);
+
+    loop {
+        let remaining = deadline.saturating_sub(clock.now());
+
+        if remaining == Duration::ZERO {
+            return;
+        } else if mode == PowerMode::Efficient {
+            thread::sleep(remaining.min(chunk));
+        } else if remaining > SPIN_THRESHOLD {
+            thread::sleep((remaining - SPIN_THRESHOLD).min(chunk));
+        } else {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Manually driven clock for tests, time only passes on `advance`.
+///
+/// Clones share the same time, keep one around to drive a scheduler built
+/// with another.
+#[derive(Clone, Default)]
+pub struct MockClock {
+    inner: Arc<MockInner>,
+}
+
+#[derive(Default)]
+struct MockInner {
+    auto_advance: bool,
+    now: Mutex<Duration>,
+    /* What `advance` added up to, `set` leaves it alone */
+    real: Mutex<Duration>,
+    advanced: Condvar,
+    wakers: Mutex<Vec<Wake>>,
+}
+
+impl MockClock {
+    pub fn new() -> MockClock {
+        MockClock::default()
+    }
+
+    /// Virtual time that also jumps to the next deadline by itself whenever
+    /// the scheduler has nothing left to run, so simulated hours of timers
+    /// complete as fast as the work itself allows.
+    pub fn auto_advance() -> MockClock {
+        MockClock {
+            inner: Arc::new(MockInner {
+                auto_advance: true,
+                ..MockInner::default()
+            }),
+        }
+    }
+
+    pub fn advance(&self, step: Duration) {
+        let mut now = self.inner.now.lock().unwrap();
+        *now = now.saturating_add(step);
+        let mut real = self.inner.real.lock().unwrap();
+        *real = real.saturating_add(step);
+        drop((now, real));
+        self.inner.advanced.notify_all();
+
+        for wake in self.inner.wakers.lock().unwrap().iter() {
+            wake();
+        }
+    }
+
+    /// Step the clock to `now`, backwards too, the way a wall clock is set.
+    /// No time passes, so relative delays don't move with it, and like a
+    /// wall clock nobody is told: the scheduler sees it on the next
+    /// `advance`.
+    pub fn set(&self, now: Duration) {
+        *self.inner.now.lock().unwrap() = now;
+        self.inner.advanced.notify_all();
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.inner.now.lock().unwrap()
+    }
+
+    fn sleep_until(&self, deadline: Duration, _mode: PowerMode) {
+        let mut now = self.inner.now.lock().unwrap();
+        while *now < deadline {
+            now = self.inner.advanced.wait(now).unwrap();
+        }
+    }
+
+    fn is_manual(&self) -> bool {
+        true
+    }
+
+    /* Locks now before real like advance, never seeing half a move */
+    fn now_with_real_time(&self) -> Option<(Duration, Duration)> {
+        let now = self.inner.now.lock().unwrap();
+        Some((*now, *self.inner.real.lock().unwrap()))
+    }
+
+    fn on_advance(&self, wake: Wake) {
+        self.inner.wakers.lock().unwrap().push(wake);
+    }
+
+    fn fast_forward(&self, deadline: Duration) -> bool {
+        if !self.inner.auto_advance {
+            return false;
+        }
+
+        let mut now = self.inner.now.lock().unwrap();
+        if *now < deadline {
+            let mut real = self.inner.real.lock().unwrap();
+            *real = real.saturating_add(deadline - *now);
+            *now = deadline;
+        }
+        self.inner.advanced.notify_all();
+
+        true
+    }
+}
+
+impl std::fmt::Debug for MockClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockClock")
+            .field("now", &self.now())
+            .finish()
+    }
+}
@@ -0,0 +1,161 @@
+/* Terminal dashboard in the spirit of tokio-console: stats, health, the
+ * soonest pending timers and recent lateness, redrawn a few times a
+ * second from the same snapshots the public API hands out.
+ */
+use std::io;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::health::Liveness;
+use crate::scheduler::Scheduler;
+
+const REFRESH: Duration = Duration::from_millis(250);
+
+impl Scheduler {
+    /// Take over the terminal with a live dashboard of this scheduler
+    /// until `q` or Esc is pressed. Needs the `console` feature.
+    pub fn console(&self) -> io::Result<()> {
+        let mut terminal = ratatui::init();
+        let result = run(self, &mut terminal);
+        ratatui::restore();
+        result
+    }
+}
+
+fn run(scheduler: &Scheduler, terminal: &mut DefaultTerminal) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(scheduler, frame))?;
+
+        if !event::poll(REFRESH)? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press
+                && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn draw(scheduler: &Scheduler, frame: &mut Frame) {
+    let [summary, utilization, pending, lateness] = Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Length(3),
+        Constraint::Min(5),
+        Constraint::Length(6),
+    ])
+    .areas(frame.area());
+
+    draw_summary(scheduler, frame, summary);
+
+    let stats = scheduler.stats();
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title(" worker utilization "))
+            .gauge_style(Style::default().fg(Color::Cyan))
+            .ratio(stats.worker_utilization.clamp(0.0, 1.0)),
+        utilization,
+    );
+
+    draw_pending(scheduler, frame, pending);
+    draw_lateness(scheduler, frame, lateness);
+}
+
+fn draw_summary(scheduler: &Scheduler, frame: &mut Frame, area: Rect) {
+    let stats = scheduler.stats();
+    let health = scheduler.health();
+    let title = if scheduler.is_shut_down() {
+        " event_scheduler (shut down) - q to quit "
+    } else {
+        " event_scheduler - q to quit "
+    };
+
+    let lines = vec![
+        Line::from(format!(
+            "pending {}  (peak {})    queued {}  (peak {})",
+            stats.pending, stats.peak_pending, stats.queued, stats.peak_queued
+        )),
+        Line::from(format!(
+            "fired {}    cancelled {}    slow {}    missed {}",
+            stats.fired, stats.cancelled, stats.slow_tasks, stats.missed_deadlines
+        )),
+        Line::from(vec![
+            "timekeeper ".into(),
+            liveness(health.timekeeper),
+            "    worker ".into(),
+            liveness(health.worker),
+        ]),
+    ];
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title)),
+        area,
+    );
+}
+
+fn liveness(liveness: Liveness) -> ratatui::text::Span<'static> {
+    match liveness {
+        Liveness::Idle => "idle".green(),
+        Liveness::Busy { elapsed } => format!("busy {:?}", elapsed).yellow(),
+        Liveness::Stalled { elapsed } => format!("STALLED {:?}", elapsed).red().bold(),
+        Liveness::Dead => "DEAD".red().bold(),
+    }
+}
+
+fn draw_pending(scheduler: &Scheduler, frame: &mut Frame, area: Rect) {
+    let pending = scheduler.pending();
+    let shown = area.height.saturating_sub(3) as usize;
+    let rows = pending.iter().take(shown).map(|p| {
+        Row::new(vec![
+            p.id.to_string(),
+            p.name.unwrap_or("-").to_string(),
+            format!("{:?}", p.remaining),
+            if p.periodic { "periodic" } else { "once" }.to_string(),
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(10),
+            Constraint::Min(16),
+            Constraint::Length(20),
+            Constraint::Length(10),
+        ],
+    )
+    .header(Row::new(vec!["id", "name", "due in", "kind"]).bold())
+    .block(Block::bordered().title(format!(" next deadlines ({} pending) ", pending.len())));
+
+    frame.render_widget(table, area);
+}
+
+fn draw_lateness(scheduler: &Scheduler, frame: &mut Frame, area: Rect) {
+    let latency = scheduler.latency();
+    let width = area.width.saturating_sub(2) as usize;
+    let samples = latency.samples();
+    let recent: Vec<u64> = samples[samples.len().saturating_sub(width)..]
+        .iter()
+        .map(|s| s.lateness().as_micros() as u64)
+        .collect();
+
+    let title = match (latency.p99(), latency.max()) {
+        (Some(p99), Some(max)) => format!(" lateness (p99 {:?}, max {:?}) ", p99, max),
+        _ => " lateness ".to_string(),
+    };
+
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(title))
+            .style(Style::default().fg(Color::Magenta))
+            .data(&recent),
+        area,
+    );
+}
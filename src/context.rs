@@ -0,0 +1,43 @@
+/* With the `otel` feature, the OpenTelemetry context of whoever scheduled
+ * a task, attached again around each of its runs so spans started inside
+ * it are parented to the scheduling request. Tracing spans need no help,
+ * the task span already takes the caller's current span as parent.
+ */
+use crate::Work;
+
+#[derive(Clone)]
+pub(crate) struct TaskContext {
+    #[cfg(feature = "otel")]
+    context: opentelemetry::Context,
+}
+
+#[cfg(feature = "otel")]
+impl TaskContext {
+    /* On the scheduling thread */
+    pub(crate) fn capture() -> TaskContext {
+        TaskContext {
+            context: opentelemetry::Context::current(),
+        }
+    }
+
+    pub(crate) fn attach(&self, mut work: Work) -> Work {
+        let context = self.context.clone();
+        Box::new(move || {
+            let _attached = context.clone().attach();
+            work();
+        })
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+impl TaskContext {
+    #[inline(always)]
+    pub(crate) fn capture() -> TaskContext {
+        TaskContext {}
+    }
+
+    #[inline(always)]
+    pub(crate) fn attach(&self, work: Work) -> Work {
+        work
+    }
+}
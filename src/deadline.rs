@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::timeout::Label;
+use crate::TaskId;
+
+/// A timeout that fired later than the tolerated threshold.
+#[derive(Clone, Copy, Debug)]
+pub struct DeadlineMiss {
+    pub task: TaskId,
+    /// Name it was scheduled under, see `Scheduler::named`.
+    pub name: Option<&'static str>,
+    /// Deadline the task was due at, on the scheduler's clock.
+    pub expected: Duration,
+    /// When it was actually handed to the worker.
+    pub actual: Duration,
+    pub lateness: Duration,
+}
+
+#[derive(Clone)]
+pub(crate) struct MissHook {
+    pub(crate) threshold: Duration,
+    pub(crate) callback: Arc<dyn Fn(&DeadlineMiss) + Send + Sync + 'static>,
+}
+
+impl MissHook {
+    pub(crate) fn check(
+        &self,
+        task: TaskId,
+        name: Option<&'static str>,
+        expected: Duration,
+        actual: Duration,
+    ) {
+        let lateness = actual.saturating_sub(expected);
+        if lateness <= self.threshold {
+            return;
+        }
+
+        let label = Label {
+            id: Some(task),
+            name,
+        };
+        warn!("{} missed its deadline by {:?}", label, lateness);
+        (self.callback)(&DeadlineMiss {
+            task,
+            name,
+            expected,
+            actual,
+            lateness,
+        });
+    }
+}
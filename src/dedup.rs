@@ -0,0 +1,68 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::sync::{Mutex, MutexGuard};
+use crate::TaskId;
+
+/// What `schedule_delayed_keyed` does when a timeout with the same key is
+/// still pending.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dedup {
+    /// Leave the pending timeout alone and drop the new one.
+    KeepExisting,
+    /// Cancel the pending timeout in favour of the new one.
+    ReplaceExisting,
+    /// Keep whichever of the two is due first.
+    KeepEarliest,
+}
+
+/* Pending keyed timeouts, released by the timekeeper once they fire or
+ * are cancelled, before they stop counting as pending. A key found under
+ * the lock is thus still counted, and a replaced timeout can hand its
+ * count over to the one replacing it, see Scheduler::submit_keyed.
+ */
+#[derive(Default)]
+pub(crate) struct Keys {
+    inner: Mutex<KeysInner>,
+}
+
+#[derive(Default)]
+pub(crate) struct KeysInner {
+    by_key: HashMap<String, (TaskId, Duration)>,
+    by_id: HashMap<TaskId, String>,
+    handed_over: HashSet<TaskId>,
+}
+
+impl Keys {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, KeysInner> {
+        self.inner.lock().unwrap()
+    }
+
+    /* Whether `id` still counts as pending, not once handed over */
+    pub(crate) fn release(&self, id: TaskId) -> bool {
+        let mut inner = self.lock();
+        if let Some(key) = inner.by_id.remove(&id) {
+            inner.by_key.remove(&key);
+        }
+        !inner.handed_over.remove(&id)
+    }
+}
+
+impl KeysInner {
+    /* Id and deadline of the timeout pending under `key` */
+    pub(crate) fn get(&self, key: &str) -> Option<(TaskId, Duration)> {
+        self.by_key.get(key).copied()
+    }
+
+    /* `id` no longer counts as pending, someone else uncounted it */
+    pub(crate) fn hand_over(&mut self, id: TaskId) {
+        self.handed_over.insert(id);
+    }
+
+    pub(crate) fn insert(&mut self, key: String, id: TaskId, deadline: Duration) {
+        if let Some((old, _)) = self.by_key.insert(key.clone(), (id, deadline)) {
+            self.by_id.remove(&old);
+        }
+        self.by_id.insert(id, key);
+    }
+}
@@ -0,0 +1,336 @@
+/* The delta list at the heart of the timekeeper, usable without std.
+ *
+ * Entries are kept in deadline order with each delay relative to the entry
+ * before it, so time passing only ever touches the front. TimerQueue
+ * drives it from the outside: no threads, the caller reads its own clock
+ * and calls tick(now).
+ */
+
+#[cfg(feature = "nightly")]
+use alloc::collections::LinkedList;
+use alloc::collections::TryReserveError;
+#[cfg(not(feature = "nightly"))]
+use alloc::collections::VecDeque;
+use core::time::Duration;
+
+use crate::TaskId;
+
+/* Nightly builds splice in place with linked list cursors, stable ones
+ * shift a ring buffer.
+ */
+#[cfg(feature = "nightly")]
+pub(crate) type DeltaList<T> = LinkedList<T>;
+#[cfg(not(feature = "nightly"))]
+pub(crate) type DeltaList<T> = VecDeque<T>;
+
+/* Anything that sits in a delta list. The deadline is absolute, on the
+ * same clock as the time the list is relative to. The sequence number
+ * orders entries with equal deadlines, lowest first, and is handed out in
+ * submission order.
+ */
+pub(crate) trait Delayed {
+    fn delay(&self) -> Duration;
+    fn delay_mut(&mut self) -> &mut Duration;
+    fn deadline(&self) -> Duration;
+    fn seq(&self) -> u64;
+
+    /* Where the entry goes in the list, smallest first. By delay alone
+     * every expired entry would tie, and one arriving late would fire
+     * after those due after it.
+     */
+    fn order(&self) -> (Duration, u64) {
+        (self.deadline(), self.seq())
+    }
+}
+
+#[cfg(feature = "nightly")]
+pub(crate) fn delta_insert<T: Delayed>(list: &mut DeltaList<T>, mut new: T) {
+    let mut list_cursor = list.cursor_front_mut();
+
+    while let Some(t) = list_cursor.current() {
+        if t.order() > new.order() {
+            *t.delay_mut() -= new.delay();
+            list_cursor.insert_before(new);
+            return;
+        }
+
+        *new.delay_mut() -= t.delay();
+
+        list_cursor.move_next();
+    }
+
+    /* Past the last entry, insert_after would wrap to the front */
+    list.push_back(new);
+}
+
+#[cfg(not(feature = "nightly"))]
+pub(crate) fn delta_insert<T: Delayed>(list: &mut DeltaList<T>, mut new: T) {
+    let mut i = 0;
+
+    while let Some(t) = list.get_mut(i) {
+        if t.order() > new.order() {
+            *t.delay_mut() -= new.delay();
+            list.insert(i, new);
+            return;
+        }
+
+        *new.delay_mut() -= t.delay();
+
+        i += 1;
+    }
+
+    list.push_back(new);
+}
+
+/* Make room for `additional` more entries up front. Linked list nodes are
+ * allocated one by one on insert, so there is nothing to reserve.
+ */
+#[cfg(feature = "nightly")]
+pub(crate) fn delta_try_reserve<T>(
+    _list: &mut DeltaList<T>,
+    _additional: usize,
+) -> Result<(), TryReserveError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "nightly"))]
+pub(crate) fn delta_try_reserve<T>(
+    list: &mut DeltaList<T>,
+    additional: usize,
+) -> Result<(), TryReserveError> {
+    list.try_reserve(additional)
+}
+
+#[cfg(feature = "nightly")]
+pub(crate) fn delta_remove<T, F>(list: &mut DeltaList<T>, mut matches: F) -> Option<T>
+where
+    T: Delayed,
+    F: FnMut(&T) -> bool,
+{
+    let mut list_cursor = list.cursor_front_mut();
+
+    while let Some(t) = list_cursor.current() {
+        if matches(t) {
+            let removed = list_cursor.remove_current().unwrap();
+
+            /* The next entry was relative to the removed one */
+            if let Some(next) = list_cursor.current() {
+                *next.delay_mut() += removed.delay();
+            }
+
+            return Some(removed);
+        }
+
+        list_cursor.move_next();
+    }
+
+    None
+}
+
+#[cfg(not(feature = "nightly"))]
+pub(crate) fn delta_remove<T, F>(list: &mut DeltaList<T>, matches: F) -> Option<T>
+where
+    T: Delayed,
+    F: FnMut(&T) -> bool,
+{
+    let i = list.iter().position(matches)?;
+    let removed = list.remove(i)?;
+
+    /* The next entry was relative to the removed one */
+    if let Some(next) = list.get_mut(i) {
+        *next.delay_mut() += removed.delay();
+    }
+
+    Some(removed)
+}
+
+/* Account for time spent past the front deadline, expiring following
+ * entries as needed.
+ */
+pub(crate) fn delta_elapse<T: Delayed>(list: &mut DeltaList<T>, mut elapsed: Duration) {
+    for t in list.iter_mut() {
+        if elapsed == Duration::ZERO {
+            break;
+        }
+
+        let step = t.delay().min(elapsed);
+        *t.delay_mut() -= step;
+        elapsed -= step;
+    }
+}
+
+/* The running sum of delays up to each entry must put it at its deadline,
+ * or at the front of the list if that has passed. Checked after every
+ * insert and remove with the debug-invariants feature, a no-op otherwise.
+ */
+#[cfg(feature = "debug-invariants")]
+pub(crate) fn delta_check<T: Delayed>(list: &DeltaList<T>, base: Duration) {
+    use alloc::string::String;
+    use core::fmt::Write;
+
+    let mut offset = Duration::ZERO;
+    let mut broken = None;
+    let mut dump = String::new();
+
+    for (i, t) in list.iter().enumerate() {
+        offset += t.delay();
+        if broken.is_none() && offset != t.deadline().saturating_sub(base) {
+            broken = Some(i);
+        }
+
+        let _ = writeln!(
+            dump,
+            "  [{}] delay {:?} offset {:?} deadline {:?}",
+            i,
+            t.delay(),
+            offset,
+            t.deadline()
+        );
+    }
+
+    if let Some(i) = broken {
+        panic!(
+            "delta list out of order at entry {}, relative to {:?}:\n{}",
+            i, base, dump
+        );
+    }
+}
+
+#[cfg(not(feature = "debug-invariants"))]
+#[inline(always)]
+pub(crate) fn delta_check<T: Delayed>(_list: &DeltaList<T>, _base: Duration) {}
+
+struct Entry<T> {
+    id: TaskId,
+    delay: Duration,
+    deadline: Duration,
+    item: T,
+}
+
+impl<T> Delayed for Entry<T> {
+    fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    fn delay_mut(&mut self) -> &mut Duration {
+        &mut self.delay
+    }
+
+    fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    fn seq(&self) -> u64 {
+        self.id.as_u64()
+    }
+}
+
+/// Items waiting for a deadline, driven by explicit `tick`s. The same
+/// ordering the timekeeper keeps its timeouts in, for other event loops.
+///
+/// Times are durations on whatever clock the caller reads, as long as it
+/// is the same one for every call. Items due at the same time expire in
+/// the order they were inserted.
+pub struct TimerQueue<T> {
+    list: DeltaList<Entry<T>>,
+    /* Where the front of the list is relative to */
+    now: Duration,
+    next_id: u64,
+}
+
+impl<T> TimerQueue<T> {
+    pub fn new(now: Duration) -> TimerQueue<T> {
+        TimerQueue {
+            list: DeltaList::new(),
+            now,
+            next_id: 0,
+        }
+    }
+
+    /// Queue `item` to expire `delay` after the last tick.
+    pub fn insert(&mut self, delay: Duration, item: T) -> TaskId {
+        let id = TaskId::from_u64(self.next_id);
+        self.next_id += 1;
+
+        let deadline = self.now.saturating_add(delay);
+        let delay = deadline - self.now;
+        delta_insert(
+            &mut self.list,
+            Entry {
+                id,
+                delay,
+                deadline,
+                item,
+            },
+        );
+        delta_check(&self.list, self.now);
+        id
+    }
+
+    /// Like `insert`, but fails instead of aborting when the queue can't
+    /// grow. With the `nightly` store the allocation is not fallible.
+    pub fn try_insert(&mut self, delay: Duration, item: T) -> Result<TaskId, TryReserveError> {
+        delta_try_reserve(&mut self.list, 1)?;
+        Ok(self.insert(delay, item))
+    }
+
+    /// Take `id` out of the queue, if it has not expired yet.
+    pub fn remove(&mut self, id: TaskId) -> Option<T> {
+        let removed = delta_remove(&mut self.list, |e| e.id == id);
+        delta_check(&self.list, self.now);
+        removed.map(|e| e.item)
+    }
+
+    /// When the earliest item expires, for the caller to arm a timer with.
+    /// May be before the last tick if that left expired items behind.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.list.front().map(Delayed::deadline)
+    }
+
+    /// The earliest item and its deadline, without taking it out.
+    pub fn peek(&self) -> Option<(TaskId, Duration, &T)> {
+        self.list.front().map(|e| (e.id, e.deadline, &e.item))
+    }
+
+    /// Move time forward to `now` and take the earliest item if it has
+    /// expired. Call until `None` to drain everything due.
+    pub fn pop_expired(&mut self, now: Duration) -> Option<T> {
+        self.tick(now).next()
+    }
+
+    /// Move time forward to `now` and return every item that expired, in
+    /// deadline order. Times earlier than the last tick are ignored.
+    pub fn tick(&mut self, now: Duration) -> Expired<'_, T> {
+        let elapsed = now.saturating_sub(self.now);
+        self.now = self.now.max(now);
+        delta_elapse(&mut self.list, elapsed);
+
+        Expired { queue: self }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+}
+
+/// Items expired by a `TimerQueue::tick`. Whatever is not iterated stays
+/// at the front of the queue, due immediately.
+pub struct Expired<'a, T> {
+    queue: &'a mut TimerQueue<T>,
+}
+
+impl<T> Iterator for Expired<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.queue.list.front()?.delay > Duration::ZERO {
+            return None;
+        }
+
+        self.queue.list.pop_front().map(|e| e.item)
+    }
+}
@@ -0,0 +1,127 @@
+/* Timers for an event loop the caller owns. No threads: the loop passes
+ * next_timeout to mio's poll and runs process_expired after it returns.
+ * Timers scheduled from another thread that come due before the loop would
+ * have woken wake its poll through a mio Waker registered under the
+ * caller's token.
+ */
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use mio::{Registry, Token, Waker};
+
+use crate::delta::TimerQueue;
+use crate::TaskId;
+
+type Callback = Box<dyn FnOnce() + Send + 'static>;
+
+/// Timers driven from a mio `Poll` loop instead of a timekeeper thread.
+///
+/// Poll with `next_timeout` as the timeout, then call `process_expired`,
+/// which runs whatever is due on the loop's thread. Events for the token
+/// given to `new` only mean the timers changed, there is nothing to read.
+/// Clones share the timers, for other threads to schedule from.
+#[derive(Clone)]
+pub struct TimerDriver {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    timers: Mutex<TimerQueue<Callback>>,
+    waker: Waker,
+    token: Token,
+    started: Instant,
+}
+
+impl TimerDriver {
+    /// Register the driver's wakeup with `registry` under `token`.
+    pub fn new(registry: &Registry, token: Token) -> io::Result<TimerDriver> {
+        Ok(TimerDriver {
+            inner: Arc::new(Inner {
+                timers: Mutex::new(TimerQueue::new(Duration::ZERO)),
+                waker: Waker::new(registry, token)?,
+                token,
+                started: Instant::now(),
+            }),
+        })
+    }
+
+    pub fn token(&self) -> Token {
+        self.inner.token
+    }
+
+    /// Run `work` from `process_expired` once `delay` has passed.
+    pub fn schedule_delayed<F>(&self, delay: Duration, work: F) -> TaskId
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let now = self.elapsed();
+        let mut timers = self.lock();
+        /* Inserts count from the last tick, bring it up to now. What is
+         * due stays queued for process_expired.
+         */
+        let _ = timers.tick(now);
+        let earliest = timers.next_deadline();
+        let id = timers.insert(delay, Box::new(work));
+        drop(timers);
+
+        /* The loop may be asleep past the new deadline */
+        if earliest.is_none_or(|earliest| now.saturating_add(delay) < earliest) {
+            if let Err(e) = self.inner.waker.wake() {
+                warn!("failed to wake the event loop: {}", e);
+            }
+        }
+        id
+    }
+
+    /// Take `id` out before it runs, false if it already ran or was
+    /// cancelled.
+    pub fn cancel(&self, id: TaskId) -> bool {
+        self.lock().remove(id).is_some()
+    }
+
+    /// How long the loop may block in `poll` before a timer is due, None
+    /// with no timers.
+    pub fn next_timeout(&self) -> Option<Duration> {
+        let deadline = self.lock().next_deadline()?;
+        Some(deadline.saturating_sub(self.elapsed()))
+    }
+
+    /// Run every timer due by now, in deadline order. Returns how many ran.
+    pub fn process_expired(&self) -> usize {
+        let mut ran = 0;
+        loop {
+            /* Not held while the work runs, it may schedule more */
+            let Some(work) = self.lock().pop_expired(self.elapsed()) else {
+                return ran;
+            };
+            work();
+            ran += 1;
+        }
+    }
+
+    /// Timers waiting to run.
+    pub fn pending(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.inner.started.elapsed()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TimerQueue<Callback>> {
+        self.inner
+            .timers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl std::fmt::Debug for TimerDriver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimerDriver")
+            .field("token", &self.inner.token)
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
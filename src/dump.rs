@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use crate::backend::Backend;
+use crate::json::{self, Object};
+use crate::pending::PendingInfo;
+use crate::stats::Stats;
+use crate::worker::PanicPolicy;
+
+/// Everything a scheduler knows about itself at one point, for bug reports
+/// and support tooling. See `Scheduler::dump_state`.
+#[derive(Clone, Debug)]
+pub struct StateDump {
+    pub config: Config,
+    pub stats: Stats,
+    pub worker: WorkerState,
+    /// Soonest first.
+    pub pending: Vec<PendingInfo>,
+    pub shut_down: bool,
+}
+
+/// How the scheduler was built.
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    pub resolution: Duration,
+    pub compensate_overhead: bool,
+    pub calibrate: bool,
+    pub backend: Backend,
+    pub manual_clock: bool,
+    pub latency_history: usize,
+    pub max_pending: Option<usize>,
+    pub fallible_alloc: bool,
+    pub panic_policy: PanicPolicy,
+    pub slow_task_threshold: Option<Duration>,
+    pub lateness_budget: Option<Duration>,
+    pub watchdog: Duration,
+    /// What the random number generator was seeded with, see
+    /// `Builder::seed`.
+    pub seed: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WorkerState {
+    /// Running a task right now.
+    pub busy: bool,
+    /// Work handed over and not finished, the running task included.
+    pub queued: usize,
+    /// Worker threads that died and were replaced.
+    pub restarts: usize,
+}
+
+impl StateDump {
+    /// The dump as a JSON object. Durations are integer nanoseconds, in
+    /// fields ending in `_ns`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        Object::new(&mut out)
+            .raw("config", &config_json(&self.config))
+            .raw("stats", &stats_json(&self.stats))
+            .raw("worker", &worker_json(&self.worker))
+            .raw(
+                "pending",
+                &json::array(self.pending.iter().map(pending_json)),
+            )
+            .bool("shut_down", self.shut_down)
+            .finish();
+        out
+    }
+}
+
+fn config_json(config: &Config) -> String {
+    let mut out = String::new();
+    Object::new(&mut out)
+        .num("resolution_ns", json::nanos(config.resolution))
+        .bool("compensate_overhead", config.compensate_overhead)
+        .bool("calibrate", config.calibrate)
+        .str("backend", &format!("{:?}", config.backend))
+        .bool("manual_clock", config.manual_clock)
+        .num("latency_history", config.latency_history)
+        .opt_num("max_pending", config.max_pending)
+        .bool("fallible_alloc", config.fallible_alloc)
+        .str("panic_policy", &format!("{:?}", config.panic_policy))
+        .opt_num(
+            "slow_task_threshold_ns",
+            config.slow_task_threshold.map(json::nanos),
+        )
+        .opt_num(
+            "lateness_budget_ns",
+            config.lateness_budget.map(json::nanos),
+        )
+        .num("watchdog_ns", json::nanos(config.watchdog))
+        .num("seed", config.seed)
+        .finish();
+    out
+}
+
+fn worker_json(worker: &WorkerState) -> String {
+    let mut out = String::new();
+    Object::new(&mut out)
+        .bool("busy", worker.busy)
+        .num("queued", worker.queued)
+        .num("restarts", worker.restarts)
+        .finish();
+    out
+}
+
+fn stats_json(stats: &Stats) -> String {
+    let mut out = String::new();
+    Object::new(&mut out)
+        .opt_num(
+            "calibrated_overshoot_ns",
+            stats.calibrated_overshoot.map(json::nanos),
+        )
+        .num(
+            "compensation_margin_ns",
+            json::nanos(stats.compensation_margin),
+        )
+        .num("periodic_drift_ns", json::nanos(stats.periodic_drift))
+        .num("pending", stats.pending)
+        .num("queued", stats.queued)
+        .num("peak_pending", stats.peak_pending)
+        .num("peak_queued", stats.peak_queued)
+        .num("recent_peak_pending", stats.recent_peak_pending)
+        .num("recent_peak_queued", stats.recent_peak_queued)
+        .num("fired", stats.fired)
+        .num("cancelled", stats.cancelled)
+        .num("slow_tasks", stats.slow_tasks)
+        .num("missed_deadlines", stats.missed_deadlines)
+        .num("worker_utilization", stats.worker_utilization)
+        .num("mean_lateness_ns", json::nanos(stats.mean_lateness))
+        .num("max_lateness_ns", json::nanos(stats.max_lateness))
+        .finish();
+    out
+}
+
+fn pending_json(pending: &PendingInfo) -> String {
+    let mut out = String::new();
+    Object::new(&mut out)
+        .num("id", pending.id.as_u64())
+        .opt_str("name", pending.name)
+        .num("remaining_ns", json::nanos(pending.remaining))
+        .num("deadline_ns", json::nanos(pending.deadline))
+        .opt_num("delay_ns", pending.delay.map(json::nanos))
+        .bool("periodic", pending.periodic)
+        .finish();
+    out
+}
@@ -0,0 +1,390 @@
+/* Durable timers, jobs that outlive the process. What a job does can't be
+ * stored, so a job is a kind, naming a handler registered under it, and a
+ * payload handed to that handler. Jobs are kept with their due time on the
+ * wall clock, the only clock that means anything after a restart, and are
+ * armed on the scheduler as `schedule_at` timeouts once a handler for
+ * their kind is registered. Loaded jobs of a kind nobody handles stay
+ * pending.
+ *
+ * Every change goes to the TimerStore, by default FileStore's write-ahead
+ * journal, before it is acknowledged or takes effect. A job is removed
+ * from the store after its handler returned, one that was running when
+ * the process died runs again after the restart. Jobs found overdue on
+ * opening are fired or skipped as the Missed policy says.
+ */
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::time::{Duration, SystemTime};
+
+use crate::error::SchedError;
+use crate::scheduler::Scheduler;
+use crate::store::{FileStore, TimerStore};
+use crate::TaskId;
+
+/// Refers to a durable job, the same across restarts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(u64);
+
+impl JobId {
+    pub const fn from_u64(id: u64) -> JobId {
+        JobId(id)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    pub(crate) fn successor(self) -> JobId {
+        JobId(self.0 + 1)
+    }
+}
+
+/// A job as kept on disk, see `DurableTimers`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DurableJob {
+    pub id: JobId,
+    /// What handles it, see `DurableTimers::handle`.
+    pub kind: String,
+    pub due: SystemTime,
+    pub payload: Vec<u8>,
+}
+
+/// What becomes of jobs that came due while the process was down, see
+/// `DurableBuilder::missed`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Missed {
+    /// Fire them as soon as their kind has a handler.
+    #[default]
+    Fire,
+    /// Drop them unrun, as if cancelled.
+    Skip,
+    /// Fire those at most this late, skip the others.
+    FireWithin(Duration),
+}
+
+impl Missed {
+    fn skips(self, late: Duration) -> bool {
+        match self {
+            Missed::Fire => false,
+            Missed::Skip => true,
+            Missed::FireWithin(within) => late > within,
+        }
+    }
+}
+
+type Handler = Arc<dyn Fn(&[u8]) + Send + Sync + 'static>;
+
+/// Timers that survive a crash or restart, kept in a `TimerStore`, by
+/// default a journal on disk, and armed on a scheduler.
+///
+/// Open the store with the scheduler, then register a handler for each
+/// kind of job with `handle`, which arms the jobs of that kind still
+/// pending from before. What happens to jobs that came due while the
+/// process was down is up to `DurableBuilder::missed`. A job is only
+/// removed from the store once its handler returned, so one interrupted by
+/// a crash runs again: handlers should be idempotent. Clones share the
+/// jobs.
+#[derive(Clone)]
+pub struct DurableTimers {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    scheduler: Weak<Scheduler>,
+    /* Overdue on opening, fired or skipped */
+    missed: Vec<DurableJob>,
+    state: Mutex<State>,
+}
+
+struct State {
+    store: Box<dyn TimerStore>,
+    jobs: BTreeMap<JobId, Entry>,
+    handlers: HashMap<String, Handler>,
+    next_id: u64,
+}
+
+struct Entry {
+    job: DurableJob,
+    /* On the scheduler, once there's a handler */
+    armed: Option<TaskId>,
+}
+
+/// Opens `DurableTimers` with other than the defaults.
+#[derive(Clone, Debug, Default)]
+pub struct DurableBuilder {
+    missed: Missed,
+}
+
+impl DurableBuilder {
+    pub fn new() -> DurableBuilder {
+        DurableBuilder::default()
+    }
+
+    /// Decide what becomes of jobs found overdue on opening, by default
+    /// they fire.
+    pub fn missed(mut self, missed: Missed) -> DurableBuilder {
+        self.missed = missed;
+        self
+    }
+
+    /// Recover the jobs journaled in `path`, created if missing, for
+    /// `scheduler` to fire, see `FileStore`. Nothing is armed until
+    /// `handle` is called.
+    pub fn open<P: AsRef<Path>>(
+        self,
+        scheduler: &Arc<Scheduler>,
+        path: P,
+    ) -> io::Result<DurableTimers> {
+        self.open_store(scheduler, FileStore::open(path)?)
+    }
+
+    /// Like `open`, with the jobs kept in `store`.
+    pub fn open_store<S: TimerStore>(
+        self,
+        scheduler: &Arc<Scheduler>,
+        mut store: S,
+    ) -> io::Result<DurableTimers> {
+        let now = SystemTime::now();
+        let mut missed = store.scan_due(now)?;
+        missed.sort_by_key(|job| (job.due, job.id));
+        for job in &missed {
+            let late = now.duration_since(job.due).unwrap_or_default();
+            if self.missed.skips(late) {
+                store.remove(job.id)?;
+            }
+        }
+
+        let jobs: BTreeMap<JobId, Entry> = store
+            .snapshot()?
+            .into_iter()
+            .map(|job| (job.id, Entry { job, armed: None }))
+            .collect();
+        /* A store that lost count still mustn't reuse the id of a live job */
+        let next_id = jobs
+            .keys()
+            .map(|id| id.0 + 1)
+            .fold(store.next_id()?.0, u64::max);
+        Ok(DurableTimers {
+            inner: Arc::new(Inner {
+                scheduler: Arc::downgrade(scheduler),
+                missed,
+                state: Mutex::new(State {
+                    store: Box::new(store),
+                    jobs,
+                    handlers: HashMap::new(),
+                    next_id,
+                }),
+            }),
+        })
+    }
+}
+
+impl DurableTimers {
+    /// `DurableBuilder::open` with the defaults.
+    pub fn open<P: AsRef<Path>>(scheduler: &Arc<Scheduler>, path: P) -> io::Result<DurableTimers> {
+        DurableBuilder::new().open(scheduler, path)
+    }
+
+    /// `DurableBuilder::open_store` with the defaults.
+    pub fn open_store<S: TimerStore>(
+        scheduler: &Arc<Scheduler>,
+        store: S,
+    ) -> io::Result<DurableTimers> {
+        DurableBuilder::new().open_store(scheduler, store)
+    }
+
+    pub fn builder() -> DurableBuilder {
+        DurableBuilder::new()
+    }
+
+    /// Run jobs of `kind` with `handler`, on the worker like any other
+    /// task. Arms those already pending, replaces an earlier handler.
+    pub fn handle<F>(&self, kind: &str, handler: F) -> io::Result<()>
+    where
+        F: Fn(&[u8]) + Send + Sync + 'static,
+    {
+        let mut state = self.inner.lock();
+        state.handlers.insert(kind.to_string(), Arc::new(handler));
+        let unarmed: Vec<(JobId, SystemTime)> = state
+            .jobs
+            .values()
+            .filter(|entry| entry.armed.is_none() && entry.job.kind == kind)
+            .map(|entry| (entry.job.id, entry.job.due))
+            .collect();
+        for (id, due) in unarmed {
+            let task = self.inner.arm(id, due)?;
+            if let Some(entry) = state.jobs.get_mut(&id) {
+                entry.armed = Some(task);
+            }
+        }
+        Ok(())
+    }
+
+    /// Have the handler of `kind` run with `payload` at `when`, also if
+    /// the process restarted in between. Returns once the job is
+    /// stored, on an error nothing is. Kinds are words, without
+    /// whitespace.
+    pub fn schedule_at<P: Into<Vec<u8>>>(
+        &self,
+        kind: &str,
+        when: SystemTime,
+        payload: P,
+    ) -> io::Result<JobId> {
+        if kind.is_empty() || kind.contains(char::is_whitespace) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("job kind {:?} is not a word", kind),
+            ));
+        }
+
+        let running = self.inner.scheduler.upgrade();
+        if running.is_none_or(|scheduler| scheduler.is_shut_down()) {
+            return Err(io::Error::other(SchedError::ShutDown));
+        }
+
+        let mut state = self.inner.lock();
+        let id = JobId(state.next_id);
+        let job = DurableJob {
+            id,
+            kind: kind.to_string(),
+            due: when,
+            payload: payload.into(),
+        };
+        state.store.put(&job)?;
+        state.next_id += 1;
+        state.jobs.insert(id, Entry { job, armed: None });
+
+        if !state.handlers.contains_key(kind) {
+            return Ok(id);
+        }
+        match self.inner.arm(id, when) {
+            Ok(task) => {
+                if let Some(entry) = state.jobs.get_mut(&id) {
+                    entry.armed = Some(task);
+                }
+                Ok(id)
+            }
+            /* Failed, so not scheduled after a restart either */
+            Err(e) => {
+                state.jobs.remove(&id);
+                if let Err(e) = state.store.remove(id) {
+                    error!("unarmed durable job {} left stored: {}", id.0, e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// `schedule_at` `delay` from now.
+    pub fn schedule_delayed<P: Into<Vec<u8>>>(
+        &self,
+        kind: &str,
+        delay: Duration,
+        payload: P,
+    ) -> io::Result<JobId> {
+        let when = SystemTime::now().checked_add(delay).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} from now is past the end of time", delay),
+            )
+        })?;
+        self.schedule_at(kind, when, payload)
+    }
+
+    /// Take job `id` out, false if it already ran or was cancelled.
+    pub fn cancel(&self, id: JobId) -> io::Result<bool> {
+        let mut state = self.inner.lock();
+        if !state.jobs.contains_key(&id) {
+            return Ok(false);
+        }
+
+        state.store.remove(id)?;
+        let entry = state.jobs.remove(&id);
+        if let (Some(task), Some(scheduler)) = (
+            entry.and_then(|entry| entry.armed),
+            self.inner.scheduler.upgrade(),
+        ) {
+            let _ = scheduler.cancel(task);
+        }
+        Ok(true)
+    }
+
+    /// The jobs not run yet, soonest first.
+    pub fn pending(&self) -> Vec<DurableJob> {
+        let mut jobs: Vec<DurableJob> = self
+            .inner
+            .lock()
+            .jobs
+            .values()
+            .map(|entry| entry.job.clone())
+            .collect();
+        jobs.sort_by_key(|job| (job.due, job.id));
+        jobs
+    }
+
+    /// The jobs that came due while the process was down, found on
+    /// opening, whether `Missed` had them fire or skipped.
+    pub fn missed(&self) -> &[DurableJob] {
+        &self.inner.missed
+    }
+}
+
+impl std::fmt::Debug for DurableTimers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DurableTimers")
+            .field("pending", &self.inner.lock().jobs.len())
+            .field("missed", &self.inner.missed.len())
+            .finish()
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /* Called with the state locked, the job can't run before it's noted
+     * as armed
+     */
+    fn arm(self: &Arc<Self>, id: JobId, due: SystemTime) -> io::Result<TaskId> {
+        let scheduler = self
+            .scheduler
+            .upgrade()
+            .ok_or_else(|| io::Error::other(SchedError::ShutDown))?;
+        let inner = Arc::downgrade(self);
+        let handle = scheduler
+            .named("durable job")
+            .schedule_at(due, move || {
+                if let Some(inner) = inner.upgrade() {
+                    inner.fire(id);
+                }
+            })
+            .map_err(io::Error::other)?;
+        Ok(handle.id())
+    }
+
+    fn fire(&self, id: JobId) {
+        let (handler, payload) = {
+            let state = self.lock();
+            let Some(entry) = state.jobs.get(&id) else {
+                return;
+            };
+            let Some(handler) = state.handlers.get(&entry.job.kind) else {
+                return;
+            };
+            (handler.clone(), entry.job.payload.clone())
+        };
+
+        handler(&payload);
+
+        let mut state = self.lock();
+        if state.jobs.remove(&id).is_none() {
+            return;
+        }
+        if let Err(e) = state.store.remove(id) {
+            error!("durable job {} ran but will run again: {}", id.0, e);
+        }
+    }
+}
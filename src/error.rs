@@ -0,0 +1,39 @@
+use std::fmt;
+
+/// Why the scheduler could not do what was asked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SchedError {
+    /// The timekeeper or worker is gone, nothing more will run.
+    ShutDown,
+    /// A bounded queue had no room for the submission.
+    QueueFull,
+    /// As many timeouts as `Builder::max_pending` allows are pending.
+    TooManyTimers,
+    /// The clock could not be read, e.g. the wall clock is before its epoch.
+    ClockError,
+    /// The worker thread panicked while running work.
+    WorkerPanicked,
+    /// The timer store could not grow, see `Builder::fallible_alloc`.
+    OutOfMemory,
+    /// The timeout was dropped before its work ran, see `TimeoutHandle`.
+    Cancelled,
+}
+
+impl fmt::Display for SchedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedError::ShutDown => write!(f, "scheduler is shut down"),
+            SchedError::QueueFull => write!(f, "queue is full"),
+            SchedError::TooManyTimers => write!(f, "too many pending timeouts"),
+            SchedError::ClockError => write!(f, "failed to read the clock"),
+            SchedError::WorkerPanicked => write!(f, "worker thread panicked"),
+            SchedError::OutOfMemory => write!(f, "out of memory for timeouts"),
+            SchedError::Cancelled => write!(f, "timeout was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SchedError {}
+
+pub type Result<T> = std::result::Result<T, SchedError>;
@@ -0,0 +1,89 @@
+/* Scheduler::events subscribers, fed by an observer the builder always
+ * installs. Costs a relaxed load per event while nobody is subscribed.
+ * Monitoring, so std primitives, see sync.rs.
+ */
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Mutex, PoisonError};
+
+use crate::observer::{
+    CancelEvent, ExecuteEvent, FireEvent, ScheduleEvent, SchedulerObserver, StartEvent,
+};
+
+/* Events a subscriber may fall behind by before newer ones are dropped */
+const BUFFER: usize = 1024;
+
+/// A stage of a task's life, see `Scheduler::events`.
+#[derive(Clone, Copy, Debug)]
+pub enum TaskEvent {
+    Scheduled(ScheduleEvent),
+    Fired(FireEvent),
+    Started(StartEvent),
+    Finished(ExecuteEvent),
+    Panicked(ExecuteEvent),
+    Cancelled(CancelEvent),
+}
+
+#[derive(Default)]
+pub(crate) struct Broadcast {
+    subscribers: Mutex<Vec<SyncSender<TaskEvent>>>,
+    count: AtomicUsize,
+}
+
+impl Broadcast {
+    pub(crate) fn subscribe(&self) -> Receiver<TaskEvent> {
+        let (sender, receiver) = sync_channel(BUFFER);
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        subscribers.push(sender);
+        self.count.store(subscribers.len(), Ordering::Relaxed);
+        receiver
+    }
+
+    fn send(&self, event: TaskEvent) {
+        if self.count.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        /* A full subscriber misses this one, a gone one is dropped */
+        subscribers.retain(|subscriber| {
+            !matches!(
+                subscriber.try_send(event),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+        self.count.store(subscribers.len(), Ordering::Relaxed);
+    }
+}
+
+impl SchedulerObserver for Broadcast {
+    fn on_schedule(&self, event: &ScheduleEvent) {
+        self.send(TaskEvent::Scheduled(*event));
+    }
+
+    fn on_fire(&self, event: &FireEvent) {
+        self.send(TaskEvent::Fired(*event));
+    }
+
+    fn on_start(&self, event: &StartEvent) {
+        self.send(TaskEvent::Started(*event));
+    }
+
+    fn on_execute(&self, event: &ExecuteEvent) {
+        if event.panicked {
+            self.send(TaskEvent::Panicked(*event));
+        } else {
+            self.send(TaskEvent::Finished(*event));
+        }
+    }
+
+    fn on_cancel(&self, event: &CancelEvent) {
+        self.send(TaskEvent::Cancelled(*event));
+    }
+}
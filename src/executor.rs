@@ -0,0 +1,96 @@
+/* The execution stage as seen from tests. With a RecordingExecutor the
+ * work of every task is dropped where it would have been handed to the
+ * worker, and what would have run is written down instead. The worker
+ * still gets a no-op in its place, so stats and observers add up as usual.
+ * Monitoring, so std primitives, see sync.rs.
+ */
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{TaskId, Work};
+
+/// A task the scheduler would have run, see `RecordingExecutor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Invocation {
+    /// None for immediate work.
+    pub task: Option<TaskId>,
+    pub name: Option<&'static str>,
+    /// Its deadline on the scheduler's clock, for immediate work when it
+    /// was scheduled.
+    pub scheduled_for: Duration,
+    /// When it was handed over to run, on the same clock.
+    pub fired_at: Duration,
+}
+
+/// Records every task instead of running it, see `Builder::executor`.
+/// Clones share the record.
+#[derive(Clone, Default)]
+pub struct RecordingExecutor {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    invocations: Mutex<Vec<Invocation>>,
+    recorded: Condvar,
+}
+
+impl RecordingExecutor {
+    pub fn new() -> RecordingExecutor {
+        RecordingExecutor::default()
+    }
+
+    /// Everything recorded so far, in the order it would have run.
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.lock().clone()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Block until at least `count` invocations are recorded, false if
+    /// `timeout` passes first. Fires are recorded on the scheduler's
+    /// threads, so after moving a manual clock this is how to wait for
+    /// them.
+    pub fn wait_for(&self, count: usize, timeout: Duration) -> bool {
+        let give_up = Instant::now() + timeout;
+        let mut invocations = self.lock();
+        while invocations.len() < count {
+            let left = give_up.saturating_duration_since(Instant::now());
+            if left == Duration::ZERO {
+                return false;
+            }
+            invocations = self
+                .inner
+                .recorded
+                .wait_timeout(invocations, left)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        true
+    }
+
+    /* Take the place of `work`, which is dropped unrun */
+    pub(crate) fn intercept(&self, invocation: Invocation, work: Work) -> Work {
+        drop(work);
+        self.lock().push(invocation);
+        self.inner.recorded.notify_all();
+        Box::new(|| {})
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Invocation>> {
+        self.inner
+            .invocations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl std::fmt::Debug for RecordingExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingExecutor")
+            .field("invocations", &self.lock().len())
+            .finish()
+    }
+}
@@ -0,0 +1,156 @@
+/* Deliberate misbehaviour for chaos tests, with the fault-injection
+ * feature. Every fault is drawn on the timekeeper thread from its fork of
+ * the scheduler's generator, so the same Builder::seed and the same
+ * sequence of timeouts make for the same faults. Without the feature the
+ * injector is a no-op.
+ */
+use std::time::Duration;
+
+#[cfg(feature = "fault-injection")]
+use std::cell::{Cell, RefCell};
+
+#[cfg(feature = "fault-injection")]
+use crate::rng::Rng;
+use crate::Work;
+
+/// Faults for the scheduler to inject, see `Builder::faults`.
+/// Probabilities are between 0 and 1, every fault is off by default.
+/// Which faults hit is decided by `Builder::seed`.
+///
+/// Timing faults only show with real clocks, a manual clock is never
+/// slept on.
+#[cfg(feature = "fault-injection")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Faults {
+    oversleep: (f64, Duration),
+    dropped_wakeup: f64,
+    delayed_dispatch: (f64, Duration),
+    task_panic: f64,
+}
+
+#[cfg(feature = "fault-injection")]
+impl Faults {
+    pub fn new() -> Faults {
+        Faults::default()
+    }
+
+    /// Wake up to `max` past a deadline with probability `p`.
+    pub fn oversleep(mut self, p: f64, max: Duration) -> Faults {
+        self.oversleep = (p, max);
+        self
+    }
+
+    /// With probability `p` miss the wakeup for a timeout arriving while
+    /// the timekeeper waits, so an earlier deadline is only noticed once
+    /// the one it was waiting for passes.
+    pub fn dropped_wakeup(mut self, p: f64) -> Faults {
+        self.dropped_wakeup = p;
+        self
+    }
+
+    /// Stall the timekeeper up to `max` before handing a fired timeout to
+    /// the worker, with probability `p`.
+    pub fn delayed_dispatch(mut self, p: f64, max: Duration) -> Faults {
+        self.delayed_dispatch = (p, max);
+        self
+    }
+
+    /// Replace a fired task's run with a panic with probability `p`. The
+    /// panic is handled as the `PanicPolicy` says.
+    pub fn task_panic(mut self, p: f64) -> Faults {
+        self.task_panic = p;
+        self
+    }
+}
+
+#[cfg(feature = "fault-injection")]
+pub(crate) struct Injector {
+    faults: Faults,
+    rng: RefCell<Rng>,
+    /* A deadline the timekeeper keeps sleeping towards after losing the
+     * wakeup for an earlier one
+     */
+    lost: Cell<Option<Duration>>,
+}
+
+#[cfg(feature = "fault-injection")]
+impl Injector {
+    /* A manual clock is never slept on, timing faults would only confuse
+     * the timekeeper's waits on it
+     */
+    pub(crate) fn new(mut faults: Faults, manual_clock: bool, rng: Rng) -> Injector {
+        if manual_clock {
+            faults.oversleep.0 = 0.0;
+            faults.dropped_wakeup = 0.0;
+        }
+
+        Injector {
+            faults,
+            rng: RefCell::new(rng),
+            lost: Cell::new(None),
+        }
+    }
+
+    fn chance(&self, p: f64) -> bool {
+        p > 0.0 && self.rng.borrow_mut().chance(p)
+    }
+
+    /* When the timekeeper wakes up for `deadline` */
+    pub(crate) fn wake_at(&self, deadline: Duration) -> Duration {
+        let mut wake = deadline.max(self.lost.take().unwrap_or(deadline));
+
+        let (p, max) = self.faults.oversleep;
+        if self.chance(p) {
+            wake = wake.saturating_add(self.rng.borrow_mut().up_to(max));
+        }
+        wake
+    }
+
+    /* A new timeout cut short the wait for `deadline` */
+    pub(crate) fn interrupted(&self, deadline: Duration) {
+        if self.chance(self.faults.dropped_wakeup) {
+            self.lost.set(Some(deadline));
+        }
+    }
+
+    pub(crate) fn dispatch_delay(&self) -> Duration {
+        let (p, max) = self.faults.delayed_dispatch;
+        if self.chance(p) {
+            self.rng.borrow_mut().up_to(max)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    pub(crate) fn sabotage(&self, work: Work) -> Work {
+        if self.chance(self.faults.task_panic) {
+            Box::new(|| panic!("injected task panic"))
+        } else {
+            work
+        }
+    }
+}
+
+#[cfg(not(feature = "fault-injection"))]
+pub(crate) struct Injector;
+
+#[cfg(not(feature = "fault-injection"))]
+impl Injector {
+    #[inline(always)]
+    pub(crate) fn wake_at(&self, deadline: Duration) -> Duration {
+        deadline
+    }
+
+    #[inline(always)]
+    pub(crate) fn interrupted(&self, _deadline: Duration) {}
+
+    #[inline(always)]
+    pub(crate) fn dispatch_delay(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    #[inline(always)]
+    pub(crate) fn sabotage(&self, work: Work) -> Work {
+        work
+    }
+}
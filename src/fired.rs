@@ -0,0 +1,312 @@
+/* Scheduler::fired_stream, the scheduler as a pure timing service. Fired
+ * work is handed to the subscriber where it would have gone to the worker,
+ * the worker gets a no-op in its place like with a RecordingExecutor. With
+ * nobody subscribed it costs a relaxed load per fire.
+ *
+ * On Linux the stream's fd is an eventfd, created when first asked for.
+ * Signalled on every push and on close, drained by whichever take empties
+ * the queue unless the stream has ended, all under the state lock, so it
+ * is readable exactly while there is something to take or the stream has
+ * ended.
+ */
+use std::collections::VecDeque;
+use std::sync::{Arc, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+use crate::backend::eventfd::EventFd;
+use crate::executor::Invocation;
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::{Condvar, Mutex, MutexGuard};
+use crate::{TaskId, Work};
+
+/// When and what fired, see `Scheduler::fired_stream`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FireInfo {
+    /// None for immediate work.
+    pub task: Option<TaskId>,
+    pub name: Option<&'static str>,
+    /// Its deadline on the scheduler's clock, for immediate work when it
+    /// was scheduled.
+    pub scheduled_for: Duration,
+    /// When it fired, on the same clock.
+    pub fired_at: Duration,
+}
+
+/// The work of a fired task, for the subscriber to run wherever it likes.
+/// Dropping it drops the work unrun.
+pub struct FiredTask {
+    work: Work,
+}
+
+impl FiredTask {
+    pub fn run(mut self) {
+        (self.work)()
+    }
+}
+
+impl std::fmt::Debug for FiredTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FiredTask").finish_non_exhaustive()
+    }
+}
+
+/// Fired tasks in the order they fired, see `Scheduler::fired_stream`.
+/// Take them like from a `Receiver`, or await them, with the `stream`
+/// feature as a `Stream`. Either ends once the scheduler shuts down or a
+/// newer stream takes over. Dropping it hands fired tasks back to the
+/// worker, those still waiting in it are dropped unrun.
+///
+/// On Linux it is also a file descriptor for `epoll` and other poll loops,
+/// readable while a task is waiting or the stream has ended. Run the
+/// worker stage from the loop by taking tasks with `try_recv` whenever it
+/// is. Asking for the fd panics if no eventfd can be created.
+pub struct FiredStream {
+    channel: Arc<Channel>,
+}
+
+impl FiredStream {
+    /// Block for the next fired task, None once the stream has ended.
+    pub fn recv(&mut self) -> Option<(FiredTask, FireInfo)> {
+        let mut state = self.channel.lock();
+        loop {
+            if let Some(fired) = self.channel.pop(&mut state) {
+                return Some(fired);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .channel
+                .ready
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// The next fired task if one is waiting, without blocking.
+    pub fn try_recv(&mut self) -> Option<(FiredTask, FireInfo)> {
+        self.channel.pop(&mut self.channel.lock())
+    }
+
+    /// Blocking iterator over the fired tasks, like `Receiver::iter`.
+    pub fn iter(&mut self) -> impl Iterator<Item = (FiredTask, FireInfo)> + '_ {
+        std::iter::from_fn(move || self.recv())
+    }
+
+    /// The next fired task, waiting without blocking the executor.
+    pub async fn next_fired(&mut self) -> Option<(FiredTask, FireInfo)> {
+        std::future::poll_fn(|cx| self.poll_fired(cx)).await
+    }
+
+    fn poll_fired(&mut self, cx: &mut Context<'_>) -> Poll<Option<(FiredTask, FireInfo)>> {
+        let mut state = self.channel.lock();
+        if let Some(fired) = self.channel.pop(&mut state) {
+            return Poll::Ready(Some(fired));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for FiredStream {
+    type Item = (FiredTask, FireInfo);
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<(FiredTask, FireInfo)>> {
+        self.get_mut().poll_fired(cx)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::os::fd::AsFd for FiredStream {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        self.channel.eventfd().as_fd()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::os::fd::AsRawFd for FiredStream {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.channel.eventfd().as_raw_fd()
+    }
+}
+
+impl Drop for FiredStream {
+    fn drop(&mut self) {
+        self.channel.lock().detached = true;
+    }
+}
+
+impl std::fmt::Debug for FiredStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.channel.lock();
+        f.debug_struct("FiredStream")
+            .field("waiting", &state.fired.len())
+            .field("closed", &state.closed)
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct Channel {
+    state: Mutex<State>,
+    ready: Condvar,
+    #[cfg(target_os = "linux")]
+    eventfd: std::sync::OnceLock<EventFd>,
+}
+
+#[derive(Default)]
+struct State {
+    fired: VecDeque<(FiredTask, FireInfo)>,
+    waker: Option<Waker>,
+    /* No more coming */
+    closed: bool,
+    /* The stream is gone, nobody would take more */
+    detached: bool,
+}
+
+impl Channel {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn pop(&self, state: &mut State) -> Option<(FiredTask, FireInfo)> {
+        let fired = state.fired.pop_front()?;
+        #[cfg(target_os = "linux")]
+        if state.fired.is_empty() && !state.closed {
+            if let Some(eventfd) = self.eventfd.get() {
+                eventfd.drain();
+            }
+        }
+        Some(fired)
+    }
+
+    fn push(&self, state: &mut State, fired: (FiredTask, FireInfo)) {
+        state.fired.push_back(fired);
+        self.signal();
+    }
+
+    fn signal(&self) {
+        #[cfg(target_os = "linux")]
+        if let Some(eventfd) = self.eventfd.get() {
+            eventfd.signal();
+        }
+    }
+
+    /* Caught up with what happened before it existed. Created under the
+     * lock, a push can't slip in between the catching up and the others
+     * seeing it.
+     */
+    #[cfg(target_os = "linux")]
+    fn eventfd(&self) -> &EventFd {
+        if let Some(eventfd) = self.eventfd.get() {
+            return eventfd;
+        }
+
+        let state = self.lock();
+        self.eventfd.get_or_init(|| {
+            let eventfd = EventFd::new();
+            if !state.fired.is_empty() || state.closed {
+                eventfd.signal();
+            }
+            eventfd
+        })
+    }
+
+    fn close(&self) {
+        let waker = {
+            let mut state = self.lock();
+            state.closed = true;
+            self.signal();
+            state.waker.take()
+        };
+        self.ready.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/* Shared by the scheduler, which subscribes, and whoever hands work over */
+#[derive(Default)]
+pub(crate) struct Handoff {
+    active: AtomicBool,
+    channel: Mutex<Option<Arc<Channel>>>,
+}
+
+impl Handoff {
+    /* Replaces, and ends, an earlier subscriber */
+    pub(crate) fn subscribe(&self) -> FiredStream {
+        let channel = Arc::new(Channel::default());
+        let previous = self
+            .channel
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(channel.clone());
+        self.active.store(true, Ordering::Relaxed);
+        if let Some(previous) = previous {
+            previous.close();
+        }
+        FiredStream { channel }
+    }
+
+    pub(crate) fn close(&self) {
+        let channel = self
+            .channel
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        self.active.store(false, Ordering::Relaxed);
+        if let Some(channel) = channel {
+            channel.close();
+        }
+    }
+
+    /* Take over `work` if subscribed, leaving a no-op to run instead */
+    pub(crate) fn intercept(&self, invocation: &Invocation, work: Work) -> Work {
+        if !self.active.load(Ordering::Relaxed) {
+            return work;
+        }
+
+        let mut channel = self.channel.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(subscriber) = channel.as_ref() else {
+            return work;
+        };
+        let mut state = subscriber.lock();
+        if state.detached {
+            drop(state);
+            channel.take();
+            self.active.store(false, Ordering::Relaxed);
+            return work;
+        }
+
+        let info = FireInfo {
+            task: invocation.task,
+            name: invocation.name,
+            scheduled_for: invocation.scheduled_for,
+            fired_at: invocation.fired_at,
+        };
+        subscriber.push(&mut state, (FiredTask { work }, info));
+        let waker = state.waker.take();
+        drop(state);
+        subscriber.ready.notify_one();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Box::new(|| {})
+    }
+}
+
+impl Drop for Handoff {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
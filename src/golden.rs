@@ -0,0 +1,601 @@
+/* A reference scheduler too simple to be wrong, and a harness running the
+ * same workload against it and a real implementation to compare what
+ * fires when. Public so other backends can be held to the same model.
+ *
+ * The model: a timeout scheduled at t with delay d is due at t + d, a
+ * periodic one with interval i at t + n * i for every n >= 1. Due timeouts
+ * fire in order of due time, ties in order of scheduling, and never before
+ * they are due. Time only moves through advance_to.
+ */
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::rng::Rng;
+use crate::{
+    Builder, Clock, MockClock, MonotonicClock, RecordingExecutor, Scheduler, SimScheduler, TaskId,
+    TimeoutHandle, TimerQueue,
+};
+
+/// One fire as a target reports it. Times are since the target started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fire {
+    /// As given to `Target::schedule`.
+    pub id: u64,
+    pub due: Duration,
+    pub fired_at: Duration,
+}
+
+/// Something timeouts can be scheduled on, driven by the harness.
+///
+/// Ids are chosen by the workload and unique within it. Times are since
+/// the target was made.
+pub trait Target {
+    /// Schedule `id` to fire `delay` from now, and every `delay` after
+    /// that if `periodic`.
+    fn schedule(&mut self, id: u64, delay: Duration, periodic: bool);
+
+    /// Stop `id` from firing again, a no-op if it no longer can.
+    fn cancel(&mut self, id: u64);
+
+    /// Let time pass until `now` and report, in firing order, what fired
+    /// since the last call.
+    fn advance_to(&mut self, now: Duration) -> Vec<Fire>;
+}
+
+struct Timer {
+    due: Duration,
+    seq: u64,
+    id: u64,
+    /* Anchor, interval and which tick is next for periodic timers */
+    period: Option<(Duration, Duration, u32)>,
+}
+
+/// The model as a sorted `Vec`, single-threaded and obviously correct.
+/// Fires report the time they were advanced to as `fired_at`.
+#[derive(Default)]
+pub struct Reference {
+    now: Duration,
+    seq: u64,
+    timers: Vec<Timer>,
+}
+
+impl Reference {
+    pub fn new() -> Reference {
+        Reference::default()
+    }
+
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// When `id` fires next, if it still does.
+    pub fn next_due(&self, id: u64) -> Option<Duration> {
+        self.timers.iter().find(|t| t.id == id).map(|t| t.due)
+    }
+
+    fn insert(&mut self, timer: Timer) {
+        let at = self
+            .timers
+            .partition_point(|t| (t.due, t.seq) <= (timer.due, timer.seq));
+        self.timers.insert(at, timer);
+    }
+}
+
+impl Target for Reference {
+    fn schedule(&mut self, id: u64, delay: Duration, periodic: bool) {
+        let seq = self.seq;
+        self.seq += 1;
+        let due = self.now.saturating_add(delay);
+        let period = periodic.then_some((self.now, delay, 2));
+        self.insert(Timer {
+            due,
+            seq,
+            id,
+            period,
+        });
+    }
+
+    fn cancel(&mut self, id: u64) {
+        self.timers.retain(|t| t.id != id);
+    }
+
+    fn advance_to(&mut self, now: Duration) -> Vec<Fire> {
+        let now = now.max(self.now);
+        let mut fired = Vec::new();
+
+        while self.timers.first().is_some_and(|t| t.due <= now) {
+            let mut timer = self.timers.remove(0);
+            fired.push(Fire {
+                id: timer.id,
+                due: timer.due,
+                fired_at: now,
+            });
+
+            if let Some((anchor, interval, tick)) = timer.period {
+                timer.due = anchor.saturating_add(interval.saturating_mul(tick));
+                timer.period = Some((anchor, interval, tick + 1));
+                self.insert(timer);
+            }
+        }
+
+        self.now = now;
+        fired
+    }
+}
+
+/// A step of a workload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Schedule {
+        id: u64,
+        delay: Duration,
+        periodic: bool,
+    },
+    Cancel {
+        id: u64,
+    },
+    AdvanceTo(Duration),
+}
+
+/// A sequence of steps to run against targets, see `differential`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Workload {
+    pub ops: Vec<Op>,
+}
+
+impl Workload {
+    /// `len` random steps with times in multiples of `unit`, the same for
+    /// the same `seed`. Cancels only hit timeouts more than a `unit` away
+    /// from firing, so with a tolerance below `unit` real-time targets
+    /// can't race them. Ends with time advanced past every one-shot
+    /// deadline.
+    pub fn random(seed: u64, len: usize, unit: Duration) -> Workload {
+        let mut rng = Rng::new(seed);
+        let mut model = Reference::new();
+        let mut ops = Vec::with_capacity(len + 1);
+        let mut next_id = 0;
+        let units = |n: u64| unit.saturating_mul(n as u32);
+
+        for _ in 0..len {
+            let op = match rng.next_u64() % 10 {
+                0..=3 => Op::Schedule {
+                    id: next_id,
+                    delay: units(rng.next_u64() % 20),
+                    periodic: false,
+                },
+                4 => Op::Schedule {
+                    id: next_id,
+                    delay: units(1 + rng.next_u64() % 10),
+                    periodic: true,
+                },
+                5 | 6 => {
+                    let horizon = model.now().saturating_add(unit);
+                    let cancellable = (0..next_id)
+                        .filter(|&id| model.next_due(id).is_some_and(|due| due > horizon))
+                        .collect::<Vec<_>>();
+                    if cancellable.is_empty() {
+                        continue;
+                    }
+                    let pick = rng.next_u64() % cancellable.len() as u64;
+                    Op::Cancel {
+                        id: cancellable[pick as usize],
+                    }
+                }
+                _ => Op::AdvanceTo(model.now().saturating_add(units(rng.next_u64() % 10))),
+            };
+
+            if let Op::Schedule { .. } = op {
+                next_id += 1;
+            }
+            apply(&mut model, op);
+            ops.push(op);
+        }
+
+        ops.push(Op::AdvanceTo(model.now().saturating_add(units(20))));
+        Workload { ops }
+    }
+}
+
+fn apply<T: Target>(target: &mut T, op: Op) -> Vec<Fire> {
+    match op {
+        Op::Schedule {
+            id,
+            delay,
+            periodic,
+        } => target.schedule(id, delay, periodic),
+        Op::Cancel { id } => target.cancel(id),
+        Op::AdvanceTo(now) => return target.advance_to(now),
+    }
+    Vec::new()
+}
+
+/// How a target strayed from the reference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Divergence {
+    /// The reference fired this, the target didn't.
+    Missing(Fire),
+    /// The target fired this, the reference didn't.
+    Unexpected(Fire),
+    WrongDue {
+        expected: Fire,
+        actual: Fire,
+    },
+    /// Fired before it was due.
+    Early(Fire),
+    /// Fired after the advance that made it due returned.
+    Late(Fire),
+    /// `second` fired after `first` but should have gone before it.
+    OutOfOrder {
+        first: Fire,
+        second: Fire,
+    },
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Divergence::Missing(fire) => write!(f, "missing fire {:?}", fire),
+            Divergence::Unexpected(fire) => write!(f, "unexpected fire {:?}", fire),
+            Divergence::WrongDue { expected, actual } => {
+                write!(f, "fire {:?} should be {:?}", actual, expected)
+            }
+            Divergence::Early(fire) => write!(f, "early fire {:?}", fire),
+            Divergence::Late(fire) => write!(f, "late fire {:?}", fire),
+            Divergence::OutOfOrder { first, second } => {
+                write!(f, "{:?} fired after {:?}", second, first)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// Run `workload` against `target` and the `Reference`, and compare every
+/// fire. With a `tolerance` of zero the target has to match exactly, fire
+/// for fire and step for step. Targets running in real time get to be
+/// `tolerance` off in due and fire times and order, and `workload` then
+/// needs its times in units above it, see `Workload::random`.
+///
+/// Targets must not round deadlines, e.g. to a `Builder::resolution`.
+pub fn differential<T: Target>(
+    workload: &Workload,
+    target: &mut T,
+    tolerance: Duration,
+) -> Result<(), Divergence> {
+    let mut reference = Reference::new();
+    /* Reference fires with the time of the advance that made them */
+    let mut expected = Vec::new();
+    let mut actual = Vec::new();
+
+    let settle = workload
+        .ops
+        .iter()
+        .filter_map(|op| match op {
+            Op::AdvanceTo(now) => Some(*now),
+            _ => None,
+        })
+        .max()
+        .unwrap_or_default()
+        .saturating_add(tolerance);
+    let ops = workload.ops.iter().copied().chain([Op::AdvanceTo(settle)]);
+
+    for op in ops {
+        let step = match op {
+            Op::AdvanceTo(now) => now.max(reference.now()),
+            _ => reference.now(),
+        };
+        expected.extend(apply(&mut reference, op).into_iter().map(|f| (f, step)));
+        actual.extend(apply(target, op));
+    }
+
+    compare(
+        &expected,
+        &actual,
+        tolerance,
+        settle.saturating_sub(tolerance),
+    )
+}
+
+fn compare(
+    expected: &[(Fire, Duration)],
+    actual: &[Fire],
+    tolerance: Duration,
+    end: Duration,
+) -> Result<(), Divergence> {
+    /* Match the nth fire of an id on one side with the nth on the other */
+    let mut by_id: HashMap<u64, Vec<(Fire, Duration)>> = HashMap::new();
+    for &(fire, step) in expected.iter().rev() {
+        by_id.entry(fire.id).or_default().push((fire, step));
+    }
+
+    let mut matched = Vec::with_capacity(actual.len());
+    for &fire in actual {
+        match by_id.get_mut(&fire.id).and_then(Vec::pop) {
+            Some(expected) => matched.push((expected, fire)),
+            /* Came due right at the end, the reference may be just short */
+            None if fire.due > end => {}
+            None => return Err(Divergence::Unexpected(fire)),
+        }
+    }
+    if let Some(&(missing, _)) = by_id
+        .values()
+        .flat_map(|left| left.iter())
+        .filter(|(fire, _)| fire.due <= end)
+        .min_by_key(|(fire, _)| fire.due)
+    {
+        return Err(Divergence::Missing(missing));
+    }
+
+    for &((expected, step), actual) in &matched {
+        if actual.due.abs_diff(expected.due) > tolerance {
+            return Err(Divergence::WrongDue { expected, actual });
+        }
+        if actual.fired_at < actual.due {
+            return Err(Divergence::Early(actual));
+        }
+        if actual.fired_at > step.saturating_add(tolerance) {
+            return Err(Divergence::Late(actual));
+        }
+    }
+
+    /* In the reference's order, exactly or give or take the tolerance */
+    let position = |fire: &Fire| expected.iter().position(|(e, _)| e == fire);
+    for pair in matched.windows(2) {
+        let ((first, _), (second, _)) = (pair[0].0, pair[1].0);
+        let inverted = match tolerance {
+            Duration::ZERO => position(&first) > position(&second),
+            _ => second.due.saturating_add(tolerance) < first.due,
+        };
+        if inverted {
+            return Err(Divergence::OutOfOrder {
+                first: pair[0].1,
+                second: pair[1].1,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// A `TimerQueue` driven the way an event loop would, re-inserting
+/// periodic timeouts as they expire. A re-inserted timeout goes behind
+/// others due at the same time, so what comes due together is run in
+/// the order it was first scheduled, as the scheduler does.
+pub struct QueueTarget {
+    /* Items are the order first scheduled in, id and interval */
+    queue: TimerQueue<(u64, u64, Option<Duration>)>,
+    now: Duration,
+    seq: u64,
+    ids: HashMap<u64, TaskId>,
+    /* Anchor and ticks so far of periodic timeouts */
+    periods: HashMap<u64, (Duration, u32)>,
+}
+
+impl QueueTarget {
+    pub fn new() -> QueueTarget {
+        QueueTarget {
+            queue: TimerQueue::new(Duration::ZERO),
+            now: Duration::ZERO,
+            seq: 0,
+            ids: HashMap::new(),
+            periods: HashMap::new(),
+        }
+    }
+}
+
+impl Default for QueueTarget {
+    fn default() -> QueueTarget {
+        QueueTarget::new()
+    }
+}
+
+impl Target for QueueTarget {
+    fn schedule(&mut self, id: u64, delay: Duration, periodic: bool) {
+        let interval = periodic.then_some(delay);
+        if periodic {
+            self.periods.insert(id, (self.now, 1));
+        }
+        let task = self.queue.insert(delay, (self.seq, id, interval));
+        self.seq += 1;
+        self.ids.insert(id, task);
+    }
+
+    fn cancel(&mut self, id: u64) {
+        if let Some(task) = self.ids.remove(&id) {
+            self.queue.remove(task);
+        }
+        self.periods.remove(&id);
+    }
+
+    fn advance_to(&mut self, now: Duration) -> Vec<Fire> {
+        let mut fired = Vec::new();
+
+        /* Stop at every deadline on the way, so re-armed timeouts land
+         * where they are due rather than at `now`
+         */
+        while let Some(due) = self.queue.next_deadline().filter(|due| *due <= now) {
+            self.now = self.now.max(due);
+            let mut expired = self.queue.tick(self.now).collect::<Vec<_>>();
+            expired.sort_by_key(|&(seq, _, _)| seq);
+
+            for (seq, id, interval) in expired {
+                fired.push(Fire {
+                    id,
+                    due,
+                    fired_at: due,
+                });
+
+                if let Some(interval) = interval {
+                    let (anchor, ticks) = self.periods[&id];
+                    let next = anchor.saturating_add(interval.saturating_mul(ticks + 1));
+                    self.periods.insert(id, (anchor, ticks + 1));
+                    let task = self
+                        .queue
+                        .insert(next - self.now, (seq, id, Some(interval)));
+                    self.ids.insert(id, task);
+                }
+            }
+        }
+
+        self.now = self.now.max(now);
+        self.queue.tick(self.now).for_each(drop);
+        fired
+    }
+}
+
+/// A `SimScheduler`, whose fires are due whenever they run.
+pub struct SimTarget {
+    sim: SimScheduler,
+    fired: Rc<RefCell<Vec<Fire>>>,
+    ids: HashMap<u64, TaskId>,
+}
+
+impl SimTarget {
+    pub fn new() -> SimTarget {
+        SimTarget {
+            sim: SimScheduler::new(),
+            fired: Rc::default(),
+            ids: HashMap::new(),
+        }
+    }
+}
+
+impl Default for SimTarget {
+    fn default() -> SimTarget {
+        SimTarget::new()
+    }
+}
+
+impl Target for SimTarget {
+    fn schedule(&mut self, id: u64, delay: Duration, periodic: bool) {
+        let (sim, fired) = (self.sim.clone(), self.fired.clone());
+        let work = move || {
+            fired.borrow_mut().push(Fire {
+                id,
+                due: sim.now(),
+                fired_at: sim.now(),
+            })
+        };
+        let task = if periodic {
+            self.sim.schedule_periodic(delay, work)
+        } else {
+            self.sim.schedule_delayed(delay, work)
+        };
+        self.ids.insert(id, task);
+    }
+
+    fn cancel(&mut self, id: u64) {
+        if let Some(task) = self.ids.remove(&id) {
+            self.sim.cancel(task);
+        }
+    }
+
+    fn advance_to(&mut self, now: Duration) -> Vec<Fire> {
+        self.sim.advance_to(now);
+        self.fired.borrow_mut().drain(..).collect()
+    }
+}
+
+enum TargetClock {
+    Virtual(MockClock),
+    Real(MonotonicClock),
+}
+
+/// A threaded `Scheduler`, on virtual or real time. Its tasks go to a
+/// `RecordingExecutor`, which is where fires are read off.
+pub struct SchedulerTarget {
+    scheduler: Scheduler,
+    executor: RecordingExecutor,
+    clock: TargetClock,
+    seen: usize,
+    handles: HashMap<u64, TimeoutHandle>,
+    ids: HashMap<TaskId, u64>,
+}
+
+impl SchedulerTarget {
+    /// Build on a `MockClock`, moved by `advance_to`, which then waits
+    /// until everything due has fired. Manual clocks always use the
+    /// channel backend.
+    pub fn virtual_time(builder: Builder) -> SchedulerTarget {
+        let clock = MockClock::new();
+        SchedulerTarget::build(builder.clock(clock.clone()), TargetClock::Virtual(clock))
+    }
+
+    /// Build on a `MonotonicClock`, with `advance_to` sleeping until then.
+    /// For the backends waiting on real time, compare with a tolerance.
+    pub fn real_time(builder: Builder) -> SchedulerTarget {
+        let clock = MonotonicClock::new();
+        SchedulerTarget::build(builder.clock(clock), TargetClock::Real(clock))
+    }
+
+    /* On virtual time, have the timekeeper take in every call before the
+     * clock next moves, or it may see the two the other way round
+     */
+    fn settle(&self) {
+        if let TargetClock::Virtual(_) = self.clock {
+            self.scheduler.pending();
+        }
+    }
+
+    fn build(builder: Builder, clock: TargetClock) -> SchedulerTarget {
+        let executor = RecordingExecutor::new();
+        SchedulerTarget {
+            scheduler: builder.executor(executor.clone()).build(),
+            executor,
+            clock,
+            seen: 0,
+            handles: HashMap::new(),
+            ids: HashMap::new(),
+        }
+    }
+}
+
+impl Target for SchedulerTarget {
+    fn schedule(&mut self, id: u64, delay: Duration, periodic: bool) {
+        let handle = if periodic {
+            self.scheduler.schedule_periodic(delay, || {})
+        } else {
+            self.scheduler.schedule_delayed(delay, || {})
+        };
+        let handle = handle.expect("scheduler refused a timeout");
+        self.ids.insert(handle.id(), id);
+        self.handles.insert(id, handle);
+        self.settle();
+    }
+
+    fn cancel(&mut self, id: u64) {
+        if let Some(handle) = self.handles.remove(&id) {
+            let _ = handle.cancel();
+        }
+        self.settle();
+    }
+
+    fn advance_to(&mut self, now: Duration) -> Vec<Fire> {
+        match &self.clock {
+            TargetClock::Virtual(clock) => {
+                clock.advance(now.saturating_sub(clock.now()));
+                /* Answered once the timekeeper has fired what is due */
+                self.scheduler.pending();
+            }
+            TargetClock::Real(clock) => {
+                std::thread::sleep(now.saturating_sub(clock.now()));
+            }
+        }
+
+        let invocations = self.executor.invocations();
+        let fresh = invocations.get(self.seen..).unwrap_or_default();
+        self.seen = invocations.len();
+        fresh
+            .iter()
+            .filter_map(|invocation| {
+                Some(Fire {
+                    id: *self.ids.get(&invocation.task?)?,
+                    due: invocation.scheduled_for,
+                    fired_at: invocation.fired_at,
+                })
+            })
+            .collect()
+    }
+}
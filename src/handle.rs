@@ -0,0 +1,254 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::backend::Submitter;
+use crate::error::{Result, SchedError};
+use crate::sync::{Condvar, Mutex};
+use crate::timekeeper::Message;
+use crate::{TaskId, Work};
+
+/// Refers to a pending timeout, dropping it leaves the timeout scheduled.
+///
+/// Awaiting it waits, without blocking, for the timeout to be done with:
+/// what one-shot work returned, `WorkerPanicked` if it panicked, or
+/// `Cancelled` if it was dropped before it ran, e.g. cancelled or still
+/// pending at shutdown. Periodic timeouts only end in `Cancelled`. Clones
+/// share the result, only the first of them to be awaited gets it.
+pub struct TimeoutHandle<R = ()> {
+    id: TaskId,
+    intake: Submitter,
+    done: Arc<Completion<R>>,
+}
+
+impl<R> TimeoutHandle<R> {
+    pub(crate) fn new(id: TaskId, intake: Submitter, done: Arc<Completion<R>>) -> TimeoutHandle<R> {
+        TimeoutHandle { id, intake, done }
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Whether the scheduler behind this handle has shut down, after which
+    /// the timeout will never fire.
+    pub fn is_shut_down(&self) -> bool {
+        self.intake.is_closed()
+    }
+
+    /// Block until the timeout is done with and return the result, the
+    /// same as awaiting the handle would.
+    pub fn wait(&self) -> Result<R> {
+        self.done.wait(None).unwrap_or(Err(SchedError::Cancelled))
+    }
+
+    /// Like `wait`, but give up after `timeout`, None if it isn't done by
+    /// then.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<Result<R>> {
+        self.done.wait(Some(timeout))
+    }
+
+    /// Whether the timeout is done with, i.e. waiting on it or awaiting it
+    /// would return right away.
+    pub fn is_finished(&self) -> bool {
+        self.done.is_finished()
+    }
+
+    /// Remove the timeout if it has not fired yet. Periodic timeouts stop
+    /// ticking, a tick already handed to the worker still runs. Once the
+    /// scheduler is shut down this does nothing and returns `ShutDown`.
+    pub fn cancel(&self) -> Result<()> {
+        self.intake.send(Message::Cancel(self.id))
+    }
+}
+
+impl<R> Clone for TimeoutHandle<R> {
+    fn clone(&self) -> TimeoutHandle<R> {
+        TimeoutHandle {
+            id: self.id,
+            intake: self.intake.clone(),
+            done: self.done.clone(),
+        }
+    }
+}
+
+impl<R> Future for TimeoutHandle<R> {
+    type Output = Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<R>> {
+        self.done.poll(cx.waker())
+    }
+}
+
+impl<R> std::fmt::Debug for TimeoutHandle<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimeoutHandle")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/* Where the work behind a handle leaves its result, filled in on the
+ * worker right after it ran, or by whoever drops it unrun. Async waiters
+ * leave a waker, blocking ones wait on the condvar.
+ */
+pub(crate) struct Completion<R> {
+    state: Mutex<State<R>>,
+    finished: Condvar,
+}
+
+enum State<R> {
+    Waiting(Vec<Waker>),
+    Done(Result<R>),
+    Taken,
+}
+
+impl<R> Completion<R> {
+    pub(crate) fn new() -> Arc<Completion<R>> {
+        Arc::new(Completion {
+            state: Mutex::new(State::Waiting(Vec::new())),
+            finished: Condvar::new(),
+        })
+    }
+
+    pub(crate) fn complete(&self, result: Result<R>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let State::Waiting(wakers) = &mut *state {
+            let wakers = std::mem::take(wakers);
+            *state = State::Done(result);
+            drop(state);
+            self.finished.notify_all();
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+
+    /* Block until finished or `timeout` has passed, None if not finished */
+    fn wait(&self, timeout: Option<Duration>) -> Option<Result<R>> {
+        /* A deadline past what Instant holds is as good as none */
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while let State::Waiting(_) = *state {
+            state = match deadline {
+                None => self.finished.wait(state).unwrap_or_else(|e| e.into_inner()),
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return None;
+                    }
+                    self.finished
+                        .wait_timeout(state, left)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+            };
+        }
+
+        match std::mem::replace(&mut *state, State::Taken) {
+            State::Done(result) => Some(result),
+            _ => Some(Err(SchedError::Cancelled)),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        !matches!(*state, State::Waiting(_))
+    }
+
+    fn poll(&self, waker: &Waker) -> Poll<Result<R>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match std::mem::replace(&mut *state, State::Taken) {
+            State::Waiting(mut wakers) => {
+                if !wakers.iter().any(|w| w.will_wake(waker)) {
+                    wakers.push(waker.clone());
+                }
+                *state = State::Waiting(wakers);
+                Poll::Pending
+            }
+            State::Done(result) => Poll::Ready(result),
+            State::Taken => Poll::Ready(Err(SchedError::Cancelled)),
+        }
+    }
+}
+
+/* Wrap one-shot `work` to complete its handle with what it returns */
+pub(crate) fn once<F, R>(work: F) -> (Work, Arc<Completion<R>>)
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let done = Completion::new();
+    let mut once = Once {
+        work: Some(work),
+        running: false,
+        done: done.clone(),
+    };
+    (Box::new(move || once.run()), done)
+}
+
+/* One-shot `work` as Work, runs on the first call only */
+pub(crate) fn work_once<F>(work: F) -> Work
+where
+    F: FnOnce() + Send + 'static,
+{
+    let mut work = Some(work);
+    Box::new(move || {
+        if let Some(work) = work.take() {
+            work();
+        }
+    })
+}
+
+/* Wrap work that may run any number of times, its handle completes once
+ * it is dropped
+ */
+pub(crate) fn repeated<F>(work: F) -> (Work, Arc<Completion<()>>)
+where
+    F: FnMut() + Send + 'static,
+{
+    let done = Completion::new();
+    let mut repeated = Repeated {
+        work,
+        done: done.clone(),
+    };
+    (Box::new(move || (repeated.work)()), done)
+}
+
+struct Once<F, R> {
+    work: Option<F>,
+    /* Still set when dropped if the work panicked */
+    running: bool,
+    done: Arc<Completion<R>>,
+}
+
+impl<F: FnOnce() -> R, R> Once<F, R> {
+    fn run(&mut self) {
+        if let Some(work) = self.work.take() {
+            self.running = true;
+            let result = work();
+            self.running = false;
+            self.done.complete(Ok(result));
+        }
+    }
+}
+
+impl<F, R> Drop for Once<F, R> {
+    fn drop(&mut self) {
+        match self.running {
+            true => self.done.complete(Err(SchedError::WorkerPanicked)),
+            false => self.done.complete(Err(SchedError::Cancelled)),
+        }
+    }
+}
+
+struct Repeated<F> {
+    work: F,
+    done: Arc<Completion<()>>,
+}
+
+impl<F> Drop for Repeated<F> {
+    fn drop(&mut self) {
+        self.done.complete(Err(SchedError::Cancelled));
+    }
+}
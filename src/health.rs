@@ -0,0 +1,133 @@
+/* Heartbeats of the scheduler threads. Each thread marks itself busy when
+ * it starts on something and idle when it goes back to waiting, so a
+ * thread that has been busy for long is stuck on whatever it started,
+ * while one blocked waiting for work is fine however long it waits.
+ * Plain std atomics, see sync.rs.
+ */
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const IDLE: u64 = 0;
+const DEAD: u64 = u64::MAX;
+
+/// Threads busy longer than this are reported stalled, unless configured
+/// with `Builder::watchdog`.
+pub(crate) const DEFAULT_WATCHDOG: Duration = Duration::from_secs(1);
+
+/// What a scheduler thread is up to, see `Scheduler::health`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Liveness {
+    /// Waiting for work, or exited after shutdown.
+    Idle,
+    /// Working on something for `elapsed` so far, within the watchdog
+    /// interval.
+    Busy { elapsed: Duration },
+    /// Working on the same thing for longer than the watchdog interval.
+    Stalled { elapsed: Duration },
+    /// The thread panicked and could not be replaced.
+    Dead,
+}
+
+/// Liveness of the scheduler threads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Health {
+    pub timekeeper: Liveness,
+    pub worker: Liveness,
+}
+
+impl Health {
+    /// Whether no thread is stalled or dead.
+    pub fn is_healthy(&self) -> bool {
+        let ok = |liveness| matches!(liveness, Liveness::Idle | Liveness::Busy { .. });
+        ok(self.timekeeper) && ok(self.worker)
+    }
+}
+
+pub(crate) struct Heartbeat {
+    origin: Instant,
+    /* When the thread became busy, as nanoseconds since `origin` plus
+     * one, or one of IDLE and DEAD
+     */
+    state: AtomicU64,
+}
+
+impl Heartbeat {
+    fn new(origin: Instant) -> Heartbeat {
+        Heartbeat {
+            origin,
+            state: AtomicU64::new(IDLE),
+        }
+    }
+
+    pub(crate) fn busy(&self) {
+        let since = self.origin.elapsed().as_nanos() as u64 + 1;
+        self.state.store(since.min(DEAD - 1), Ordering::Relaxed);
+    }
+
+    pub(crate) fn idle(&self) {
+        self.state.store(IDLE, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dead(&self) {
+        self.state.store(DEAD, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_busy(&self) -> bool {
+        !matches!(self.state.load(Ordering::Relaxed), IDLE | DEAD)
+    }
+
+    fn liveness(&self, watchdog: Duration) -> Liveness {
+        match self.state.load(Ordering::Relaxed) {
+            IDLE => Liveness::Idle,
+            DEAD => Liveness::Dead,
+            since => {
+                let elapsed = self
+                    .origin
+                    .elapsed()
+                    .saturating_sub(Duration::from_nanos(since - 1));
+                if elapsed > watchdog {
+                    Liveness::Stalled { elapsed }
+                } else {
+                    Liveness::Busy { elapsed }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct Heartbeats {
+    pub(crate) timekeeper: Heartbeat,
+    pub(crate) worker: Heartbeat,
+}
+
+impl Default for Heartbeats {
+    fn default() -> Heartbeats {
+        let origin = Instant::now();
+        Heartbeats {
+            timekeeper: Heartbeat::new(origin),
+            worker: Heartbeat::new(origin),
+        }
+    }
+}
+
+impl Heartbeats {
+    pub(crate) fn health(&self, watchdog: Duration) -> Health {
+        Health {
+            timekeeper: self.timekeeper.liveness(watchdog),
+            worker: self.worker.liveness(watchdog),
+        }
+    }
+}
+
+/* Marks the timekeeper idle once it returns, or dead if it panicked */
+pub(crate) struct Exit<'a>(pub(crate) &'a Heartbeat);
+
+impl Drop for Exit<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.dead();
+        } else {
+            self.0.idle();
+        }
+    }
+}
@@ -0,0 +1,154 @@
+/* Log-linear histogram of nanosecond durations, HDR style: every power of
+ * two is split into SUB_BUCKETS linear buckets, so a recorded value is
+ * off by at most 1/SUB_BUCKETS of itself, from nanoseconds to centuries,
+ * in fixed memory. Recording is a single relaxed increment.
+ *
+ * The buckets are plain std atomics, see sync.rs, loom would have to
+ * track thousands of them.
+ */
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const SUB_BITS: u32 = 6;
+const SUB_BUCKETS: u64 = 1 << SUB_BITS;
+const BUCKETS: usize = ((65 - SUB_BITS) as u64 * SUB_BUCKETS) as usize;
+
+fn index(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS {
+        return nanos as usize;
+    }
+
+    let exp = 63 - nanos.leading_zeros();
+    let mantissa = nanos >> (exp - SUB_BITS);
+    ((exp - SUB_BITS + 1) as u64 * SUB_BUCKETS + mantissa - SUB_BUCKETS) as usize
+}
+
+/* Largest value landing in bucket `index` */
+fn highest(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+
+    let shift = index / SUB_BUCKETS - 1;
+    let mantissa = index % SUB_BUCKETS + SUB_BUCKETS;
+    (mantissa << shift) + ((1 << shift) - 1)
+}
+
+pub(crate) struct AtomicHistogram {
+    buckets: Box<[AtomicU64]>,
+}
+
+impl Default for AtomicHistogram {
+    fn default() -> AtomicHistogram {
+        AtomicHistogram {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+}
+
+impl AtomicHistogram {
+    pub(crate) fn record(&self, value: Duration) {
+        let nanos = u64::try_from(value.as_nanos()).unwrap_or(u64::MAX);
+        self.buckets[index(nanos)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /* Values recorded while this runs may or may not make it */
+    pub(crate) fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> Histogram {
+        let counts: Vec<(u64, u64)> = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, bucket)| match bucket.load(Ordering::Relaxed) {
+                0 => None,
+                count => Some((highest(i), count)),
+            })
+            .collect();
+
+        Histogram {
+            total: counts.iter().map(|&(_, count)| count).sum(),
+            counts,
+        }
+    }
+}
+
+/// Distribution of recorded durations, precise to within 1/64 of each
+/// value, see `Scheduler::lateness_histogram`.
+#[derive(Clone, Debug, Default)]
+pub struct Histogram {
+    /* Highest value of each non-empty bucket and its count, ascending */
+    counts: Vec<(u64, u64)>,
+    total: u64,
+}
+
+impl Histogram {
+    /// Number of recorded values.
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.counts
+            .last()
+            .map(|&(value, _)| Duration::from_nanos(value))
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        if self.total == 0 {
+            return None;
+        }
+
+        let sum: u128 = self
+            .counts
+            .iter()
+            .map(|&(value, count)| value as u128 * count as u128)
+            .sum();
+        let mean = sum / self.total as u128;
+        Some(Duration::from_nanos(
+            u64::try_from(mean).unwrap_or(u64::MAX),
+        ))
+    }
+
+    /// Value at or below which `percentile` percent of recorded values
+    /// fall.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.total == 0 {
+            return None;
+        }
+
+        let rank = (percentile / 100.0 * self.total as f64).ceil() as u64;
+        let rank = rank.clamp(1, self.total);
+        let mut seen = 0;
+        for &(value, count) in &self.counts {
+            seen += count;
+            if seen >= rank {
+                return Some(Duration::from_nanos(value));
+            }
+        }
+
+        self.max()
+    }
+
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(50.0)
+    }
+
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(99.0)
+    }
+
+    pub fn p999(&self) -> Option<Duration> {
+        self.percentile(99.9)
+    }
+}
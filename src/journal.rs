@@ -0,0 +1,270 @@
+/* The write-ahead journal behind FileStore. Every change is appended as
+ * a line and synced before it is acknowledged, replaying the lines gives
+ * back the exact set of pending jobs:
+ *
+ *     S <id> <due> <kind> <hex payload>   scheduled, due in ns since epoch
+ *     C <id>                              cancelled or fired
+ *     N <id>                              next id, on compaction
+ *
+ * each followed by the FNV-1a hash of the rest as 16 hex digits. A crash
+ * mid-append leaves a last line without its newline or with a wrong hash,
+ * which is cut off on open. Anything wrong before it is corruption.
+ *
+ * Lines for jobs that are gone pile up, once they outnumber the live ones
+ * by enough the journal is compacted: the next id, so ids of jobs that
+ * are gone aren't handed out again, and the live jobs are written to a
+ * temporary journal that is synced and renamed over it.
+ */
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::durable::{DurableJob, JobId};
+
+/* Dead lines tolerated on top of as many as there are live jobs */
+const COMPACT_AFTER: usize = 64;
+
+pub(crate) enum Record<'a> {
+    Put(&'a DurableJob),
+    Remove(JobId),
+    NextId(JobId),
+}
+
+pub(crate) struct Journal {
+    path: PathBuf,
+    file: File,
+    /* Lines in the file */
+    records: usize,
+    /* Above every id ever put */
+    next_id: JobId,
+    #[cfg(feature = "fault-injection")]
+    failing_dir_syncs: usize,
+}
+
+impl Journal {
+    /* Replays `path`, created if missing */
+    pub(crate) fn open(path: &Path) -> io::Result<(Journal, BTreeMap<JobId, DurableJob>)> {
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        let mut jobs = BTreeMap::new();
+        let mut next_id = JobId::from_u64(0);
+        let mut records = 0;
+        let mut intact = 0;
+        let mut lines = contents.split_inclusive(|&byte| byte == b'\n').peekable();
+        while let Some(line) = lines.next() {
+            let last = lines.peek().is_none();
+            let record = line
+                .strip_suffix(b"\n")
+                .and_then(|line| std::str::from_utf8(line).ok())
+                .and_then(verify);
+            match (record, last) {
+                (Some(record), _) => replay(&mut jobs, &mut next_id, record).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unreadable journal line {}: {:?}", records + 1, record),
+                    )
+                })?,
+                /* Torn by a crash while appending */
+                (None, true) => break,
+                (None, false) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("corrupt journal line {}", records + 1),
+                    ))
+                }
+            }
+            records += 1;
+            intact += line.len();
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if intact < contents.len() {
+            warn!(
+                "dropping {} bytes torn off the end of {}",
+                contents.len() - intact,
+                path.display()
+            );
+            file.set_len(intact as u64)?;
+            file.sync_all()?;
+        }
+
+        let mut journal = Journal {
+            path: path.to_path_buf(),
+            file,
+            records,
+            next_id,
+            #[cfg(feature = "fault-injection")]
+            failing_dir_syncs: 0,
+        };
+        journal.compact_if_due(jobs.values())?;
+        Ok((journal, jobs))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn next_id(&self) -> JobId {
+        self.next_id
+    }
+
+    /* Durable once this returns */
+    pub(crate) fn append(&mut self, record: Record<'_>) -> io::Result<()> {
+        let mut line = String::new();
+        format_record(&mut line, &record);
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+        self.records += 1;
+        if let Record::Put(job) = record {
+            self.next_id = self.next_id.max(job.id.successor());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn compact_if_due<'a>(
+        &mut self,
+        live: impl ExactSizeIterator<Item = &'a DurableJob>,
+    ) -> io::Result<()> {
+        if self.records <= live.len() * 2 + COMPACT_AFTER {
+            return Ok(());
+        }
+        self.compact(live)
+    }
+
+    fn compact<'a>(&mut self, live: impl Iterator<Item = &'a DurableJob>) -> io::Result<()> {
+        let mut contents = String::new();
+        format_record(&mut contents, &Record::NextId(self.next_id));
+        let mut records = 1;
+        for job in live {
+            format_record(&mut contents, &Record::Put(job));
+            records += 1;
+        }
+
+        let mut temporary = OsString::from(self.path.as_os_str());
+        temporary.push(".tmp");
+        let mut file = File::create(&temporary)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        /* Renamed it is the journal, appends must go to it even if what
+         * follows fails
+         */
+        self.file = file;
+        self.records = records;
+
+        /* The rename itself is only durable once the directory is synced */
+        self.fail_dir_sync()?;
+        match self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            Some(dir) => File::open(dir)?.sync_all(),
+            None => File::open(".")?.sync_all(),
+        }
+    }
+
+    #[cfg(feature = "fault-injection")]
+    pub(crate) fn fail_dir_syncs(&mut self, count: usize) {
+        self.failing_dir_syncs = count;
+    }
+
+    #[cfg(feature = "fault-injection")]
+    fn fail_dir_sync(&mut self) -> io::Result<()> {
+        if self.failing_dir_syncs == 0 {
+            return Ok(());
+        }
+        self.failing_dir_syncs -= 1;
+        Err(io::Error::other("injected directory sync failure"))
+    }
+
+    #[cfg(not(feature = "fault-injection"))]
+    fn fail_dir_sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn format_record(out: &mut String, record: &Record<'_>) {
+    let start = out.len();
+    match record {
+        Record::Put(job) => {
+            let due = job.due.duration_since(UNIX_EPOCH).unwrap_or_default();
+            let _ = write!(
+                out,
+                "S {} {} {} ",
+                job.id.as_u64(),
+                due.as_nanos(),
+                job.kind
+            );
+            for byte in &job.payload {
+                let _ = write!(out, "{:02x}", byte);
+            }
+        }
+        Record::Remove(id) => {
+            let _ = write!(out, "C {}", id.as_u64());
+        }
+        Record::NextId(id) => {
+            let _ = write!(out, "N {}", id.as_u64());
+        }
+    }
+    let hash = fnv1a(&out.as_bytes()[start..]);
+    let _ = writeln!(out, " {:016x}", hash);
+}
+
+/* The record of a line if its hash matches */
+fn verify(line: &str) -> Option<&str> {
+    let (record, hash) = line.rsplit_once(' ')?;
+    (u64::from_str_radix(hash, 16).ok()? == fnv1a(record.as_bytes())).then_some(record)
+}
+
+fn replay(jobs: &mut BTreeMap<JobId, DurableJob>, next_id: &mut JobId, record: &str) -> Option<()> {
+    let mut fields = record.splitn(5, ' ');
+    let op = fields.next()?;
+    let id = JobId::from_u64(fields.next()?.parse().ok()?);
+    match op {
+        "S" => {
+            let due: u128 = fields.next()?.parse().ok()?;
+            let kind = fields.next().filter(|kind| !kind.is_empty())?.to_string();
+            let payload = fields.next()?;
+            let due = UNIX_EPOCH.checked_add(Duration::new(
+                u64::try_from(due / 1_000_000_000).ok()?,
+                (due % 1_000_000_000) as u32,
+            ))?;
+            let job = DurableJob {
+                id,
+                kind,
+                due,
+                payload: unhex(payload)?,
+            };
+            jobs.insert(id, job);
+            *next_id = (*next_id).max(id.successor());
+        }
+        "C" => {
+            jobs.remove(&id);
+        }
+        "N" => *next_id = (*next_id).max(id),
+        _ => return None,
+    }
+    Some(())
+}
+
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok())
+        .collect()
+}
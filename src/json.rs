@@ -0,0 +1,108 @@
+/* Just enough JSON writing for state dumps and logs, without pulling in
+ * a serialization framework. Durations are written as integer
+ * nanoseconds.
+ */
+use std::fmt::Write;
+use std::time::Duration;
+
+pub(crate) fn string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+pub(crate) fn nanos(duration: Duration) -> u128 {
+    duration.as_nanos()
+}
+
+/* Writes `{"key": value, ...}`, closed on finish */
+pub(crate) struct Object<'a> {
+    out: &'a mut String,
+    empty: bool,
+}
+
+impl<'a> Object<'a> {
+    pub(crate) fn new(out: &'a mut String) -> Object<'a> {
+        out.push('{');
+        Object { out, empty: true }
+    }
+
+    fn key(&mut self, key: &str) -> &mut String {
+        if !self.empty {
+            self.out.push(',');
+        }
+        self.empty = false;
+        string(self.out, key);
+        self.out.push(':');
+        self.out
+    }
+
+    pub(crate) fn str(&mut self, key: &str, value: &str) -> &mut Object<'a> {
+        let out = self.key(key);
+        string(out, value);
+        self
+    }
+
+    pub(crate) fn opt_str(&mut self, key: &str, value: Option<&str>) -> &mut Object<'a> {
+        match value {
+            Some(value) => self.str(key, value),
+            None => self.raw(key, "null"),
+        }
+    }
+
+    pub(crate) fn num<T: std::fmt::Display>(&mut self, key: &str, value: T) -> &mut Object<'a> {
+        let out = self.key(key);
+        let _ = write!(out, "{}", value);
+        self
+    }
+
+    pub(crate) fn opt_num<T: std::fmt::Display>(
+        &mut self,
+        key: &str,
+        value: Option<T>,
+    ) -> &mut Object<'a> {
+        match value {
+            Some(value) => self.num(key, value),
+            None => self.raw(key, "null"),
+        }
+    }
+
+    pub(crate) fn bool(&mut self, key: &str, value: bool) -> &mut Object<'a> {
+        self.raw(key, if value { "true" } else { "false" })
+    }
+
+    /* Already encoded JSON, e.g. a nested object or array */
+    pub(crate) fn raw(&mut self, key: &str, json: &str) -> &mut Object<'a> {
+        self.key(key).push_str(json);
+        self
+    }
+
+    pub(crate) fn finish(&mut self) {
+        self.out.push('}');
+    }
+}
+
+/* `[item, ...]` from already encoded items */
+pub(crate) fn array<I: IntoIterator<Item = String>>(items: I) -> String {
+    let mut out = String::from("[");
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&item);
+    }
+    out.push(']');
+    out
+}
@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::sync::Mutex;
+
+pub(crate) const DEFAULT_HISTORY: usize = 1024;
+
+/// When a timeout was due and when it was actually handed to the worker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FireSample {
+    pub expected: Duration,
+    pub actual: Duration,
+}
+
+impl FireSample {
+    /// How late the fire was, early fires count as zero.
+    pub fn lateness(&self) -> Duration {
+        self.actual.saturating_sub(self.expected)
+    }
+}
+
+/* Most recent fire samples, oldest dropped first */
+pub(crate) struct LatencyRing {
+    samples: Mutex<VecDeque<FireSample>>,
+    capacity: usize,
+}
+
+impl LatencyRing {
+    pub(crate) fn new(capacity: usize) -> LatencyRing {
+        LatencyRing {
+            samples: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub(crate) fn record(&self, sample: FireSample) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    pub(crate) fn snapshot(&self) -> Latency {
+        let samples = self.samples.lock().unwrap();
+        let mut sorted: Vec<Duration> = samples.iter().map(FireSample::lateness).collect();
+        sorted.sort_unstable();
+
+        Latency {
+            samples: samples.iter().copied().collect(),
+            sorted,
+        }
+    }
+}
+
+/// Fire lateness over the most recent timeouts, see `Builder::latency_history`.
+#[derive(Clone, Debug, Default)]
+pub struct Latency {
+    samples: Vec<FireSample>,
+    sorted: Vec<Duration>,
+}
+
+impl Latency {
+    /// Recorded samples, oldest first.
+    pub fn samples(&self) -> &[FireSample] {
+        &self.samples
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.sorted.first().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.sorted.last().copied()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        if self.sorted.is_empty() {
+            return None;
+        }
+
+        let total: u128 = self.sorted.iter().map(Duration::as_nanos).sum();
+        let mean = total / self.sorted.len() as u128;
+        Some(Duration::from_nanos(
+            u64::try_from(mean).unwrap_or(u64::MAX),
+        ))
+    }
+
+    /// Lateness at or below which `percentile` percent of samples fall.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.sorted.is_empty() {
+            return None;
+        }
+
+        let rank = (percentile / 100.0 * self.sorted.len() as f64).ceil() as usize;
+        let index = rank.clamp(1, self.sorted.len()) - 1;
+        Some(self.sorted[index])
+    }
+
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(99.0)
+    }
+}
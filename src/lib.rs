@@ -0,0 +1,234 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "nightly", feature(linked_list_cursors))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+#[macro_use]
+mod logging;
+
+#[cfg(feature = "std")]
+mod actor;
+#[cfg(all(feature = "std", target_os = "linux"))]
+mod alarm;
+#[cfg(feature = "std")]
+mod async_task;
+#[cfg(feature = "std")]
+mod audit;
+#[cfg(feature = "std")]
+mod backend;
+#[cfg(feature = "std")]
+mod calibrate;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "std")]
+mod chrome;
+#[cfg(feature = "std")]
+mod clock;
+#[cfg(feature = "console")]
+mod console;
+#[cfg(feature = "std")]
+mod context;
+#[cfg(feature = "std")]
+mod deadline;
+#[cfg(feature = "std")]
+mod dedup;
+#[cfg(feature = "alloc")]
+mod delta;
+#[cfg(feature = "mio")]
+mod driver;
+#[cfg(feature = "std")]
+mod dump;
+#[cfg(feature = "durable")]
+mod durable;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "std")]
+mod events;
+#[cfg(feature = "std")]
+mod executor;
+#[cfg(feature = "std")]
+mod faults;
+#[cfg(feature = "std")]
+mod fired;
+#[cfg(feature = "std")]
+pub mod golden;
+#[cfg(feature = "std")]
+mod handle;
+#[cfg(feature = "std")]
+mod health;
+#[cfg(feature = "std")]
+mod histogram;
+#[cfg(feature = "durable")]
+mod journal;
+#[cfg(feature = "std")]
+mod json;
+#[cfg(feature = "std")]
+mod latency;
+#[cfg(feature = "std")]
+mod local;
+#[cfg(feature = "std")]
+mod named;
+#[cfg(feature = "std")]
+mod observer;
+#[cfg(feature = "std")]
+mod pending;
+#[cfg(feature = "std")]
+mod platform;
+#[cfg(any(
+    feature = "rayon",
+    all(target_os = "macos", feature = "gcd"),
+    all(target_os = "linux", feature = "numa")
+))]
+mod pool;
+#[cfg(feature = "std")]
+mod power;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "std")]
+mod record;
+#[cfg(all(feature = "std", target_os = "linux"))]
+mod reservation;
+#[cfg(feature = "std")]
+mod rng;
+#[cfg(feature = "std")]
+mod scheduler;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(all(feature = "std", target_os = "linux"))]
+mod signal;
+#[cfg(feature = "std")]
+mod sim;
+#[cfg(feature = "std")]
+mod sink;
+#[cfg(feature = "std")]
+mod span;
+#[cfg(feature = "std")]
+mod spawn;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "durable")]
+mod store;
+#[cfg(feature = "std")]
+mod sync;
+mod table;
+mod task;
+#[cfg(feature = "std")]
+mod telemetry;
+#[cfg(feature = "std")]
+mod time;
+#[cfg(feature = "std")]
+mod timekeeper;
+#[cfg(feature = "std")]
+mod timeout;
+#[cfg(feature = "std")]
+mod timings;
+#[cfg(feature = "std")]
+mod tsc;
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+mod wasm;
+#[cfg(feature = "std")]
+mod worker;
+
+#[cfg(feature = "std")]
+pub use actor::{Actor, Addr};
+#[cfg(feature = "std")]
+pub use audit::AuditLog;
+#[cfg(feature = "std")]
+pub use backend::{Backend, TimerBackend};
+#[cfg(feature = "std")]
+pub use chrome::ChromeTrace;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use clock::BootTimeClock;
+#[cfg(feature = "std")]
+pub use clock::{Clock, MockClock, MonotonicClock, SystemClock, Wake};
+#[cfg(feature = "std")]
+pub use deadline::DeadlineMiss;
+#[cfg(feature = "std")]
+pub use dedup::Dedup;
+#[cfg(feature = "alloc")]
+pub use delta::{Expired, TimerQueue};
+#[cfg(feature = "mio")]
+pub use driver::TimerDriver;
+#[cfg(feature = "std")]
+pub use dump::{Config, StateDump, WorkerState};
+#[cfg(feature = "durable")]
+pub use durable::{DurableBuilder, DurableJob, DurableTimers, JobId, Missed};
+#[cfg(feature = "std")]
+pub use error::{Result, SchedError};
+#[cfg(feature = "std")]
+pub use events::TaskEvent;
+#[cfg(feature = "std")]
+pub use executor::{Invocation, RecordingExecutor};
+#[cfg(feature = "fault-injection")]
+pub use faults::Faults;
+#[cfg(feature = "std")]
+pub use fired::{FireInfo, FiredStream, FiredTask};
+#[cfg(feature = "std")]
+pub use handle::TimeoutHandle;
+#[cfg(feature = "std")]
+pub use health::{Health, Liveness};
+#[cfg(feature = "std")]
+pub use histogram::Histogram;
+#[cfg(feature = "std")]
+pub use latency::{FireSample, Latency};
+#[cfg(feature = "std")]
+pub use local::LocalScheduler;
+#[cfg(feature = "std")]
+pub use named::Named;
+#[cfg(feature = "std")]
+pub use observer::{
+    CancelEvent, ExecuteEvent, FireEvent, ScheduleEvent, SchedulerObserver, StartEvent,
+};
+#[cfg(feature = "std")]
+pub use pending::PendingInfo;
+#[cfg(feature = "std")]
+pub use platform::Realtime;
+#[cfg(all(target_os = "macos", feature = "gcd"))]
+pub use platform::{GcdSpawner, Qos};
+#[cfg(all(target_os = "linux", feature = "numa"))]
+pub use platform::{NumaNode, NumaSpawner};
+#[cfg(feature = "std")]
+pub use power::PowerMode;
+#[cfg(feature = "std")]
+pub use record::{CallKind, RecordedCall, Recorder, Recording};
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use reservation::{DeadlineTask, Reservation};
+#[cfg(feature = "std")]
+pub use scheduler::{Builder, Scheduler};
+#[cfg(feature = "std")]
+pub use shared::Shared;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use signal::Signal;
+#[cfg(feature = "std")]
+pub use sim::{SimScheduler, SimSnapshot, SimWork};
+#[cfg(feature = "std")]
+pub use sink::{Job, ScheduleSink};
+#[cfg(feature = "async-std")]
+pub use spawn::AsyncStdSpawner;
+#[cfg(feature = "rayon")]
+pub use spawn::RayonSpawner;
+#[cfg(feature = "smol")]
+pub use spawn::SmolSpawner;
+#[cfg(feature = "std")]
+pub use spawn::{Spawned, Spawner};
+#[cfg(feature = "std")]
+pub use stats::Stats;
+#[cfg(feature = "durable")]
+pub use store::{FileStore, MemoryStore, TimerStore};
+pub use table::{Due, TimerTable};
+pub use task::TaskId;
+#[cfg(feature = "std")]
+pub use time::{
+    interval, sleep, timeout, Elapsed, Interval, MissedTicks, Sleep, TickInfo, WithTimeout,
+};
+#[cfg(feature = "std")]
+pub use timings::TaskTimings;
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub use wasm::WasmScheduler;
+#[cfg(feature = "std")]
+pub use worker::PanicPolicy;
+
+#[cfg(feature = "std")]
+pub type Work = Box<dyn FnMut() + Send + 'static>;
@@ -0,0 +1,106 @@
+/* The single-threaded scheduler on real time: a SimScheduler whose clock
+ * is brought up to the time elapsed since creation whenever it is polled
+ * or handed new work, so delays count from the moment they are given.
+ */
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::sim::SimScheduler;
+use crate::TaskId;
+
+/// A scheduler for the current thread, for work that isn't `Send`, e.g.
+/// GUI callbacks.
+///
+/// Nothing runs on its own: `poll` runs what is due, `run` keeps doing so
+/// until nothing is left. An event loop of its own can instead wait for
+/// `next_deadline` and then `poll`. Clones share the scheduler, for tasks
+/// to schedule more work.
+#[derive(Clone)]
+pub struct LocalScheduler {
+    sim: SimScheduler,
+    started: Instant,
+}
+
+impl LocalScheduler {
+    pub fn new() -> LocalScheduler {
+        LocalScheduler {
+            sim: SimScheduler::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Queue `work` to run on the next `poll`.
+    pub fn schedule<F: FnOnce() + 'static>(&self, work: F) {
+        self.sim.schedule_once(work);
+    }
+
+    pub fn schedule_delayed<F: FnOnce() + 'static>(&self, delay: Duration, work: F) -> TaskId {
+        self.sim.catch_up(self.elapsed());
+        self.sim.schedule_delayed_once(delay, work)
+    }
+
+    /// Run `work` every `interval`, the first time one interval from now.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero.
+    pub fn schedule_periodic<F: FnMut() + 'static>(&self, interval: Duration, work: F) -> TaskId {
+        self.sim.catch_up(self.elapsed());
+        self.sim.schedule_periodic(interval, work)
+    }
+
+    /// Stop `id` from running again. False if it already ran or was
+    /// cancelled.
+    pub fn cancel(&self, id: TaskId) -> bool {
+        self.sim.cancel(id)
+    }
+
+    /// Timeouts waiting for their deadline, periodic ones counting once.
+    pub fn pending(&self) -> usize {
+        self.sim.pending()
+    }
+
+    /// When the earliest timeout is due, for an event loop to wake up at.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.sim.next_deadline().map(|d| self.started + d)
+    }
+
+    /// Run ready work and every timeout due by now, including work those
+    /// schedule for now. Returns how many tasks ran.
+    pub fn poll(&self) -> usize {
+        self.sim.advance_to(self.elapsed())
+    }
+
+    /// Poll and sleep until the next deadline, until no work and no
+    /// timeouts are left, periodic ones keep it going until cancelled.
+    /// Returns how many tasks ran.
+    pub fn run(&self) -> usize {
+        let mut ran = 0;
+        loop {
+            ran += self.poll();
+            let Some(deadline) = self.next_deadline() else {
+                return ran;
+            };
+            thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl Default for LocalScheduler {
+    fn default() -> LocalScheduler {
+        LocalScheduler::new()
+    }
+}
+
+impl std::fmt::Debug for LocalScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalScheduler")
+            .field("pending", &self.pending())
+            .field("next_deadline", &self.next_deadline())
+            .finish()
+    }
+}
@@ -0,0 +1,38 @@
+/* Diagnostics go through the log crate with the `log` feature and are
+ * compiled out without it, arguments still type-checked.
+ */
+/* Only used with some features */
+#[cfg(feature = "log")]
+#[allow(unused_macros)]
+macro_rules! trace {
+    ($($arg:tt)+) => { log::trace!($($arg)+) };
+}
+
+#[cfg(feature = "log")]
+macro_rules! warn {
+    ($($arg:tt)+) => { log::warn!($($arg)+) };
+}
+
+#[cfg(feature = "log")]
+macro_rules! error {
+    ($($arg:tt)+) => { log::error!($($arg)+) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! warn {
+    ($($arg:tt)+) => { trace!($($arg)+) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! error {
+    ($($arg:tt)+) => { trace!($($arg)+) };
+}
@@ -0,0 +1,166 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::dedup::Dedup;
+use crate::error::Result;
+use crate::handle::{self, TimeoutHandle};
+use crate::scheduler::Scheduler;
+
+/// Schedules tasks under a name, see `Scheduler::named`. Takes the same
+/// calls as the scheduler itself.
+#[derive(Clone, Copy)]
+pub struct Named<'a> {
+    scheduler: &'a Scheduler,
+    name: &'static str,
+    tags: &'a [(&'a str, &'a str)],
+    node: Option<usize>,
+}
+
+impl<'a> Named<'a> {
+    pub(crate) fn new(scheduler: &'a Scheduler, name: &'static str) -> Named<'a> {
+        Named {
+            scheduler,
+            name,
+            tags: &[],
+            node: None,
+        }
+    }
+
+    /// Attach key/value `tags` to the tasks scheduled from here on, for
+    /// profilers. They are recorded on the tracing span with the `tracing`
+    /// feature and sent along with every run as a Linux user event with
+    /// the `user-events` feature.
+    pub fn tags(mut self, tags: &'a [(&'a str, &'a str)]) -> Named<'a> {
+        self.tags = tags;
+        self
+    }
+
+    /// Hint that the tasks scheduled from here on should run on NUMA node
+    /// `node`, near the memory they work on. Only a spawner that knows
+    /// about nodes, like `NumaSpawner`, acts on it, the worker thread runs
+    /// them wherever it runs.
+    pub fn numa_node(mut self, node: usize) -> Named<'a> {
+        self.node = Some(node);
+        self
+    }
+
+    /* As `key=value,...` */
+    fn formatted_tags(&self) -> Option<Arc<str>> {
+        if self.tags.is_empty() {
+            return None;
+        }
+
+        let tags: Vec<String> = self
+            .tags
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        Some(tags.join(",").into())
+    }
+
+    pub fn schedule<F>(&self, work: F) -> Result<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.scheduler.run(
+            Some(self.name),
+            self.formatted_tags(),
+            self.node,
+            handle::work_once(work),
+        )
+    }
+
+    pub fn schedule_delayed<F, R>(&self, delay: Duration, work: F) -> Result<TimeoutHandle<R>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (work, done) = handle::once(work);
+        let timeout = self.scheduler.delayed(Some(self.name), delay, work)?;
+        self.scheduler.submit(
+            timeout.tagged(self.formatted_tags()).on_node(self.node),
+            done,
+        )
+    }
+
+    pub fn schedule_async<Fut>(
+        &self,
+        delay: Duration,
+        future: Fut,
+    ) -> Result<TimeoutHandle<Fut::Output>>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let (timeout, done) =
+            self.scheduler
+                .delayed_async(Some(self.name), delay, Box::pin(future))?;
+        self.scheduler.submit(
+            timeout.tagged(self.formatted_tags()).on_node(self.node),
+            done,
+        )
+    }
+
+    pub fn schedule_delayed_keyed<K, F, R>(
+        &self,
+        key: K,
+        delay: Duration,
+        policy: Dedup,
+        work: F,
+    ) -> Result<TimeoutHandle<R>>
+    where
+        K: Into<String>,
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (work, done) = handle::once(work);
+        let timeout = self.scheduler.delayed(Some(self.name), delay, work)?;
+        self.scheduler.submit_keyed(
+            key.into(),
+            policy,
+            timeout.tagged(self.formatted_tags()).on_node(self.node),
+            done,
+        )
+    }
+
+    pub fn schedule_at<F, R>(&self, when: SystemTime, work: F) -> Result<TimeoutHandle<R>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (work, done) = handle::once(work);
+        let timeout = self.scheduler.at(Some(self.name), when, work)?;
+        self.scheduler.submit(
+            timeout.tagged(self.formatted_tags()).on_node(self.node),
+            done,
+        )
+    }
+
+    /// See `Scheduler::schedule_periodic`.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero.
+    pub fn schedule_periodic<F>(&self, interval: Duration, work: F) -> Result<TimeoutHandle>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let (work, done) = handle::repeated(work);
+        let timeout = self.scheduler.periodic(Some(self.name), interval, work)?;
+        self.scheduler.submit(
+            timeout.tagged(self.formatted_tags()).on_node(self.node),
+            done,
+        )
+    }
+}
+
+impl std::fmt::Debug for Named<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Named")
+            .field("name", &self.name)
+            .field("tags", &self.tags)
+            .field("node", &self.node)
+            .finish()
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::TaskId;
+
+/// Receives an event for every stage of a task's life, see
+/// `Builder::observer`. Every method defaults to doing nothing.
+///
+/// Events are delivered on whichever thread the stage happens on: the
+/// caller's for `on_schedule`, the timekeeper's for `on_fire` and
+/// `on_cancel`, the worker's for `on_start` and `on_execute`. Keep them
+/// short.
+pub trait SchedulerObserver: Send + Sync + 'static {
+    fn on_schedule(&self, _event: &ScheduleEvent) {}
+    fn on_fire(&self, _event: &FireEvent) {}
+    fn on_start(&self, _event: &StartEvent) {}
+    fn on_execute(&self, _event: &ExecuteEvent) {}
+    fn on_cancel(&self, _event: &CancelEvent) {}
+}
+
+/// A task was submitted to the scheduler. Sent before it is handed over,
+/// so it comes ahead of the task's other events.
+#[derive(Clone, Copy, Debug)]
+pub struct ScheduleEvent {
+    /// None for immediate work.
+    pub task: Option<TaskId>,
+    pub name: Option<&'static str>,
+    /// Deadline on the scheduler's clock, None for immediate work.
+    pub deadline: Option<Duration>,
+    pub periodic: bool,
+    /// When it was scheduled, on the scheduler's clock.
+    pub at: Duration,
+}
+
+/// A timeout expired and its work was handed to the worker.
+#[derive(Clone, Copy, Debug)]
+pub struct FireEvent {
+    pub task: TaskId,
+    pub name: Option<&'static str>,
+    pub expected: Duration,
+    pub actual: Duration,
+}
+
+/// The worker is about to run a task.
+#[derive(Clone, Copy, Debug)]
+pub struct StartEvent {
+    /// None for immediate work.
+    pub task: Option<TaskId>,
+    pub name: Option<&'static str>,
+}
+
+/// The worker finished running a task.
+#[derive(Clone, Copy, Debug)]
+pub struct ExecuteEvent {
+    /// None for immediate work.
+    pub task: Option<TaskId>,
+    pub name: Option<&'static str>,
+    pub duration: Duration,
+    pub panicked: bool,
+}
+
+/// A timeout was removed before it fired.
+#[derive(Clone, Copy, Debug)]
+pub struct CancelEvent {
+    pub task: TaskId,
+    pub name: Option<&'static str>,
+    /// When the timekeeper took it out, on the scheduler's clock.
+    pub at: Duration,
+}
+
+/* Everything registered, shared by the scheduler threads */
+#[derive(Clone, Default)]
+pub(crate) struct Observers {
+    observers: Vec<Arc<dyn SchedulerObserver>>,
+}
+
+impl Observers {
+    pub(crate) fn push(&mut self, observer: Arc<dyn SchedulerObserver>) {
+        self.observers.push(observer);
+    }
+
+    pub(crate) fn schedule(&self, event: ScheduleEvent) {
+        for observer in &self.observers {
+            observer.on_schedule(&event);
+        }
+    }
+
+    pub(crate) fn fire(&self, event: FireEvent) {
+        for observer in &self.observers {
+            observer.on_fire(&event);
+        }
+    }
+
+    pub(crate) fn start(&self, event: StartEvent) {
+        for observer in &self.observers {
+            observer.on_start(&event);
+        }
+    }
+
+    pub(crate) fn execute(&self, event: ExecuteEvent) {
+        for observer in &self.observers {
+            observer.on_execute(&event);
+        }
+    }
+
+    pub(crate) fn cancel(&self, event: CancelEvent) {
+        for observer in &self.observers {
+            observer.on_cancel(&event);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use crate::timeout::TimeoutList;
+use crate::TaskId;
+
+/// A timeout waiting in the scheduler, see `Scheduler::pending`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingInfo {
+    pub id: TaskId,
+    /// Name it was scheduled under, see `Scheduler::named`.
+    pub name: Option<&'static str>,
+    /// Time left until it fires, zero if it is due.
+    pub remaining: Duration,
+    /// When it fires, on the scheduler's clock.
+    pub deadline: Duration,
+    /// Its interval if periodic. The delay a one-shot timeout was
+    /// scheduled with is only kept with the `debug-timing` feature.
+    pub delay: Option<Duration>,
+    /// Whether it fires again after this.
+    pub periodic: bool,
+}
+
+/* The list in firing order, `list` being relative to now */
+pub(crate) fn pending_info(list: &TimeoutList) -> Vec<PendingInfo> {
+    let mut remaining = Duration::ZERO;
+
+    list.iter()
+        .map(|t| {
+            remaining = remaining.saturating_add(t.delay);
+            PendingInfo {
+                id: t.id,
+                name: t.name,
+                remaining,
+                deadline: t.deadline,
+                delay: t.initial_delay(),
+                periodic: t.is_periodic(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,123 @@
+/* Grand Central Dispatch queues as a Spawner. libdispatch is part of
+ * libSystem, which every macOS binary links, so there is nothing to add
+ * at build time. Queues are opaque, retained while a spawner refers to
+ * them and safe to hand work to from any thread.
+ */
+use std::ffi::{c_char, c_long, c_ulong, c_void, CString};
+use std::sync::Arc;
+
+use crate::pool::{Pool, PoolTask};
+use crate::spawn::{Spawned, Spawner};
+
+type Queue = *mut c_void;
+
+extern "C" {
+    /* What dispatch_get_main_queue() expands to */
+    static mut _dispatch_main_q: c_void;
+    fn dispatch_get_global_queue(identifier: c_long, flags: c_ulong) -> Queue;
+    fn dispatch_queue_create(label: *const c_char, attr: *mut c_void) -> Queue;
+    fn dispatch_async_f(queue: Queue, context: *mut c_void, work: extern "C" fn(*mut c_void));
+    fn dispatch_retain(object: *mut c_void);
+    fn dispatch_release(object: *mut c_void);
+}
+
+/// The quality of service of a global GCD queue, see `GcdSpawner::global`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Qos {
+    UserInteractive,
+    UserInitiated,
+    #[default]
+    Default,
+    Utility,
+    Background,
+}
+
+impl Qos {
+    fn class(self) -> c_long {
+        match self {
+            Qos::UserInteractive => 0x21,
+            Qos::UserInitiated => 0x19,
+            Qos::Default => 0x15,
+            Qos::Utility => 0x11,
+            Qos::Background => 0x09,
+        }
+    }
+}
+
+struct Retained(Queue);
+
+unsafe impl Send for Retained {}
+unsafe impl Sync for Retained {}
+
+impl Drop for Retained {
+    fn drop(&mut self) {
+        unsafe { dispatch_release(self.0) };
+    }
+}
+
+/// Runs tasks on a GCD queue instead of the worker thread, macOS only.
+/// Futures are polled on the queue too, each wake dispatching the next
+/// poll. On the main queue tasks run on the main thread, for UI callbacks,
+/// once the application runs its main run loop or `dispatch_main`.
+#[derive(Clone)]
+pub struct GcdSpawner {
+    queue: Arc<Retained>,
+}
+
+impl GcdSpawner {
+    pub fn main() -> GcdSpawner {
+        let queue = &raw mut _dispatch_main_q as Queue;
+        unsafe { dispatch_retain(queue) };
+        GcdSpawner::new(queue)
+    }
+
+    /// A global concurrent queue, tasks run on several threads at once.
+    pub fn global(qos: Qos) -> GcdSpawner {
+        let queue = unsafe { dispatch_get_global_queue(qos.class(), 0) };
+        unsafe { dispatch_retain(queue) };
+        GcdSpawner::new(queue)
+    }
+
+    /// A new serial queue named `label`, tasks run one at a time in the
+    /// order they were handed over.
+    pub fn serial(label: &str) -> GcdSpawner {
+        let label = CString::new(label.replace('\0', "")).unwrap_or_default();
+        /* Created retained, a null attr is DISPATCH_QUEUE_SERIAL */
+        let queue = unsafe { dispatch_queue_create(label.as_ptr(), std::ptr::null_mut()) };
+        GcdSpawner::new(queue)
+    }
+
+    fn new(queue: Queue) -> GcdSpawner {
+        GcdSpawner {
+            queue: Arc::new(Retained(queue)),
+        }
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+extern "C" fn run_job(context: *mut c_void) {
+    let job = unsafe { Box::from_raw(context as *mut Job) };
+    job();
+}
+
+impl Pool for GcdSpawner {
+    fn run<F: FnOnce() + Send + 'static>(&self, run: F) {
+        let job: Box<Job> = Box::new(Box::new(run));
+        unsafe { dispatch_async_f(self.queue.0, Box::into_raw(job) as *mut c_void, run_job) };
+    }
+}
+
+impl Spawner for GcdSpawner {
+    fn spawn(&self, task: Spawned) {
+        PoolTask::spawn(self, task);
+    }
+}
+
+impl std::fmt::Debug for GcdSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcdSpawner")
+            .field("queue", &self.queue.0)
+            .finish()
+    }
+}
@@ -0,0 +1,77 @@
+/* Scheduling tweaks for the calling thread. None of them are worth failing
+ * a build over, without the privileges for one it is logged and the thread
+ * carries on as it was.
+ */
+use std::io;
+use std::time::Duration;
+
+use super::Realtime;
+
+pub(crate) fn set_realtime(realtime: Realtime) {
+    let (policy, priority) = match realtime {
+        Realtime::Fifo(priority) => (libc::SCHED_FIFO, priority),
+        Realtime::RoundRobin(priority) => (libc::SCHED_RR, priority),
+    };
+    let (min, max) = unsafe {
+        (
+            libc::sched_get_priority_min(policy),
+            libc::sched_get_priority_max(policy),
+        )
+    };
+    let param = libc::sched_param {
+        sched_priority: i32::from(priority).clamp(min, max),
+    };
+
+    let ret = unsafe { libc::pthread_setschedparam(libc::pthread_self(), policy, &param) };
+    if ret != 0 {
+        warn!(
+            "timekeeper stays on the default scheduling policy, {:?} refused: {}",
+            realtime,
+            io::Error::from_raw_os_error(ret)
+        );
+    }
+}
+
+/* Linux threads have a niceness of their own, though POSIX says it is the
+ * process's
+ */
+pub(crate) fn set_nice(nice: i32) {
+    let ret = unsafe {
+        libc::setpriority(
+            libc::PRIO_PROCESS,
+            libc::gettid() as libc::id_t,
+            nice.clamp(-20, 19),
+        )
+    };
+    if ret != 0 {
+        warn!(
+            "worker keeps its niceness, {} refused: {}",
+            nice,
+            io::Error::last_os_error()
+        );
+    }
+}
+
+pub(crate) fn set_timer_slack(slack: Duration) {
+    /* 0 would mean going back to the inherited default */
+    let nanos = slack.as_nanos().clamp(1, libc::c_ulong::MAX as u128) as libc::c_ulong;
+    let ret = unsafe { libc::prctl(libc::PR_SET_TIMERSLACK, nanos, 0, 0, 0) };
+    if ret != 0 {
+        warn!(
+            "timekeeper keeps its default timer slack, {:?} refused: {}",
+            slack,
+            io::Error::last_os_error()
+        );
+    }
+}
+
+/* Back to what the thread started out with */
+pub(crate) fn reset_timer_slack() {
+    let ret = unsafe { libc::prctl(libc::PR_SET_TIMERSLACK, 0 as libc::c_ulong, 0, 0, 0) };
+    if ret != 0 {
+        warn!(
+            "timekeeper keeps its timer slack, resetting it refused: {}",
+            io::Error::last_os_error()
+        );
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(all(target_os = "macos", feature = "gcd"))]
+mod gcd;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(all(target_os = "linux", feature = "numa"))]
+mod numa;
+#[cfg(target_os = "linux")]
+mod systemd;
+#[cfg(all(target_os = "linux", feature = "user-events"))]
+mod user_events;
+#[cfg(all(windows, feature = "windows-hires"))]
+mod windows;
+
+#[cfg(all(target_os = "linux", feature = "user-events"))]
+pub(crate) use self::user_events::task_run;
+
+#[cfg(all(target_os = "macos", feature = "gcd"))]
+pub use self::gcd::{GcdSpawner, Qos};
+#[cfg(target_os = "linux")]
+pub(crate) use self::linux::{reset_timer_slack, set_nice, set_realtime, set_timer_slack};
+#[cfg(all(target_os = "linux", feature = "numa"))]
+pub use self::numa::{NumaNode, NumaSpawner};
+#[cfg(target_os = "linux")]
+pub(crate) use self::systemd::watchdog as systemd_watchdog;
+#[cfg(all(windows, feature = "windows-hires"))]
+pub(crate) use self::windows::TimerPeriod;
+
+/// A real-time scheduling policy for the timekeeper thread, see
+/// `Builder::realtime`. Priorities are clamped to what the kernel allows
+/// for the policy, 1 to 99 on Linux.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Realtime {
+    /// SCHED_FIFO, runs until it blocks or something of higher priority
+    /// wants the CPU.
+    Fifo(u8),
+    /// SCHED_RR, like `Fifo` but shares its CPU with threads of the same
+    /// priority in time slices.
+    RoundRobin(u8),
+}
+
+/* No real-time scheduling to ask for */
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn set_realtime(realtime: Realtime) {
+    warn!(
+        "{:?} is only supported on Linux, the timekeeper keeps its default scheduling",
+        realtime
+    );
+}
+
+/* Per thread niceness is a Linux notion */
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn set_nice(nice: i32) {
+    warn!(
+        "niceness {} is only supported on Linux, the worker keeps its own",
+        nice
+    );
+}
+
+/* Timer slack is a Linux notion */
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn set_timer_slack(_slack: std::time::Duration) {}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn reset_timer_slack() {}
+
+/* No systemd to keep happy */
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn systemd_watchdog<H>(_scheduler: &crate::Scheduler, _health: H)
+where
+    H: Fn() -> crate::Health + Send + 'static,
+{
+}
+
+/* No global timer period to manage elsewhere */
+#[cfg(not(all(windows, feature = "windows-hires")))]
+pub(crate) struct TimerPeriod;
+
+#[cfg(not(all(windows, feature = "windows-hires")))]
+impl TimerPeriod {
+    pub(crate) fn new() -> TimerPeriod {
+        TimerPeriod
+    }
+
+    pub(crate) fn update(&mut self, _next: Option<std::time::Duration>) {}
+}
+
+/* Nowhere to report task runs to profilers */
+#[cfg(not(all(target_os = "linux", feature = "user-events")))]
+#[inline(always)]
+pub(crate) fn task_run(_label: crate::timeout::Label, _tags: Option<&str>) {}
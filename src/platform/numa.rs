@@ -0,0 +1,295 @@
+/* NUMA nodes as a Spawner, a pool of threads per node pinned to its CPUs.
+ * The topology is probed from sysfs the way hwloc does it on Linux, each
+ * online node's cpulist, narrowed down to the CPUs we may run on. Memory
+ * needs no binding: under the kernel's default first-touch policy what a
+ * task allocates lands on the node it runs on. A kernel without NUMA has
+ * no node directory, everything is then one node.
+ *
+ * Node threads share a channel, so whichever is free takes the next job.
+ * Spawns from a node thread stay on its node, so the polls of a future
+ * and what it spawns follow the first hop.
+ */
+use std::cell::Cell;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use crate::pool::{Pool, PoolTask};
+use crate::spawn::{Spawned, Spawner};
+
+const NODES: &str = "/sys/devices/system/node";
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/* Tells spawners apart for CURRENT */
+static SPAWNERS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /* The spawner of the node thread we are on and its index into nodes */
+    static CURRENT: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// A NUMA node as probed by `NumaSpawner`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumaNode {
+    id: usize,
+    cpus: Vec<usize>,
+}
+
+impl NumaNode {
+    /// The kernel's number for the node, what `Named::numa_node` takes.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// The node's CPUs this process may run on, ascending.
+    pub fn cpus(&self) -> &[usize] {
+        &self.cpus
+    }
+}
+
+/// Runs tasks on per-NUMA-node thread pools instead of the worker thread,
+/// so memory-heavy jobs run next to their data. Tasks scheduled with a
+/// `Named::numa_node` hint run on that node, others take turns across
+/// nodes. Futures are polled on the node they were first spawned on. Linux
+/// only, elsewhere see `GcdSpawner` or `RayonSpawner`.
+#[derive(Clone)]
+pub struct NumaSpawner {
+    id: usize,
+    nodes: Arc<[NodePool]>,
+    next: Arc<AtomicUsize>,
+}
+
+#[derive(Clone)]
+struct NodePool {
+    node: NumaNode,
+    jobs: Sender<Job>,
+}
+
+impl Pool for NodePool {
+    fn run<F: FnOnce() + Send + 'static>(&self, run: F) {
+        /* The threads only go once every sender has */
+        let _ = self.jobs.send(Box::new(run));
+    }
+}
+
+impl NumaSpawner {
+    /// Probe the topology and start a thread for every CPU of every node,
+    /// no more in all than `std::thread::available_parallelism` so a
+    /// cgroup CPU quota is kept to. Nodes then get threads in proportion
+    /// to their CPUs, at least one each.
+    pub fn new() -> io::Result<NumaSpawner> {
+        NumaSpawner::start(None)
+    }
+
+    /// Like `new`, with `threads` threads per node, at least one.
+    pub fn with_threads(threads: usize) -> io::Result<NumaSpawner> {
+        NumaSpawner::start(Some(threads.max(1)))
+    }
+
+    fn start(threads: Option<usize>) -> io::Result<NumaSpawner> {
+        let id = SPAWNERS.fetch_add(1, Ordering::Relaxed);
+        let topology = topology()?;
+        let shares = match threads {
+            Some(threads) => vec![threads; topology.len()],
+            None => shares(&topology),
+        };
+
+        let mut nodes = Vec::new();
+        for (index, (node, threads)) in topology.into_iter().zip(shares).enumerate() {
+            let (jobs, receiver) = channel();
+            let receiver = Arc::new(Mutex::new(receiver));
+            for _ in 0..threads {
+                let receiver = receiver.clone();
+                let cpus = node.cpus.clone();
+                thread::Builder::new()
+                    .name(format!("event_scheduler numa{}", node.id))
+                    .spawn(move || node_thread((id, index), &cpus, &receiver))?;
+            }
+            nodes.push(NodePool { node, jobs });
+        }
+
+        Ok(NumaSpawner {
+            id,
+            nodes: nodes.into(),
+            next: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// The nodes tasks are spread across, in the order of their ids.
+    pub fn nodes(&self) -> impl Iterator<Item = &NumaNode> {
+        self.nodes.iter().map(|pool| &pool.node)
+    }
+
+    fn pool(&self) -> &NodePool {
+        let index = match CURRENT.get() {
+            Some((spawner, index)) if spawner == self.id => index,
+            _ => self.next.fetch_add(1, Ordering::Relaxed) % self.nodes.len(),
+        };
+        &self.nodes[index]
+    }
+}
+
+impl Spawner for NumaSpawner {
+    fn spawn(&self, task: Spawned) {
+        PoolTask::spawn(self.pool(), task);
+    }
+
+    /* Unknown nodes, like one without CPUs of ours, get no say */
+    fn spawn_on_node(&self, task: Spawned, node: usize) {
+        match self.nodes.iter().find(|pool| pool.node.id == node) {
+            Some(pool) => PoolTask::spawn(pool, task),
+            None => self.spawn(task),
+        }
+    }
+}
+
+impl std::fmt::Debug for NumaSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NumaSpawner")
+            .field("nodes", &self.nodes().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn node_thread(current: (usize, usize), cpus: &[usize], receiver: &Mutex<Receiver<Job>>) {
+    CURRENT.set(Some(current));
+    pin(cpus);
+    loop {
+        let job = receiver
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
+
+fn pin(cpus: &[usize]) {
+    let ret = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if ret != 0 {
+        warn!(
+            "numa thread runs unpinned, CPUs {:?} refused: {}",
+            cpus,
+            io::Error::last_os_error()
+        );
+    }
+}
+
+/* Threads per node for NumaSpawner::new. Rounding down leaves a few of the
+ * total over, those go to the first nodes with CPUs to spare.
+ */
+fn shares(nodes: &[NumaNode]) -> Vec<usize> {
+    let cpus: usize = nodes.iter().map(|node| node.cpus.len()).sum();
+    let total = thread::available_parallelism()
+        .map_or(cpus, NonZeroUsize::get)
+        .min(cpus);
+
+    let mut shares: Vec<usize> = nodes
+        .iter()
+        .map(|node| (node.cpus.len() * total / cpus).max(1))
+        .collect();
+    let mut left = total.saturating_sub(shares.iter().sum());
+    for (share, node) in shares.iter_mut().zip(nodes) {
+        if left == 0 {
+            break;
+        }
+        if *share < node.cpus.len() {
+            *share += 1;
+            left -= 1;
+        }
+    }
+    shares
+}
+
+/* Nodes with CPUs we may run on, by id */
+fn topology() -> io::Result<Vec<NumaNode>> {
+    let allowed = allowed_cpus()?;
+    let entries = match fs::read_dir(NODES) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(vec![NumaNode {
+                id: 0,
+                cpus: allowed,
+            }]);
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut nodes = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(id) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("node"))
+            .and_then(|id| id.parse().ok())
+        else {
+            continue;
+        };
+        let list = fs::read_to_string(entry.path().join("cpulist"))?;
+        let cpus: Vec<usize> = parse_cpulist(&list)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unreadable cpulist of node {}: {:?}", id, list.trim()),
+                )
+            })?
+            .into_iter()
+            .filter(|cpu| allowed.contains(cpu))
+            .collect();
+        if !cpus.is_empty() {
+            nodes.push(NumaNode { id, cpus });
+        }
+    }
+    nodes.sort_by_key(|node| node.id);
+
+    /* Not one of the CPUs we may use is on a listed node */
+    if nodes.is_empty() {
+        nodes.push(NumaNode {
+            id: 0,
+            cpus: allowed,
+        });
+    }
+    Ok(nodes)
+}
+
+fn allowed_cpus() -> io::Result<Vec<usize>> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+            .collect())
+    }
+}
+
+/* As the kernel prints CPU lists, `0-3,8,10-11`, empty for none */
+fn parse_cpulist(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        let (first, last): (usize, usize) = match range.split_once('-') {
+            Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+            None => {
+                let cpu = range.parse().ok()?;
+                (cpu, cpu)
+            }
+        };
+        cpus.extend(first..=last);
+    }
+    Some(cpus)
+}
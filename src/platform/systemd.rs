@@ -0,0 +1,89 @@
+/* The systemd watchdog keepalive, without libsystemd. systemd passes the
+ * timeout in WATCHDOG_USEC and, as with every sd_notify message, where to
+ * send it in NOTIFY_SOCKET: a datagram socket path, abstract when it
+ * starts with '@'. Pinging at half the timeout is what sd_watchdog_enabled
+ * recommends.
+ */
+use std::env;
+use std::io;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::process;
+use std::time::Duration;
+
+use crate::health::Health;
+use crate::scheduler::Scheduler;
+
+struct Notify {
+    socket: UnixDatagram,
+    addr: SocketAddr,
+}
+
+impl Notify {
+    fn open() -> io::Result<Option<Notify>> {
+        let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+            return Ok(None);
+        };
+        let addr = match path.as_bytes().strip_prefix(b"@") {
+            Some(name) => SocketAddr::from_abstract_name(name)?,
+            None => SocketAddr::from_pathname(&path)?,
+        };
+
+        Ok(Some(Notify {
+            socket: UnixDatagram::unbound()?,
+            addr,
+        }))
+    }
+
+    fn send(&self, state: &str) -> io::Result<()> {
+        self.socket
+            .send_to_addr(state.as_bytes(), &self.addr)
+            .map(drop)
+    }
+}
+
+/* Only when systemd watches this very process */
+fn interval() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(process::id()) {
+            return None;
+        }
+    }
+
+    Some(Duration::from_micros(usec) / 2).filter(|interval| !interval.is_zero())
+}
+
+pub(crate) fn watchdog<H>(scheduler: &Scheduler, health: H)
+where
+    H: Fn() -> Health + Send + 'static,
+{
+    let Some(interval) = interval() else {
+        return;
+    };
+    let notify = match Notify::open() {
+        Ok(Some(notify)) => notify,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("no systemd watchdog, NOTIFY_SOCKET unusable: {}", e);
+            return;
+        }
+    };
+
+    let pinged = scheduler
+        .named("systemd watchdog")
+        .schedule_periodic(interval, move || {
+            let health = health();
+            if !health.is_healthy() {
+                warn!("holding back the systemd watchdog ping: {:?}", health);
+                return;
+            }
+            if let Err(e) = notify.send("WATCHDOG=1") {
+                warn!("systemd watchdog ping failed: {}", e);
+            }
+        });
+    if let Err(e) = pinged {
+        warn!("no systemd watchdog: {}", e);
+    }
+}
@@ -0,0 +1,99 @@
+/* Linux user_events: one trace event per task run, carrying its id, name
+ * and tags, so `perf record -e user_events:event_scheduler_task` lines
+ * scheduler activity up with the samples in a flamegraph.
+ *
+ * Registered on first use. Without tracefs, or without the permission to
+ * write to it, nothing is emitted. The kernel flips the enable bit while a
+ * tracer is attached, until then a run costs one relaxed load.
+ */
+use std::fs::OpenOptions;
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+
+use crate::timeout::Label;
+
+const DATA: [&str; 2] = [
+    "/sys/kernel/tracing/user_events_data",
+    "/sys/kernel/debug/tracing/user_events_data",
+];
+const FORMAT: &[u8] = b"event_scheduler_task u64 id; char[32] name; char[128] tags\0";
+const NAME_LEN: usize = 32;
+const TAGS_LEN: usize = 128;
+
+/* _IOWR('*', 0, struct user_reg *) */
+const DIAG_IOCSREG: u64 = 0xc008_2a00;
+
+#[repr(C, packed)]
+struct UserReg {
+    size: u32,
+    enable_bit: u8,
+    enable_size: u8,
+    flags: u16,
+    enable_addr: u64,
+    name_args: u64,
+    write_index: u32,
+}
+
+const _: () = assert!(std::mem::size_of::<UserReg>() == 28);
+
+/* Bit 0 is set by the kernel while the event is enabled. Has to outlive
+ * the registration, so a static.
+ */
+static ENABLED: AtomicU32 = AtomicU32::new(0);
+static EVENT: OnceLock<Option<(RawFd, u32)>> = OnceLock::new();
+
+fn register() -> Option<(RawFd, u32)> {
+    let file = DATA
+        .iter()
+        .find_map(|path| OpenOptions::new().write(true).open(path).ok())?;
+
+    let mut reg = UserReg {
+        size: std::mem::size_of::<UserReg>() as u32,
+        enable_bit: 0,
+        enable_size: 4,
+        flags: 0,
+        enable_addr: &ENABLED as *const AtomicU32 as u64,
+        name_args: FORMAT.as_ptr() as u64,
+        write_index: 0,
+    };
+    if unsafe { libc::ioctl(file.as_raw_fd(), DIAG_IOCSREG as _, &mut reg) } < 0 {
+        return None;
+    }
+
+    /* Kept open for good, closing it would unregister */
+    Some((file.into_raw_fd(), reg.write_index))
+}
+
+fn fill(field: &mut [u8], value: &str) {
+    /* Leaves at least one NUL */
+    let len = value.len().min(field.len() - 1);
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+}
+
+pub(crate) fn task_run(label: Label, tags: Option<&str>) {
+    let Some((fd, write_index)) = *EVENT.get_or_init(register) else {
+        return;
+    };
+    if ENABLED.load(Ordering::Relaxed) & 1 == 0 {
+        return;
+    }
+
+    let mut payload = [0u8; 8 + NAME_LEN + TAGS_LEN];
+    let id = label.id.map_or(u64::MAX, |id| id.as_u64());
+    payload[..8].copy_from_slice(&id.to_ne_bytes());
+    fill(&mut payload[8..8 + NAME_LEN], label.name.unwrap_or(""));
+    fill(&mut payload[8 + NAME_LEN..], tags.unwrap_or(""));
+
+    let iov = [
+        libc::iovec {
+            iov_base: &write_index as *const u32 as *mut libc::c_void,
+            iov_len: 4,
+        },
+        libc::iovec {
+            iov_base: payload.as_ptr() as *mut libc::c_void,
+            iov_len: payload.len(),
+        },
+    ];
+    unsafe { libc::writev(fd, iov.as_ptr(), iov.len() as libc::c_int) };
+}
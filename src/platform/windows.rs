@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+/* Waits shorter than this get the 1ms system timer period, longer ones are
+ * fine with the default ~15.6ms granularity.
+ */
+const HIRES_THRESHOLD: Duration = Duration::from_millis(100);
+const HIRES_PERIOD_MS: u32 = 1;
+
+#[link(name = "winmm")]
+extern "system" {
+    fn timeBeginPeriod(period: u32) -> u32;
+    fn timeEndPeriod(period: u32) -> u32;
+}
+
+/* Holds timeBeginPeriod only while a short timeout is pending, the raised
+ * period is system wide and costs power.
+ */
+pub(crate) struct TimerPeriod {
+    raised: bool,
+}
+
+impl TimerPeriod {
+    pub(crate) fn new() -> TimerPeriod {
+        TimerPeriod { raised: false }
+    }
+
+    pub(crate) fn update(&mut self, next: Option<Duration>) {
+        let want = next.is_some_and(|delay| delay < HIRES_THRESHOLD);
+
+        if want && !self.raised {
+            self.raised = unsafe { timeBeginPeriod(HIRES_PERIOD_MS) } == 0;
+        } else if !want && self.raised {
+            unsafe { timeEndPeriod(HIRES_PERIOD_MS) };
+            self.raised = false;
+        }
+    }
+}
+
+impl Drop for TimerPeriod {
+    fn drop(&mut self) {
+        self.update(None);
+    }
+}
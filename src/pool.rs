@@ -0,0 +1,81 @@
+/* Futures polled on thread pools that only run closures, rayon's and GCD's
+ * queues. Each wake hands the pool a closure polling the future once more.
+ * A wake during a poll may have a second thread pick it up, which then
+ * only marks it for the poller to go again.
+ */
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Wake, Waker};
+
+use crate::spawn::Spawned;
+
+pub(crate) trait Pool: Clone + Send + Sync + 'static {
+    fn run<F: FnOnce() + Send + 'static>(&self, run: F);
+}
+
+pub(crate) struct PoolTask<P: Pool> {
+    state: Mutex<State>,
+    pool: P,
+}
+
+enum State {
+    Idle(Spawned),
+    Running,
+    /* Woken while running */
+    Notified,
+    Done,
+}
+
+impl<P: Pool> PoolTask<P> {
+    pub(crate) fn spawn(pool: &P, task: Spawned) {
+        let task = PoolTask {
+            state: Mutex::new(State::Idle(task)),
+            pool: pool.clone(),
+        };
+        Arc::new(task).schedule();
+    }
+
+    fn schedule(self: Arc<Self>) {
+        let pool = self.pool.clone();
+        pool.run(move || self.poll());
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn poll(self: Arc<Self>) {
+        let mut state = self.lock();
+        let mut future = match std::mem::replace(&mut *state, State::Running) {
+            State::Idle(future) => future,
+            State::Running | State::Notified => {
+                *state = State::Notified;
+                return;
+            }
+            State::Done => {
+                *state = State::Done;
+                return;
+            }
+        };
+        drop(state);
+
+        let waker = Waker::from(self.clone());
+        let mut cx = Context::from_waker(&waker);
+        while future.as_mut().poll(&mut cx).is_pending() {
+            let mut state = self.lock();
+            match *state {
+                State::Notified => *state = State::Running,
+                _ => {
+                    *state = State::Idle(future);
+                    return;
+                }
+            }
+        }
+        *self.lock() = State::Done;
+    }
+}
+
+impl<P: Pool> Wake for PoolTask<P> {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+}
@@ -0,0 +1,107 @@
+/* Builder::power_mode and Scheduler::set_power_mode. The mode is shared
+ * with the timekeeper, which applies it to itself when it sees it change:
+ * timer slack is per thread, so it can't be set from the caller's side.
+ * Switching sends the timekeeper a tick for that to happen right away.
+ */
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::platform;
+use crate::sync::atomic::{AtomicBool, Ordering};
+
+/* What PowerMode::Efficient rounds deadlines up to and lets the kernel
+ * defer them by
+ */
+const EFFICIENT_RESOLUTION: Duration = Duration::from_millis(10);
+const EFFICIENT_SLACK: Duration = Duration::from_millis(5);
+
+/// How the scheduler trades timing precision for wakeups, see
+/// `Builder::power_mode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PowerMode {
+    /// The builder's resolution, timer slack and overhead compensation as
+    /// configured.
+    #[default]
+    Precise,
+    /// For laptops on battery and other devices that would rather sleep.
+    /// Deadlines round up to at least 10ms, so timeouts close together
+    /// share a wakeup, the kernel may defer those by another 5ms to line
+    /// them up with other processes', and the timekeeper never wakes early
+    /// to spin out the rest. On Windows the system timer period is no
+    /// longer raised for short timeouts either.
+    Efficient,
+}
+
+pub(crate) struct Power {
+    efficient: AtomicBool,
+    /* As configured on the builder, what Precise goes back to */
+    resolution: Duration,
+    slack: Option<Duration>,
+}
+
+impl Power {
+    pub(crate) fn new(mode: PowerMode, resolution: Duration, slack: Option<Duration>) -> Power {
+        Power {
+            efficient: AtomicBool::new(mode == PowerMode::Efficient),
+            resolution,
+            slack,
+        }
+    }
+
+    pub(crate) fn mode(&self) -> PowerMode {
+        if self.efficient.load(Ordering::Relaxed) {
+            PowerMode::Efficient
+        } else {
+            PowerMode::Precise
+        }
+    }
+
+    /* Whether it changed */
+    pub(crate) fn set(&self, mode: PowerMode) -> bool {
+        let efficient = mode == PowerMode::Efficient;
+        self.efficient.swap(efficient, Ordering::Relaxed) != efficient
+    }
+
+    /* What new deadlines are rounded up to */
+    pub(crate) fn resolution(&self) -> Duration {
+        match self.mode() {
+            PowerMode::Precise => self.resolution,
+            PowerMode::Efficient => self.resolution.max(EFFICIENT_RESOLUTION),
+        }
+    }
+}
+
+/* The timekeeper's side, remembers what it applied to its thread */
+pub(crate) struct PowerState {
+    power: Arc<Power>,
+    applied: Option<PowerMode>,
+}
+
+impl PowerState {
+    pub(crate) fn new(power: Arc<Power>) -> PowerState {
+        PowerState {
+            power,
+            applied: None,
+        }
+    }
+
+    /* Called from the timekeeper thread only */
+    pub(crate) fn update(&mut self) -> PowerMode {
+        let mode = self.power.mode();
+        if self.applied == Some(mode) {
+            return mode;
+        }
+
+        match (mode, self.power.slack) {
+            (PowerMode::Efficient, slack) => {
+                platform::set_timer_slack(slack.unwrap_or_default().max(EFFICIENT_SLACK))
+            }
+            (PowerMode::Precise, Some(slack)) => platform::set_timer_slack(slack),
+            /* Nothing to undo when starting out precise */
+            (PowerMode::Precise, None) if self.applied.is_none() => {}
+            (PowerMode::Precise, None) => platform::reset_timer_slack(),
+        }
+        self.applied = Some(mode);
+        mode
+    }
+}
@@ -0,0 +1,194 @@
+/* Python bindings, an extension module named event_scheduler. Build it
+ * with
+ *
+ *     cargo rustc --lib --release --features python --crate-type cdylib
+ *
+ * and import libevent_scheduler.so renamed to event_scheduler.so, or with
+ * maturin. Callables run on the worker thread, which takes the GIL for
+ * each call only. Calls that block on the worker let go of the GIL while
+ * they wait, or a running callable could never finish.
+ */
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::error::SchedError;
+use crate::handle::TimeoutHandle;
+use crate::scheduler::Scheduler;
+
+type Outcome = PyResult<Py<PyAny>>;
+
+fn error(e: SchedError) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+fn seconds(seconds: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(seconds).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// A scheduler with the default configuration.
+#[pyclass(name = "Scheduler", module = "event_scheduler")]
+struct PyScheduler {
+    /* Taken by shutdown */
+    scheduler: Mutex<Option<Scheduler>>,
+}
+
+impl PyScheduler {
+    fn with<T>(&self, f: impl FnOnce(&Scheduler) -> Result<T, SchedError>) -> PyResult<T> {
+        let scheduler = self
+            .scheduler
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let scheduler = scheduler
+            .as_ref()
+            .ok_or_else(|| error(SchedError::ShutDown))?;
+        f(scheduler).map_err(error)
+    }
+}
+
+#[pymethods]
+impl PyScheduler {
+    #[new]
+    fn new() -> PyScheduler {
+        PyScheduler {
+            scheduler: Mutex::new(Some(Scheduler::new())),
+        }
+    }
+
+    /// Call `callback` on the worker as soon as it is free.
+    fn schedule(&self, callback: Py<PyAny>) -> PyResult<()> {
+        self.with(|scheduler| {
+            scheduler.schedule(move || {
+                Python::attach(|py| {
+                    if let Err(e) = callback.call0(py) {
+                        e.print(py);
+                    }
+                })
+            })
+        })
+    }
+
+    /// Call `callback` once `delay` seconds have passed. Its return value,
+    /// or the exception it raised, is what waiting on the handle gives.
+    fn schedule_delayed(&self, delay: f64, callback: Py<PyAny>) -> PyResult<PyTimeoutHandle> {
+        let delay = seconds(delay)?;
+        let handle = self.with(|scheduler| {
+            scheduler.schedule_delayed(delay, move || Python::attach(|py| callback.call0(py)))
+        })?;
+        Ok(PyTimeoutHandle {
+            handle: Handle::Once(handle),
+        })
+    }
+
+    /// Call `callback` every `interval` seconds until cancelled. Exceptions
+    /// are printed, the next tick still comes. A zero interval is a
+    /// ValueError.
+    fn schedule_periodic(&self, interval: f64, callback: Py<PyAny>) -> PyResult<PyTimeoutHandle> {
+        let interval = seconds(interval)?;
+        if interval.is_zero() {
+            return Err(PyValueError::new_err("periodic interval must not be zero"));
+        }
+        let handle = self.with(|scheduler| {
+            scheduler.schedule_periodic(interval, move || {
+                Python::attach(|py| {
+                    if let Err(e) = callback.call0(py) {
+                        e.print(py);
+                    }
+                })
+            })
+        })?;
+        Ok(PyTimeoutHandle {
+            handle: Handle::Periodic(handle),
+        })
+    }
+
+    /// Stop taking work and wait for the worker to finish what it was
+    /// handed. Pending timeouts never fire. Does nothing the second time.
+    fn shutdown(&self, py: Python<'_>) -> PyResult<()> {
+        let scheduler = self
+            .scheduler
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        match scheduler {
+            Some(scheduler) => py.detach(|| scheduler.join()).map_err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+enum Handle {
+    Once(TimeoutHandle<Outcome>),
+    Periodic(TimeoutHandle),
+}
+
+/// Refers to a scheduled callback, see `Scheduler.schedule_delayed`.
+#[pyclass(name = "TimeoutHandle", module = "event_scheduler")]
+struct PyTimeoutHandle {
+    handle: Handle,
+}
+
+#[pymethods]
+impl PyTimeoutHandle {
+    #[getter]
+    fn id(&self) -> u64 {
+        match &self.handle {
+            Handle::Once(handle) => handle.id().as_u64(),
+            Handle::Periodic(handle) => handle.id().as_u64(),
+        }
+    }
+
+    /// Remove the timeout if it has not fired yet.
+    fn cancel(&self) -> PyResult<()> {
+        match &self.handle {
+            Handle::Once(handle) => handle.cancel(),
+            Handle::Periodic(handle) => handle.cancel(),
+        }
+        .map_err(error)
+    }
+
+    fn is_finished(&self) -> bool {
+        match &self.handle {
+            Handle::Once(handle) => handle.is_finished(),
+            Handle::Periodic(handle) => handle.is_finished(),
+        }
+    }
+
+    /// Wait for what the callback returned, re-raising its exception.
+    /// Raises `TimeoutError` if `timeout` seconds pass first. Periodic
+    /// timeouts return None once cancelled.
+    #[pyo3(signature = (timeout = None))]
+    fn wait(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Py<PyAny>> {
+        let timeout = timeout.map(seconds).transpose()?;
+        match &self.handle {
+            Handle::Once(handle) => {
+                let result = py.detach(|| match timeout {
+                    Some(timeout) => handle.wait_timeout(timeout),
+                    None => Some(handle.wait()),
+                });
+                let result = result.ok_or_else(|| PyTimeoutError::new_err("timeout not done"))?;
+                result.map_err(error)?
+            }
+            Handle::Periodic(handle) => {
+                let result = py.detach(|| match timeout {
+                    Some(timeout) => handle.wait_timeout(timeout),
+                    None => Some(handle.wait()),
+                });
+                match result {
+                    None => Err(PyTimeoutError::new_err("timeout not done")),
+                    Some(Ok(())) | Some(Err(SchedError::Cancelled)) => Ok(py.None()),
+                    Some(Err(e)) => Err(error(e)),
+                }
+            }
+        }
+    }
+}
+
+#[pymodule]
+fn event_scheduler(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyScheduler>()?;
+    module.add_class::<PyTimeoutHandle>()?;
+    Ok(())
+}
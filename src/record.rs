@@ -0,0 +1,337 @@
+/* Scheduling calls captured by an observer, to be played back against a
+ * fresh scheduler. Closures can't be captured, so replay asks the caller
+ * for the work of every call. A recording is written one call per line:
+ *
+ *   # event_scheduler recording v1
+ *   <at_ns> schedule <task|-> <immediate|once|periodic> <delay_ns> [name]
+ *   <at_ns> cancel <task> [name]
+ *
+ * Times are relative to the first call recorded. The name takes up the
+ * rest of the line, so it may contain spaces but not line breaks.
+ */
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::observer::{CancelEvent, ScheduleEvent, SchedulerObserver};
+use crate::{Clock, MockClock, Scheduler, SimScheduler, TaskId, TimeoutHandle};
+
+const HEADER: &str = "# event_scheduler recording v1";
+
+/// What a recorded call asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallKind {
+    Immediate,
+    /// A one-shot timeout due `delay` after the call.
+    Once {
+        delay: Duration,
+    },
+    Periodic {
+        interval: Duration,
+    },
+    Cancel,
+}
+
+/// One scheduling call, see `Recorder`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedCall {
+    /// Since the first call in the recording.
+    pub at: Duration,
+    /// The id it was given or cancelled, None for immediate work.
+    pub task: Option<TaskId>,
+    pub name: Option<String>,
+    pub kind: CallKind,
+}
+
+/// An observer capturing every schedule and cancel, see
+/// `Builder::observer`. Clones share the recording.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    origin: Option<Duration>,
+    calls: Vec<RecordedCall>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    /// Everything recorded so far.
+    pub fn recording(&self) -> Recording {
+        Recording {
+            calls: self.lock().calls.clone(),
+        }
+    }
+
+    /// Start over, the next call recorded is at zero.
+    pub fn clear(&self) {
+        *self.lock() = Inner::default();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn push(&self, at: Duration, task: Option<TaskId>, name: Option<&str>, kind: CallKind) {
+        let mut inner = self.lock();
+        let origin = *inner.origin.get_or_insert(at);
+        inner.calls.push(RecordedCall {
+            at: at.saturating_sub(origin),
+            task,
+            name: name.map(String::from),
+            kind,
+        });
+    }
+}
+
+impl SchedulerObserver for Recorder {
+    fn on_schedule(&self, event: &ScheduleEvent) {
+        let kind = match event.deadline {
+            None => CallKind::Immediate,
+            Some(deadline) if event.periodic => CallKind::Periodic {
+                interval: deadline.saturating_sub(event.at),
+            },
+            Some(deadline) => CallKind::Once {
+                delay: deadline.saturating_sub(event.at),
+            },
+        };
+        self.push(event.at, event.task, event.name, kind);
+    }
+
+    fn on_cancel(&self, event: &CancelEvent) {
+        self.push(event.at, Some(event.task), event.name, CallKind::Cancel);
+    }
+}
+
+/// Calls captured by a `Recorder`, in the order they were made.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub calls: Vec<RecordedCall>,
+}
+
+impl Recording {
+    /// Write the recording as text, to be read back with `read_from`.
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "{}", HEADER)?;
+        for call in &self.calls {
+            let task = call.task.map(|t| t.as_u64().to_string());
+            let task = task.as_deref().unwrap_or("-");
+            let at = call.at.as_nanos();
+            match call.kind {
+                CallKind::Immediate => write!(out, "{} schedule {} immediate 0", at, task)?,
+                CallKind::Once { delay } => {
+                    write!(out, "{} schedule {} once {}", at, task, delay.as_nanos())?
+                }
+                CallKind::Periodic { interval } => write!(
+                    out,
+                    "{} schedule {} periodic {}",
+                    at,
+                    task,
+                    interval.as_nanos()
+                )?,
+                CallKind::Cancel => write!(out, "{} cancel {}", at, task)?,
+            }
+            match &call.name {
+                Some(name) => writeln!(out, " {}", name)?,
+                None => writeln!(out)?,
+            }
+        }
+        out.flush()
+    }
+
+    pub fn read_from<R: BufRead>(input: R) -> io::Result<Recording> {
+        let mut calls = Vec::new();
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let call = parse(&line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("bad recording line {}: {}", i + 1, line),
+                )
+            })?;
+            calls.push(call);
+        }
+        Ok(Recording { calls })
+    }
+
+    /// Replay against `scheduler` in real time, sleeping until each call
+    /// is due. `work` makes the work for every call scheduling some.
+    pub fn replay<F, W>(&self, scheduler: &Scheduler, work: F) -> Result<()>
+    where
+        F: FnMut(&RecordedCall) -> W,
+        W: FnMut() + Send + 'static,
+    {
+        let start = Instant::now();
+        self.replay_scheduler(scheduler, work, |at| {
+            thread::sleep((start + at).saturating_duration_since(Instant::now()));
+        })
+    }
+
+    /// Replay against a `scheduler` built on `clock`, advancing the clock
+    /// to each call instead of waiting for it.
+    pub fn replay_mock<F, W>(&self, scheduler: &Scheduler, clock: &MockClock, work: F) -> Result<()>
+    where
+        F: FnMut(&RecordedCall) -> W,
+        W: FnMut() + Send + 'static,
+    {
+        let start = clock.now();
+        self.replay_scheduler(scheduler, work, |at| {
+            let now = clock.now();
+            clock.advance((start + at).saturating_sub(now));
+        })
+    }
+
+    /// Replay against `sim`, advancing its virtual time to each call and
+    /// running whatever comes due on the way.
+    pub fn replay_sim<F, W>(&self, sim: &SimScheduler, mut work: F)
+    where
+        F: FnMut(&RecordedCall) -> W,
+        W: FnMut() + 'static,
+    {
+        let start = sim.now();
+        let mut ids = HashMap::new();
+        for call in &self.calls {
+            sim.advance_to(start + call.at);
+            match call.kind {
+                CallKind::Immediate => sim.schedule(work(call)),
+                CallKind::Once { delay } => {
+                    let id = sim.schedule_delayed(delay, work(call));
+                    ids.extend(call.task.map(|task| (task, id)));
+                }
+                CallKind::Periodic { interval } => {
+                    let id = sim.schedule_periodic(interval, work(call));
+                    ids.extend(call.task.map(|task| (task, id)));
+                }
+                CallKind::Cancel => {
+                    if let Some(id) = call.task.and_then(|task| ids.remove(&task)) {
+                        sim.cancel(id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn replay_scheduler<F, W, S>(
+        &self,
+        scheduler: &Scheduler,
+        mut work: F,
+        mut wait: S,
+    ) -> Result<()>
+    where
+        F: FnMut(&RecordedCall) -> W,
+        W: FnMut() + Send + 'static,
+        S: FnMut(Duration),
+    {
+        /* Recorded ids to the handles they were replayed as */
+        let mut handles: HashMap<TaskId, TimeoutHandle> = HashMap::new();
+        for call in &self.calls {
+            wait(call.at);
+            let named = call
+                .name
+                .as_deref()
+                .map(|name| scheduler.named(intern(name)));
+            let handle = match (call.kind, &named) {
+                (CallKind::Immediate, Some(named)) => named.schedule(work(call)).map(|_| None)?,
+                (CallKind::Immediate, None) => scheduler.schedule(work(call)).map(|_| None)?,
+                (CallKind::Once { delay }, Some(named)) => {
+                    Some(named.schedule_delayed(delay, work(call))?)
+                }
+                (CallKind::Once { delay }, None) => {
+                    Some(scheduler.schedule_delayed(delay, work(call))?)
+                }
+                (CallKind::Periodic { interval }, Some(named)) => {
+                    Some(named.schedule_periodic(interval, work(call))?)
+                }
+                (CallKind::Periodic { interval }, None) => {
+                    Some(scheduler.schedule_periodic(interval, work(call))?)
+                }
+                (CallKind::Cancel, _) => {
+                    if let Some(handle) = call.task.and_then(|task| handles.remove(&task)) {
+                        handle.cancel()?;
+                    }
+                    None
+                }
+            };
+            if let (Some(task), Some(handle)) = (call.task, handle) {
+                handles.insert(task, handle);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse(line: &str) -> Option<RecordedCall> {
+    let mut fields = line.splitn(2, ' ');
+    let at = nanos(fields.next()?)?;
+    let mut fields = fields.next()?.splitn(2, ' ');
+    let call = fields.next()?;
+    let mut fields = fields.next()?.splitn(2, ' ');
+    let task = match fields.next()? {
+        "-" => None,
+        id => Some(TaskId::from_u64(id.parse().ok()?)),
+    };
+    let rest = fields.next();
+
+    let (kind, name) = match call {
+        "cancel" => (CallKind::Cancel, rest),
+        "schedule" => {
+            let mut fields = rest?.splitn(3, ' ');
+            let kind = fields.next()?;
+            let delay = nanos(fields.next()?)?;
+            let kind = match kind {
+                "immediate" => CallKind::Immediate,
+                "once" => CallKind::Once { delay },
+                "periodic" => CallKind::Periodic { interval: delay },
+                _ => return None,
+            };
+            (kind, fields.next())
+        }
+        _ => return None,
+    };
+
+    Some(RecordedCall {
+        at,
+        task,
+        name: name.map(String::from),
+        kind,
+    })
+}
+
+/* As written by `as_nanos`, which goes past u64 for long delays */
+fn nanos(field: &str) -> Option<Duration> {
+    let nanos: u128 = field.parse().ok()?;
+    let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+    Some(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+/* Task names must be 'static. Each distinct name read back from a
+ * recording is leaked once for the life of the process.
+ */
+fn intern(name: &str) -> &'static str {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+    let mut names = NAMES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.into());
+            names.insert(name);
+            name
+        }
+    }
+}
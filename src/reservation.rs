@@ -0,0 +1,170 @@
+/* Scheduler::schedule_deadline, hard-periodic work on threads of its own
+ * under SCHED_DEADLINE. The kernel's admission control decides whether a
+ * reservation fits next to the ones already made, the thread applies it
+ * to itself and reports back before schedule_deadline returns, so a
+ * refused reservation is an error rather than a thread that runs late.
+ *
+ * Each period the thread runs the work once and sched_yields away the
+ * rest of its runtime, which under SCHED_DEADLINE sleeps until the next
+ * period begins. Overrunning the runtime gets it throttled until then.
+ */
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Weak};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::error::{Result, SchedError};
+use crate::scheduler::Scheduler;
+
+const SCHED_DEADLINE: u32 = 6;
+const SCHED_FLAG_RESET_ON_FORK: u64 = 0x01;
+
+/* The kernel's smallest runtime and deadline, 2^DL_SCALE ns */
+const MIN_RESERVATION: Duration = Duration::from_nanos(1 << 10);
+
+/* struct sched_attr, which libc doesn't have */
+#[repr(C)]
+struct SchedAttr {
+    size: u32,
+    policy: u32,
+    flags: u64,
+    nice: i32,
+    priority: u32,
+    runtime: u64,
+    deadline: u64,
+    period: u64,
+}
+
+/// CPU time for `Scheduler::schedule_deadline`: `runtime` in every
+/// `period`, all of it within `deadline` of the period's start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reservation {
+    pub runtime: Duration,
+    pub deadline: Duration,
+    pub period: Duration,
+}
+
+impl Reservation {
+    /// `runtime` in every `period`, due by the end of it.
+    pub fn new(runtime: Duration, period: Duration) -> Reservation {
+        Reservation {
+            runtime,
+            deadline: period,
+            period,
+        }
+    }
+
+    fn check(&self) -> io::Result<()> {
+        let valid = self.runtime >= MIN_RESERVATION
+            && self.runtime <= self.deadline
+            && self.deadline <= self.period
+            && self.period.as_nanos() < 1 << 63;
+        if valid {
+            return Ok(());
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} needs 1.024µs <= runtime <= deadline <= period", self),
+        ))
+    }
+
+    fn attr(&self) -> SchedAttr {
+        SchedAttr {
+            size: std::mem::size_of::<SchedAttr>() as u32,
+            policy: SCHED_DEADLINE,
+            flags: SCHED_FLAG_RESET_ON_FORK,
+            nice: 0,
+            priority: 0,
+            runtime: self.runtime.as_nanos() as u64,
+            deadline: self.deadline.as_nanos() as u64,
+            period: self.period.as_nanos() as u64,
+        }
+    }
+}
+
+/// A task started with `Scheduler::schedule_deadline`. Dropping it leaves
+/// the task running.
+pub struct DeadlineTask {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<std::thread::Result<()>>,
+}
+
+impl DeadlineTask {
+    /// Stop the task, at the start of its next period at the latest.
+    pub fn cancel(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Block until the task stopped. `SchedError::WorkerPanicked` if its
+    /// work panicked, which stops it too.
+    pub fn join(self) -> Result<()> {
+        match self.thread.join() {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(_)) | Err(_) => Err(SchedError::WorkerPanicked),
+        }
+    }
+}
+
+impl std::fmt::Debug for DeadlineTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeadlineTask")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+pub(crate) fn start<F>(
+    scheduler: Weak<Scheduler>,
+    reservation: Reservation,
+    mut work: F,
+) -> io::Result<DeadlineTask>
+where
+    F: FnMut() + Send + 'static,
+{
+    reservation.check()?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (admitted, admission) = sync_channel(1);
+    let thread = {
+        let stop = stop.clone();
+        std::thread::Builder::new()
+            .name("event_scheduler deadline".into())
+            .spawn(move || {
+                let attr = reservation.attr();
+                let ret = unsafe { libc::syscall(libc::SYS_sched_setattr, 0, &attr, 0) };
+                let refused = (ret != 0).then(io::Error::last_os_error);
+                let _ = admitted.send(refused);
+                if ret != 0 {
+                    return Ok(());
+                }
+
+                panic::catch_unwind(AssertUnwindSafe(move || loop {
+                    let running = scheduler
+                        .upgrade()
+                        .is_some_and(|scheduler| !scheduler.is_shut_down());
+                    if !running || stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    work();
+                    unsafe { libc::sched_yield() };
+                }))
+            })?
+    };
+
+    match admission.recv() {
+        Ok(None) => Ok(DeadlineTask { stop, thread }),
+        Ok(Some(e)) => Err(io::Error::new(
+            e.kind(),
+            format!("SCHED_DEADLINE refused {:?}: {}", reservation, e),
+        )),
+        Err(_) => Err(io::Error::other("deadline thread died before admission")),
+    }
+}
@@ -0,0 +1,57 @@
+/* The scheduler's source of randomness, seeded by Builder::seed so a run
+ * can be repeated. SplitMix64: tiny, fast and good enough for coin flips
+ * in tests, not for anything that must not be guessed.
+ *
+ * Each thread drawing from it gets its own fork, taken in a fixed order
+ * when the scheduler is built. Only fault injection draws so far.
+ */
+#![cfg_attr(not(feature = "fault-injection"), allow(dead_code))]
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/* A seed for when none was given, different every time */
+pub(crate) fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /* An independent generator, for another thread */
+    pub(crate) fn fork(&mut self) -> Rng {
+        Rng::new(self.next_u64())
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /* True with probability `p`, never for 0 and always for 1 */
+    pub(crate) fn chance(&mut self, p: f64) -> bool {
+        /* 53 random bits as a float in [0, 1) */
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < p
+    }
+
+    /* Uniform in [0, max] */
+    pub(crate) fn up_to(&mut self, max: Duration) -> Duration {
+        let max = max.as_nanos().min(u64::MAX as u128) as u64;
+        match max.checked_add(1) {
+            Some(bound) => Duration::from_nanos(self.next_u64() % bound),
+            None => Duration::from_nanos(self.next_u64()),
+        }
+    }
+}
@@ -0,0 +1,1126 @@
+use std::future::Future;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::actor::{Actor, Addr};
+#[cfg(target_os = "linux")]
+use crate::alarm::Alarms;
+use crate::async_task::{AsyncTask, BoxFuture};
+use crate::backend::{self, Backend, Submitter, TimerBackend};
+use crate::calibrate::calibrate;
+use crate::clock::{Clock, SystemClock, Wake};
+use crate::deadline::{DeadlineMiss, MissHook};
+use crate::dedup::{Dedup, Keys};
+use crate::dump::{Config, StateDump};
+use crate::error::{Result, SchedError};
+use crate::events::{Broadcast, TaskEvent};
+use crate::executor::{Invocation, RecordingExecutor};
+#[cfg(feature = "fault-injection")]
+use crate::faults::Faults;
+use crate::faults::Injector;
+use crate::fired::{FiredStream, Handoff};
+use crate::handle::{self, Completion, TimeoutHandle};
+use crate::health::{Health, DEFAULT_WATCHDOG};
+use crate::histogram::Histogram;
+use crate::latency::{Latency, LatencyRing, DEFAULT_HISTORY};
+use crate::named::Named;
+use crate::observer::{Observers, ScheduleEvent, SchedulerObserver};
+use crate::pending::PendingInfo;
+use crate::platform::{self, Realtime};
+use crate::power::{Power, PowerMode, PowerState};
+#[cfg(target_os = "linux")]
+use crate::reservation::{self, DeadlineTask, Reservation};
+use crate::rng::{random_seed, Rng};
+#[cfg(target_os = "linux")]
+use crate::signal::{self, Signal};
+use crate::sink::{Room, ScheduleSink};
+use crate::span::TaskSpan;
+use crate::spawn::Spawner;
+use crate::stats::{Counters, Stats};
+use crate::sync::atomic::Ordering;
+use crate::sync::mpsc::{channel, Sender};
+use crate::sync::thread;
+use crate::sync::Mutex;
+use crate::task::TaskIds;
+use crate::telemetry;
+use crate::time::{Interval, Sleep, WithTimeout};
+use crate::timekeeper::{timekeeper_thread, Compensation, Dispatch, Message};
+use crate::timeout::{Label, Timeout};
+use crate::timings::TaskTimings;
+use crate::tsc;
+use crate::worker::{Command, ErrorHook, Hooks, PanicPolicy, Task, Worker};
+use crate::Work;
+
+pub struct Builder {
+    resolution: Duration,
+    compensate: bool,
+    calibrate: bool,
+    clock: Arc<dyn Clock>,
+    deadline_miss: Option<MissHook>,
+    backend: Backend,
+    timer_backend: Option<Box<dyn TimerBackend>>,
+    latency_history: usize,
+    max_pending: Option<usize>,
+    on_error: Option<ErrorHook>,
+    fallible_alloc: bool,
+    panic_policy: PanicPolicy,
+    slow_task: Option<Duration>,
+    lateness_budget: Option<Duration>,
+    watchdog: Duration,
+    observers: Observers,
+    seed: Option<u64>,
+    executor: Option<RecordingExecutor>,
+    spawner: Option<Arc<dyn Spawner>>,
+    realtime: Option<Realtime>,
+    timer_slack: Option<Duration>,
+    systemd_watchdog: bool,
+    worker_nice: Option<i32>,
+    power_mode: PowerMode,
+    #[cfg(feature = "fault-injection")]
+    faults: Faults,
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Builder {
+            resolution: Duration::ZERO,
+            compensate: false,
+            calibrate: false,
+            clock: Arc::new(SystemClock),
+            deadline_miss: None,
+            backend: Backend::default(),
+            timer_backend: None,
+            latency_history: DEFAULT_HISTORY,
+            max_pending: None,
+            on_error: None,
+            fallible_alloc: false,
+            panic_policy: PanicPolicy::default(),
+            slow_task: None,
+            lateness_budget: None,
+            watchdog: DEFAULT_WATCHDOG,
+            observers: Observers::default(),
+            seed: None,
+            executor: None,
+            spawner: None,
+            realtime: None,
+            timer_slack: None,
+            systemd_watchdog: false,
+            worker_nice: None,
+            power_mode: PowerMode::default(),
+            #[cfg(feature = "fault-injection")]
+            faults: Faults::default(),
+        }
+    }
+
+    /// Round deadlines up to a multiple of `resolution`, coalescing timeouts
+    /// that land in the same slot into a single wakeup.
+    pub fn resolution(mut self, resolution: Duration) -> Builder {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Wake up ahead of deadlines by a measured estimate of the dispatch
+    /// overhead and spin out the remainder, trading some CPU for accuracy.
+    pub fn compensate_overhead(mut self, compensate: bool) -> Builder {
+        self.compensate = compensate;
+        self
+    }
+
+    /// Measure the host's sleep accuracy when building the scheduler and use
+    /// it as the initial overhead compensation margin.
+    pub fn calibrate(mut self, calibrate: bool) -> Builder {
+        self.calibrate = calibrate;
+        self
+    }
+
+    /// Use `clock` as the time source instead of the system wall clock.
+    pub fn clock<C: Clock>(mut self, clock: C) -> Builder {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Call `callback` from the timekeeper for every timeout dispatched more
+    /// than `threshold` past its deadline, after logging it as a warning.
+    /// Runs on the timekeeper, so keep it short.
+    pub fn on_deadline_miss<F>(mut self, threshold: Duration, callback: F) -> Builder
+    where
+        F: Fn(&DeadlineMiss) + Send + Sync + 'static,
+    {
+        self.deadline_miss = Some(MissHook {
+            threshold,
+            callback: Arc::new(callback),
+        });
+        self
+    }
+
+    /// Select how the timekeeper waits for deadlines. Manually driven clocks
+    /// always use `Backend::Channel`.
+    pub fn backend(mut self, backend: Backend) -> Builder {
+        self.backend = backend;
+        self
+    }
+
+    /// Have the timekeeper sleep on `backend` instead of the built-in one
+    /// `Builder::backend` picks. Manually driven clocks still use
+    /// `Backend::Channel`. Where tasks run is up to `spawner`.
+    pub fn timer_backend<B: TimerBackend>(mut self, backend: B) -> Builder {
+        self.timer_backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Keep the lateness of the last `fires` timeouts for
+    /// `Scheduler::latency`, 0 disables recording.
+    pub fn latency_history(mut self, fires: usize) -> Builder {
+        self.latency_history = fires;
+        self
+    }
+
+    /// Refuse new timeouts with `SchedError::TooManyTimers` while `max`
+    /// are pending. Periodic timeouts count until cancelled.
+    pub fn max_pending(mut self, max: usize) -> Builder {
+        self.max_pending = Some(max);
+        self
+    }
+
+    /// Call `callback` when a scheduler thread fails. A worker thread that
+    /// dies is replaced and then reported as `SchedError::WorkerPanicked`,
+    /// the work queued for it is kept. Replaces logging the error.
+    pub fn on_error<F>(mut self, callback: F) -> Builder
+    where
+        F: Fn(SchedError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(callback));
+        self
+    }
+
+    /// Grow the timer store with fallible allocation. Once it can't, new
+    /// timeouts fail with `SchedError::OutOfMemory` instead of aborting the
+    /// process, and one submitted before that was noticed is dropped and
+    /// reported through `on_error`.
+    pub fn fallible_alloc(mut self, fallible: bool) -> Builder {
+        self.fallible_alloc = fallible;
+        self
+    }
+
+    /// Decide what a panicking task does to the scheduler, by default
+    /// nothing.
+    pub fn panic_policy(mut self, policy: PanicPolicy) -> Builder {
+        self.panic_policy = policy;
+        self
+    }
+
+    /// Log a warning for every task that runs longer than `threshold`,
+    /// with its name and how much work was queued behind it. Counted in
+    /// `Stats::slow_tasks`.
+    pub fn slow_task_threshold(mut self, threshold: Duration) -> Builder {
+        self.slow_task = Some(threshold);
+        self
+    }
+
+    /// Count every fire more than `budget` past its deadline, per task name
+    /// in `Scheduler::task_timings` and in total in `Stats`. Unlike
+    /// `on_deadline_miss` nothing is logged.
+    pub fn lateness_budget(mut self, budget: Duration) -> Builder {
+        self.lateness_budget = Some(budget);
+        self
+    }
+
+    /// Report a scheduler thread as stalled in `Scheduler::health` once it
+    /// has been busy for longer than `interval`, one second by default.
+    /// For the worker that includes the task it runs.
+    pub fn watchdog(mut self, interval: Duration) -> Builder {
+        self.watchdog = interval;
+        self
+    }
+
+    /// Send lifecycle events for every task to `observer`, in addition to
+    /// any registered before.
+    pub fn observer<O: SchedulerObserver>(mut self, observer: O) -> Builder {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Hand every task to `executor`, which records it instead of running
+    /// it, to check what fires when without the side effects.
+    pub fn executor(mut self, executor: RecordingExecutor) -> Builder {
+        self.executor = Some(executor);
+        self
+    }
+
+    /// Run tasks on an async runtime rather than on the worker thread,
+    /// futures from `schedule_async` included. The timekeeper still times
+    /// them. Work then runs on the runtime's threads, several at a time,
+    /// and should not block.
+    pub fn spawner<S: Spawner>(mut self, spawner: S) -> Builder {
+        self.spawner = Some(Arc::new(spawner));
+        self
+    }
+
+    /// Run the timekeeper under a real-time scheduling policy, so timers
+    /// don't wait behind other threads for a CPU under load. Needs
+    /// CAP_SYS_NICE or an RLIMIT_RTPRIO allowance, without it a warning is
+    /// logged and the timekeeper runs as usual. Linux only.
+    pub fn realtime(mut self, realtime: Realtime) -> Builder {
+        self.realtime = Some(realtime);
+        self
+    }
+
+    /// Let the kernel defer the timekeeper's wakeups by at most `slack`,
+    /// rather than the 50µs threads get by default. Real-time threads have
+    /// none either way. Ignored outside of Linux.
+    pub fn timer_slack(mut self, slack: Duration) -> Builder {
+        self.timer_slack = Some(slack);
+        self
+    }
+
+    /// Run the worker thread at niceness `nice`, from -20 to 19, so
+    /// background jobs yield the CPU to the application's own threads. The
+    /// timekeeper keeps the application's, so timing doesn't suffer.
+    /// Raising the priority needs CAP_SYS_NICE, without it a warning is
+    /// logged. Linux only, and tasks run by a `spawner` are up to its
+    /// threads.
+    pub fn worker_nice(mut self, nice: i32) -> Builder {
+        self.worker_nice = Some(nice);
+        self
+    }
+
+    /// Start out in power mode `mode`, e.g. `PowerMode::Efficient` for a
+    /// laptop on battery. `Scheduler::set_power_mode` switches later on,
+    /// when it is plugged in.
+    pub fn power_mode(mut self, mode: PowerMode) -> Builder {
+        self.power_mode = mode;
+        self
+    }
+
+    /// Ping the systemd watchdog from a periodic task named "systemd
+    /// watchdog", at half of the service's `WatchdogSec`, holding back
+    /// while `Scheduler::health` reports a stalled or dead thread. A wedged
+    /// timekeeper or worker then gets the service restarted. Does nothing
+    /// unless systemd runs the process with a watchdog, Linux only.
+    pub fn systemd_watchdog(mut self, enabled: bool) -> Builder {
+        self.systemd_watchdog = enabled;
+        self
+    }
+
+    /// Run tasks as tokio tasks on `handle`, see `spawner`.
+    #[cfg(feature = "tokio")]
+    pub fn tokio(self, handle: tokio::runtime::Handle) -> Builder {
+        self.spawner(handle)
+    }
+
+    /// Seed the generator every randomized behaviour draws from, such as
+    /// fault injection, to repeat a run exactly. Without one the seed is
+    /// random, the one used is in `Config::seed` to repeat a failed run.
+    pub fn seed(mut self, seed: u64) -> Builder {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Inject `faults` into the timekeeper and the tasks it fires, to see
+    /// how an application copes with a misbehaving scheduler.
+    #[cfg(feature = "fault-injection")]
+    pub fn faults(mut self, faults: Faults) -> Builder {
+        self.faults = faults;
+        self
+    }
+
+    pub fn build(mut self) -> Scheduler {
+        let broadcast = Arc::new(Broadcast::default());
+        let handoff = Arc::new(Handoff::default());
+        self.observers.push(broadcast.clone());
+        let room = Arc::new(Room::default());
+        self.observers.push(room.clone());
+        let counters = Arc::new(Counters::default());
+        let latency = Arc::new(LatencyRing::new(self.latency_history));
+        let keys = Arc::new(Keys::default());
+        let seed = self.seed.unwrap_or_else(random_seed);
+        #[cfg_attr(not(feature = "fault-injection"), allow(unused_mut, unused_variables))]
+        let mut rng = Rng::new(seed);
+        telemetry::describe();
+        tsc::calibrate();
+        let calibrated_overshoot = if self.calibrate {
+            Some(calibrate())
+        } else {
+            None
+        };
+
+        let power = Arc::new(Power::new(
+            self.power_mode,
+            self.resolution,
+            self.timer_slack,
+        ));
+
+        let (work_sender, work_receiver) = channel();
+        let backend = if self.clock.is_manual() {
+            Backend::Channel
+        } else {
+            self.backend
+        };
+        let timer_backend = match self.timer_backend.take() {
+            Some(custom) if !self.clock.is_manual() => custom,
+            _ => backend.start(),
+        };
+        let (timeout_work_sender, timeout_work_receiver) = backend::intake(timer_backend);
+
+        /* Startup work processor */
+        let worker = {
+            let counters = counters.clone();
+            let idle: Option<Wake> = if self.clock.is_manual() {
+                let tick_sender = timeout_work_sender.clone();
+                Some(Box::new(move || {
+                    let _ = tick_sender.send(Message::Tick);
+                }))
+            } else {
+                None
+            };
+            Worker::start(
+                work_receiver,
+                counters,
+                idle,
+                timeout_work_sender.clone(),
+                Hooks {
+                    on_error: self.on_error.clone(),
+                    panic_policy: self.panic_policy,
+                    slow_task: self.slow_task,
+                    observers: self.observers.clone(),
+                    spawner: self.spawner.clone(),
+                    nice: self.worker_nice,
+                },
+            )
+        };
+
+        /* Startup timekeeper for delayed work */
+        {
+            let tick_sender = timeout_work_sender.clone();
+            self.clock.on_advance(Box::new(move || {
+                let _ = tick_sender.send(Message::Tick);
+            }));
+
+            let dispatch = Dispatch {
+                work_sender: work_sender.clone(),
+                counters: counters.clone(),
+                latency: latency.clone(),
+                keys: keys.clone(),
+                deadline_miss: self.deadline_miss,
+                lateness_budget: self.lateness_budget,
+                on_error: self.on_error,
+                observers: self.observers.clone(),
+                #[cfg(feature = "fault-injection")]
+                faults: Injector::new(self.faults, self.clock.is_manual(), rng.fork()),
+                #[cfg(not(feature = "fault-injection"))]
+                faults: Injector,
+                executor: self.executor.clone(),
+                handoff: handoff.clone(),
+            };
+            let seed = calibrated_overshoot.unwrap_or(Duration::ZERO);
+            let compensation = if self.compensate && !self.clock.is_manual() {
+                Some(Compensation::new(seed, counters.clone()))
+            } else {
+                None
+            };
+            let clock = self.clock.clone();
+            let counters = counters.clone();
+            let fallible_alloc = self.fallible_alloc;
+            let realtime = self.realtime;
+            let power = PowerState::new(power.clone());
+            thread::spawn(move || {
+                if let Some(realtime) = realtime {
+                    platform::set_realtime(realtime);
+                }
+                timekeeper_thread(
+                    timeout_work_receiver,
+                    clock,
+                    counters,
+                    compensation,
+                    dispatch,
+                    fallible_alloc,
+                    power,
+                )
+            });
+        }
+
+        let config = Config {
+            resolution: self.resolution,
+            compensate_overhead: self.compensate,
+            calibrate: self.calibrate,
+            backend,
+            manual_clock: self.clock.is_manual(),
+            latency_history: self.latency_history,
+            max_pending: self.max_pending,
+            fallible_alloc: self.fallible_alloc,
+            panic_policy: self.panic_policy,
+            slow_task_threshold: self.slow_task,
+            lateness_budget: self.lateness_budget,
+            watchdog: self.watchdog,
+            seed,
+        };
+
+        let systemd_watchdog = self.systemd_watchdog;
+        let scheduler = Scheduler {
+            work_sender: Mutex::new(Some(work_sender)),
+            timeout_work_sender,
+            worker,
+            power,
+            counters,
+            calibrated_overshoot,
+            clock: self.clock,
+            task_ids: TaskIds::default(),
+            latency,
+            max_pending: self.max_pending,
+            keys,
+            fallible_alloc: self.fallible_alloc,
+            started: Instant::now(),
+            observers: self.observers,
+            broadcast,
+            handoff,
+            room,
+            executor: self.executor,
+            spawner: self.spawner,
+            config,
+            #[cfg(target_os = "linux")]
+            alarms: std::sync::OnceLock::new(),
+        };
+
+        if systemd_watchdog {
+            let counters = scheduler.counters.clone();
+            let watchdog = scheduler.config.watchdog;
+            platform::systemd_watchdog(&scheduler, move || counters.heartbeats.health(watchdog));
+        }
+        scheduler
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+pub struct Scheduler {
+    /* Taken on shutdown so the worker sees the channel close */
+    work_sender: Mutex<Option<Sender<Command>>>,
+    timeout_work_sender: Submitter,
+    worker: Arc<Worker>,
+    power: Arc<Power>,
+    counters: Arc<Counters>,
+    calibrated_overshoot: Option<Duration>,
+    clock: Arc<dyn Clock>,
+    task_ids: TaskIds,
+    latency: Arc<LatencyRing>,
+    max_pending: Option<usize>,
+    keys: Arc<Keys>,
+    fallible_alloc: bool,
+    started: Instant,
+    observers: Observers,
+    broadcast: Arc<Broadcast>,
+    handoff: Arc<Handoff>,
+    room: Arc<Room>,
+    executor: Option<RecordingExecutor>,
+    spawner: Option<Arc<dyn Spawner>>,
+    config: Config,
+    #[cfg(target_os = "linux")]
+    alarms: std::sync::OnceLock<Alarms>,
+}
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Builder::new().build()
+    }
+
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    pub fn schedule<F>(&self, work: F) -> Result<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.run(None, None, None, handle::work_once(work))
+    }
+
+    /// Run `work` once `delay` has passed. Timeouts due at the same time,
+    /// also after rounding to the `Builder::resolution`, fire in the order
+    /// they were scheduled, periodic ones by their first schedule. Await
+    /// the handle for what `work` returns.
+    pub fn schedule_delayed<F, R>(&self, delay: Duration, work: F) -> Result<TimeoutHandle<R>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (work, done) = handle::once(work);
+        self.submit(self.delayed(None, delay, work)?, done)
+    }
+
+    /// Run `future` on the worker once `delay` has passed, and poll it
+    /// there again every time it is woken. Await the handle for its output.
+    pub fn schedule_async<Fut>(
+        &self,
+        delay: Duration,
+        future: Fut,
+    ) -> Result<TimeoutHandle<Fut::Output>>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let (timeout, done) = self.delayed_async(None, delay, Box::pin(future))?;
+        self.submit(timeout, done)
+    }
+
+    /// A future resolving once `duration` has passed, on this scheduler's
+    /// timekeeper rather than the runtime's timer. Fails like
+    /// `schedule_delayed`.
+    pub fn sleep(&self, duration: Duration) -> Result<Sleep> {
+        Sleep::new(self, duration)
+    }
+
+    /// Run `future` for at most `duration`, timed by this scheduler's
+    /// timekeeper, see `WithTimeout`. Fails like `schedule_delayed`.
+    pub fn timeout<F: Future>(&self, duration: Duration, future: F) -> Result<WithTimeout<F>> {
+        WithTimeout::new(self, duration, future)
+    }
+
+    /// Submit jobs from async code, waiting for room rather than failing
+    /// once `Builder::max_pending` is reached, see `ScheduleSink`.
+    pub fn sink(&self) -> ScheduleSink<'_> {
+        ScheduleSink::new(self)
+    }
+
+    /// Tick every `period` on this scheduler's timekeeper, see `Interval`.
+    pub fn interval(&self, period: Duration) -> Interval<'_> {
+        Interval::new(self, period)
+    }
+
+    /// Like `schedule_delayed`, but at most one timeout per `key` is
+    /// pending. If one already is, `policy` decides which of the two stays,
+    /// the returned handle refers to that one. Awaiting it tells how `work`
+    /// went, `Cancelled` if it is the one that didn't stay.
+    pub fn schedule_delayed_keyed<K, F, R>(
+        &self,
+        key: K,
+        delay: Duration,
+        policy: Dedup,
+        work: F,
+    ) -> Result<TimeoutHandle<R>>
+    where
+        K: Into<String>,
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (work, done) = handle::once(work);
+        let timeout = self.delayed(None, delay, work)?;
+        self.submit_keyed(key.into(), policy, timeout, done)
+    }
+
+    /// Run `work` once the wall clock reads `when`. Unlike relative delays
+    /// the deadline follows the wall clock if it is stepped in the meantime.
+    pub fn schedule_at<F, R>(&self, when: SystemTime, work: F) -> Result<TimeoutHandle<R>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (work, done) = handle::once(work);
+        self.submit(self.at(None, when, work)?, done)
+    }
+
+    /// Run `work` every `interval`, the first time one interval from now.
+    /// Ticks are anchored to this call rather than to the previous fire, so
+    /// latency never accumulates into drift.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero, which would keep the timekeeper firing it.
+    pub fn schedule_periodic<F>(&self, interval: Duration, work: F) -> Result<TimeoutHandle>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let (work, done) = handle::repeated(work);
+        self.submit(self.periodic(None, interval, work)?, done)
+    }
+
+    /// Start `actor`, handling the messages sent to the returned address one
+    /// at a time on the worker.
+    pub fn start_actor<A: Actor>(self: &Arc<Self>, actor: A) -> Addr<A> {
+        Addr::start(self, actor)
+    }
+
+    /// Run `work` once `delay` has passed on `CLOCK_BOOTTIME`, waking the
+    /// system from suspend for it, for long delays that must not wait for
+    /// the machine to be woken otherwise. Waking needs CAP_WAKE_ALARM,
+    /// without it the time suspended still counts but the alarm fires on
+    /// resume. Alarms are timed on a thread of their own and cancelled
+    /// with `cancel_alarm`, their work runs like any other task's.
+    #[cfg(target_os = "linux")]
+    pub fn schedule_alarm<F>(self: &Arc<Self>, delay: Duration, work: F) -> Result<crate::TaskId>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if self.timeout_work_sender.is_closed() {
+            return Err(SchedError::ShutDown);
+        }
+
+        let alarms = self
+            .alarms
+            .get_or_init(|| Alarms::start(Arc::downgrade(self)));
+        Ok(alarms.insert(delay, handle::work_once(work)))
+    }
+
+    /// Take alarm `id` out before it fires, false if it already fired or
+    /// was cancelled.
+    #[cfg(target_os = "linux")]
+    pub fn cancel_alarm(&self, id: crate::TaskId) -> bool {
+        self.alarms.get().is_some_and(|alarms| alarms.cancel(id))
+    }
+
+    /// Run `work` once every period of `reservation` on a thread of its
+    /// own under SCHED_DEADLINE, which guarantees it the reservation's
+    /// runtime before each deadline, for hard-periodic control loops.
+    /// Work overrunning the runtime is held back until the next period.
+    /// Fails if the kernel's admission control turns the reservation down,
+    /// as it does once the CPUs are booked up or without CAP_SYS_NICE.
+    /// Stops on `DeadlineTask::cancel` or shutdown. Linux only.
+    #[cfg(target_os = "linux")]
+    pub fn schedule_deadline<F>(
+        self: &Arc<Self>,
+        reservation: Reservation,
+        work: F,
+    ) -> std::io::Result<DeadlineTask>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        if self.is_shut_down() {
+            return Err(std::io::Error::other(SchedError::ShutDown));
+        }
+        reservation::start(Arc::downgrade(self), reservation, work)
+    }
+
+    /// Run `work` on the worker every time the process receives `signal`,
+    /// as an immediate task named after it, e.g. for reload or shutdown
+    /// logic. Deliveries before it ran coalesce. Replaces the signal's
+    /// previous handler for the whole process. Fails if the handler can't
+    /// be installed.
+    #[cfg(target_os = "linux")]
+    pub fn schedule_on_signal<F>(self: &Arc<Self>, signal: Signal, work: F) -> std::io::Result<()>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        signal::register(self, signal, work)
+    }
+
+    /// Schedule tasks under `name`, which then shows up in logs, tracing
+    /// spans, deadline misses and `pending`.
+    pub fn named(&self, name: &'static str) -> Named<'_> {
+        Named::new(self, name)
+    }
+
+    pub(crate) fn run(
+        &self,
+        name: Option<&'static str>,
+        tags: Option<Arc<str>>,
+        node: Option<usize>,
+        work: Work,
+    ) -> Result<()> {
+        let work_sender = self.work_sender.lock().unwrap();
+        let work_sender = work_sender.as_ref().ok_or(SchedError::ShutDown)?;
+        /* Or the worker shut it down after a panic */
+        if self.timeout_work_sender.is_closed() {
+            return Err(SchedError::ShutDown);
+        }
+
+        let now = self.clock.now();
+        self.observers.schedule(ScheduleEvent {
+            task: None,
+            name,
+            deadline: None,
+            periodic: false,
+            at: now,
+        });
+
+        let invocation = Invocation {
+            task: None,
+            name,
+            scheduled_for: now,
+            fired_at: now,
+        };
+        let work = match self.executor.as_ref() {
+            Some(executor) => executor.intercept(invocation, work),
+            None => self.handoff.intercept(&invocation, work),
+        };
+
+        self.counters.queue();
+        let span = TaskSpan::immediate();
+        if let Some(name) = name {
+            span.name(name);
+        }
+        if let Some(tags) = tags.as_deref() {
+            span.tags(tags);
+        }
+        let task = Task {
+            work: span.instrument(work),
+            label: Label { id: None, name },
+            tags,
+            node,
+        };
+        work_sender.send(Command::Run(task)).map_err(|_| {
+            self.counters.in_flight.fetch_sub(1, Ordering::AcqRel);
+            SchedError::ShutDown
+        })
+    }
+
+    /* On the scheduler's clock */
+    pub(crate) fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    pub(crate) fn delayed(
+        &self,
+        name: Option<&'static str>,
+        delay: Duration,
+        work: Work,
+    ) -> Result<Timeout> {
+        let timeout = Timeout::new(
+            self.task_ids.next(),
+            work,
+            delay,
+            self.power.resolution(),
+            self.clock.try_now()?,
+        );
+
+        Ok(timeout.named(name))
+    }
+
+    pub(crate) fn delayed_async<T: Send + 'static>(
+        &self,
+        name: Option<&'static str>,
+        delay: Duration,
+        future: BoxFuture<T>,
+    ) -> Result<(Timeout, Arc<Completion<T>>)> {
+        let worker = self.work_sender.lock().unwrap().clone();
+        let worker = worker.ok_or(SchedError::ShutDown)?;
+        let id = self.task_ids.next();
+        let done = Completion::new();
+        let label = Label { id: Some(id), name };
+        let work = match self.spawner.clone() {
+            Some(spawner) => AsyncTask::spawn(future, spawner, done.clone()),
+            None => AsyncTask::start(future, worker, self.counters.clone(), label, done.clone()),
+        };
+        let timeout = Timeout::new(
+            id,
+            work,
+            delay,
+            self.power.resolution(),
+            self.clock.try_now()?,
+        );
+
+        Ok((timeout.named(name), done))
+    }
+
+    pub(crate) fn at(
+        &self,
+        name: Option<&'static str>,
+        when: SystemTime,
+        work: Work,
+    ) -> Result<Timeout> {
+        let timeout = Timeout::at(
+            self.task_ids.next(),
+            work,
+            when,
+            self.power.resolution(),
+            self.clock.try_now()?,
+        );
+
+        Ok(timeout.named(name))
+    }
+
+    pub(crate) fn periodic(
+        &self,
+        name: Option<&'static str>,
+        interval: Duration,
+        work: Work,
+    ) -> Result<Timeout> {
+        assert!(
+            interval > Duration::ZERO,
+            "periodic interval must not be zero"
+        );
+        let timeout =
+            Timeout::periodic(self.task_ids.next(), work, interval, self.clock.try_now()?);
+
+        Ok(timeout.named(name))
+    }
+
+    pub(crate) fn submit_keyed<R>(
+        &self,
+        key: String,
+        policy: Dedup,
+        timeout: Timeout,
+        done: Arc<Completion<R>>,
+    ) -> Result<TimeoutHandle<R>> {
+        /* Held until the new id is in, so the timekeeper can't release it
+         * in between
+         */
+        let mut keys = self.keys.lock();
+        let existing = keys.get(&key);
+        if let Some((id, deadline)) = existing {
+            let keep = match policy {
+                Dedup::KeepExisting => true,
+                Dedup::ReplaceExisting => false,
+                Dedup::KeepEarliest => deadline <= timeout.deadline,
+            };
+            if keep {
+                return Ok(TimeoutHandle::new(
+                    id,
+                    self.timeout_work_sender.clone(),
+                    done,
+                ));
+            }
+        }
+
+        /* Replacing doesn't grow what's pending: the one replaced is still
+         * counted while its key is, the new one takes its count over
+         */
+        let deadline = timeout.deadline;
+        let handle = match existing {
+            Some((id, _)) => {
+                self.counters.pending.fetch_add(1, Ordering::AcqRel);
+                let handle = self.submit_reserved(timeout, done)?;
+                keys.hand_over(id);
+                self.release();
+                let _ = self.timeout_work_sender.send(Message::Cancel(id));
+                handle
+            }
+            None => self.submit(timeout, done)?,
+        };
+        keys.insert(key, handle.id(), deadline);
+
+        Ok(handle)
+    }
+
+    pub(crate) fn submit<R>(
+        &self,
+        timeout: Timeout,
+        done: Arc<Completion<R>>,
+    ) -> Result<TimeoutHandle<R>> {
+        self.reserve()?;
+        self.submit_reserved(timeout, done)
+    }
+
+    /* Count a timeout as pending ahead of submitting it, `release` if it
+     * never is
+     */
+    pub(crate) fn reserve(&self) -> Result<()> {
+        if self.fallible_alloc && self.counters.store_full.load(Ordering::Acquire) {
+            return Err(SchedError::OutOfMemory);
+        }
+
+        let pending = self.counters.pending.fetch_add(1, Ordering::AcqRel);
+        if self.max_pending.is_some_and(|max| pending >= max) {
+            self.counters.pending.fetch_sub(1, Ordering::AcqRel);
+            return Err(SchedError::TooManyTimers);
+        }
+        self.counters.note_pending(pending + 1);
+        Ok(())
+    }
+
+    /* Cancel by id, for callers without the handle */
+    #[cfg(any(feature = "capi", feature = "durable"))]
+    pub(crate) fn cancel(&self, id: crate::TaskId) -> Result<()> {
+        self.timeout_work_sender.send(Message::Cancel(id))
+    }
+
+    pub(crate) fn room(&self) -> &Room {
+        &self.room
+    }
+
+    pub(crate) fn release(&self) {
+        self.counters.pending.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    pub(crate) fn submit_reserved<R>(
+        &self,
+        timeout: Timeout,
+        done: Arc<Completion<R>>,
+    ) -> Result<TimeoutHandle<R>> {
+        let id = timeout.id;
+        self.observers.schedule(ScheduleEvent {
+            task: Some(id),
+            name: timeout.name,
+            deadline: Some(timeout.deadline),
+            periodic: timeout.is_periodic(),
+            at: timeout.created(),
+        });
+
+        self.timeout_work_sender
+            .send(Message::Add(Box::new(timeout)))
+            .inspect_err(|_| {
+                self.counters.pending.fetch_sub(1, Ordering::AcqRel);
+            })?;
+
+        Ok(TimeoutHandle::new(
+            id,
+            self.timeout_work_sender.clone(),
+            done,
+        ))
+    }
+
+    /// Switch between precise and power-efficient timing, e.g. as the
+    /// machine goes between AC and battery. Timeouts scheduled before keep
+    /// their rounded deadline, the timekeeper's slack and spinning change
+    /// for them too.
+    pub fn set_power_mode(&self, mode: PowerMode) {
+        if self.power.set(mode) {
+            let _ = self.timeout_work_sender.send(Message::Tick);
+        }
+    }
+
+    pub fn power_mode(&self) -> PowerMode {
+        self.power.mode()
+    }
+
+    /// Timeouts waiting to fire, soonest first. Answered by the timekeeper,
+    /// so not to be called from a deadline miss callback. Empty once shut
+    /// down.
+    pub fn pending(&self) -> Vec<PendingInfo> {
+        let (reply, pending) = channel();
+        if self
+            .timeout_work_sender
+            .send(Message::Inspect(reply))
+            .is_err()
+        {
+            return Vec::new();
+        }
+
+        pending.recv().unwrap_or_default()
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            calibrated_overshoot: self.calibrated_overshoot,
+            compensation_margin: self.counters.margin(),
+            periodic_drift: self.counters.drift(),
+            pending: self.counters.pending.load(Ordering::Relaxed),
+            queued: self.counters.in_flight.load(Ordering::Relaxed),
+            peak_pending: self.counters.pending_peak.load(Ordering::Relaxed),
+            peak_queued: self.counters.queued_peak.load(Ordering::Relaxed),
+            recent_peak_pending: self.counters.recent_pending_peak.load(Ordering::Relaxed),
+            recent_peak_queued: self.counters.recent_queued_peak.load(Ordering::Relaxed),
+            fired: self.counters.fired.load(Ordering::Relaxed),
+            cancelled: self.counters.cancelled.load(Ordering::Relaxed),
+            slow_tasks: self.counters.slow_tasks.load(Ordering::Relaxed),
+            missed_deadlines: self.counters.missed_deadlines.load(Ordering::Relaxed),
+            worker_utilization: self.counters.utilization(self.started.elapsed()),
+            mean_lateness: self.counters.mean_lateness(),
+            max_lateness: self.counters.max_lateness(),
+        }
+    }
+
+    /// Stream every task event from now on, like an observer would see
+    /// them. A subscriber more than 1024 events behind misses newer ones
+    /// until it catches up. Dropping the receiver unsubscribes.
+    pub fn events(&self) -> Receiver<TaskEvent> {
+        self.broadcast.subscribe()
+    }
+
+    /// Take over running fired tasks: from now on each task that fires,
+    /// immediate work included, is handed to the returned stream instead
+    /// of the worker, leaving the scheduler a pure timing service. A later
+    /// call takes over from this one, whose stream then ends. Ignored while
+    /// a `RecordingExecutor` is set.
+    pub fn fired_stream(&self) -> FiredStream {
+        self.handoff.subscribe()
+    }
+
+    /// Pending timers, worker state, stats and configuration in one
+    /// snapshot, e.g. to attach to a bug report as `to_json`. Asks the
+    /// timekeeper like `pending`.
+    pub fn dump_state(&self) -> StateDump {
+        StateDump {
+            config: self.config,
+            stats: self.stats(),
+            worker: self.worker.state(),
+            pending: self.pending(),
+            shut_down: self.is_shut_down(),
+        }
+    }
+
+    /// Whether the timekeeper and worker are making progress, as judged
+    /// by the `Builder::watchdog` interval.
+    pub fn health(&self) -> Health {
+        self.counters.heartbeats.health(self.config.watchdog)
+    }
+
+    /// Lateness of recently fired timeouts.
+    pub fn latency(&self) -> Latency {
+        self.latency.snapshot()
+    }
+
+    /// How late timeouts fired, over every fire since the scheduler was
+    /// built or the histograms were last reset.
+    pub fn lateness_histogram(&self) -> Histogram {
+        self.counters.lateness_histogram.snapshot()
+    }
+
+    /// How long tasks ran, since the scheduler was built or the histograms
+    /// were last reset.
+    pub fn execution_histogram(&self) -> Histogram {
+        self.counters.execution_histogram.snapshot()
+    }
+
+    /// How long tasks took and how often they fired late per name they
+    /// were scheduled under, see `named`, the biggest total first. Covers
+    /// the same window as the histograms.
+    pub fn task_timings(&self) -> Vec<TaskTimings> {
+        self.counters.task_timings.snapshot()
+    }
+
+    /// Start both histograms and the task timings over, e.g. at the start
+    /// of a measurement window.
+    pub fn reset_histograms(&self) {
+        self.counters.lateness_histogram.reset();
+        self.counters.execution_histogram.reset();
+        self.counters.task_timings.reset();
+    }
+
+    /// Start `Stats::recent_peak_pending` and `recent_peak_queued` over from
+    /// the current depths, e.g. at the start of a measurement window.
+    pub fn reset_peaks(&self) {
+        self.counters.reset_peaks();
+    }
+
+    /// Stop accepting work and drop all pending timeouts. Work already
+    /// handed to the worker still runs. Afterwards every submission, here
+    /// or through a handle, fails with `SchedError::ShutDown`. Dropping the
+    /// scheduler shuts it down too.
+    pub fn shutdown(&self) {
+        /* Immediate work first, so none lands behind the worker's stop */
+        self.work_sender.lock().unwrap().take();
+        self.timeout_work_sender.close();
+        self.handoff.close();
+        self.room.wake();
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.timeout_work_sender.is_closed()
+    }
+
+    /// Shut down and block until the worker has finished what it was
+    /// handed. Under `PanicPolicy::Propagate` a task panic is resumed here.
+    pub fn join(self) -> Result<()> {
+        self.shutdown();
+        self.worker.join()
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Scheduler {
+        Scheduler::new()
+    }
+}
+
+/* The worker and clock keep submitters of their own, the intake never
+ * disconnects and backends waiting on an eventfd would block for good
+ */
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type Recovery<T> = Arc<dyn Fn(&mut T) + Send + Sync>;
+
+/// State shared between tasks that stays usable when a task panics while
+/// holding it.
+///
+/// A plain `Mutex` is poisoned for good once a holder panics. `Shared`
+/// clears the poison on the next `lock`, first handing the value to the
+/// recovery callback if one was given so it can restore its invariants.
+pub struct Shared<T> {
+    inner: Arc<Mutex<T>>,
+    recovery: Option<Recovery<T>>,
+}
+
+impl<T> Shared<T> {
+    /// Ignore poisoning, the next holder sees the value as it was left.
+    pub fn new(value: T) -> Shared<T> {
+        Shared {
+            inner: Arc::new(Mutex::new(value)),
+            recovery: None,
+        }
+    }
+
+    /// Run `recovery` on the value before handing it out after a panic.
+    pub fn with_recovery<F>(value: T, recovery: F) -> Shared<T>
+    where
+        F: Fn(&mut T) + Send + Sync + 'static,
+    {
+        Shared {
+            inner: Arc::new(Mutex::new(value)),
+            recovery: Some(Arc::new(recovery)),
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                let mut guard = poisoned.into_inner();
+                if let Some(recovery) = self.recovery.as_ref() {
+                    recovery(&mut guard);
+                }
+                self.inner.clear_poison();
+                guard
+            }
+        }
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Shared<T> {
+        Shared {
+            inner: self.inner.clone(),
+            recovery: self.recovery.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Shared").field(&*self.lock()).finish()
+    }
+}
@@ -0,0 +1,222 @@
+/* Signals as tasks. The handler only does what is async-signal-safe: it
+ * marks the signal pending and writes a byte to a pipe. A listener thread,
+ * started with the first registration, reads the pipe and hands the work
+ * registered for each pending signal to its scheduler like any other
+ * immediate task. Signals arriving before the listener gets to them
+ * coalesce, the same as the kernel's.
+ *
+ * Handlers are process wide, registering replaces whatever handler the
+ * signal had before. Registrations go away with their scheduler, or once
+ * it refuses work for having shut down.
+ */
+use std::io;
+use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, Weak};
+
+use crate::error::SchedError;
+use crate::handle;
+use crate::scheduler::Scheduler;
+
+/// A signal to run work on, see `Scheduler::schedule_on_signal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Signal {
+    Hangup,
+    Interrupt,
+    Quit,
+    Terminate,
+    User1,
+    User2,
+}
+
+const SIGNALS: [Signal; 6] = [
+    Signal::Hangup,
+    Signal::Interrupt,
+    Signal::Quit,
+    Signal::Terminate,
+    Signal::User1,
+    Signal::User2,
+];
+
+impl Signal {
+    /// Its conventional name, which tasks it triggers are named after.
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Hangup => "SIGHUP",
+            Signal::Interrupt => "SIGINT",
+            Signal::Quit => "SIGQUIT",
+            Signal::Terminate => "SIGTERM",
+            Signal::User1 => "SIGUSR1",
+            Signal::User2 => "SIGUSR2",
+        }
+    }
+
+    fn number(self) -> libc::c_int {
+        match self {
+            Signal::Hangup => libc::SIGHUP,
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Quit => libc::SIGQUIT,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::User1 => libc::SIGUSR1,
+            Signal::User2 => libc::SIGUSR2,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+type Shared = Arc<Mutex<dyn FnMut() + Send + 'static>>;
+
+struct Registration {
+    signal: Signal,
+    scheduler: Weak<Scheduler>,
+    work: Shared,
+}
+
+/* Touched from the handler, atomics only */
+static PENDING: [AtomicBool; SIGNALS.len()] = [const { AtomicBool::new(false) }; SIGNALS.len()];
+static WAKE: AtomicI32 = AtomicI32::new(-1);
+
+struct Listener {
+    registrations: Mutex<Vec<Registration>>,
+    installed: Mutex<[bool; SIGNALS.len()]>,
+}
+
+static LISTENER: OnceLock<io::Result<Listener>> = OnceLock::new();
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    let Some(raised) = SIGNALS.iter().find(|s| s.number() == signal) else {
+        return;
+    };
+    PENDING[raised.index()].store(true, Ordering::SeqCst);
+
+    /* The interrupted code may be about to read errno */
+    unsafe {
+        let errno = *libc::__errno_location();
+        let byte = 1u8;
+        libc::write(
+            WAKE.load(Ordering::SeqCst),
+            &byte as *const u8 as *const libc::c_void,
+            1,
+        );
+        *libc::__errno_location() = errno;
+    }
+}
+
+fn listener() -> io::Result<&'static Listener> {
+    let listener = LISTENER.get_or_init(|| {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        /* A full pipe already has the listener coming */
+        unsafe { libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK) };
+        WAKE.store(fds[1], Ordering::SeqCst);
+
+        std::thread::Builder::new()
+            .name("event_scheduler signals".into())
+            .spawn(move || listen(fds[0]))?;
+        Ok(Listener {
+            registrations: Mutex::new(Vec::new()),
+            installed: Mutex::new([false; SIGNALS.len()]),
+        })
+    });
+
+    listener
+        .as_ref()
+        .map_err(|e| io::Error::new(e.kind(), e.to_string()))
+}
+
+fn listen(fd: RawFd) {
+    let mut buf = [0u8; 64];
+    loop {
+        let read = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if read < 0 && io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            error!("signal listener stopped: {}", io::Error::last_os_error());
+            return;
+        }
+
+        let Some(Ok(listener)) = LISTENER.get() else {
+            continue;
+        };
+        for signal in SIGNALS {
+            if PENDING[signal.index()].swap(false, Ordering::SeqCst) {
+                listener.dispatch(signal);
+            }
+        }
+    }
+}
+
+impl Listener {
+    fn dispatch(&self, signal: Signal) {
+        let mut registrations = self
+            .registrations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        registrations.retain(|r| {
+            let Some(scheduler) = r.scheduler.upgrade() else {
+                return false;
+            };
+            if r.signal != signal {
+                return true;
+            }
+
+            let work = r.work.clone();
+            let run =
+                handle::work_once(move || (work.lock().unwrap_or_else(PoisonError::into_inner))());
+            match scheduler.run(Some(signal.name()), None, None, run) {
+                Ok(()) => true,
+                Err(SchedError::ShutDown) => false,
+                Err(e) => {
+                    warn!("{} dropped: {}", signal.name(), e);
+                    true
+                }
+            }
+        });
+    }
+
+    fn install(&self, signal: Signal) -> io::Result<()> {
+        let mut installed = self
+            .installed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if installed[signal.index()] {
+            return Ok(());
+        }
+
+        let handler: extern "C" fn(libc::c_int) = on_signal;
+        let ret = unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal.number(), &action, std::ptr::null_mut())
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        installed[signal.index()] = true;
+        Ok(())
+    }
+}
+
+pub(crate) fn register<F>(scheduler: &Arc<Scheduler>, signal: Signal, work: F) -> io::Result<()>
+where
+    F: FnMut() + Send + 'static,
+{
+    let listener = listener()?;
+    listener
+        .registrations
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Registration {
+            signal,
+            scheduler: Arc::downgrade(scheduler),
+            work: Arc::new(Mutex::new(work)),
+        });
+    listener.install(signal)
+}
@@ -0,0 +1,400 @@
+/* The scheduler without threads: timers in a delta list, a FIFO of work
+ * ready to run and a virtual clock, all driven by the caller. Time only
+ * moves when asked to, and then straight to the next deadline, so every
+ * run is the same however fast the host is.
+ */
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::delta::{delta_check, delta_elapse, delta_insert, delta_remove, Delayed, DeltaList};
+use crate::TaskId;
+
+/// Work for a `SimScheduler`. Runs on the calling thread, so it need not
+/// be `Send`.
+pub type SimWork = Box<dyn FnMut() + 'static>;
+
+/* Shared rather than owned, so a snapshot can hold on to work the
+ * scheduler has since run and dropped
+ */
+type Shared = Rc<RefCell<SimWork>>;
+
+#[derive(Clone)]
+enum Job {
+    Once(Shared),
+    /* Tick n is due at anchor + n * interval, like the real scheduler */
+    Periodic {
+        work: Shared,
+        interval: Duration,
+        anchor: Duration,
+        ticks: u32,
+    },
+}
+
+#[derive(Clone)]
+struct Entry {
+    id: TaskId,
+    delay: Duration,
+    deadline: Duration,
+    job: Job,
+}
+
+impl Delayed for Entry {
+    fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    fn delay_mut(&mut self) -> &mut Duration {
+        &mut self.delay
+    }
+
+    fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    fn seq(&self) -> u64 {
+        self.id.as_u64()
+    }
+}
+
+#[derive(Clone)]
+struct Ready {
+    /* Immediate work has none */
+    id: Option<TaskId>,
+    job: Job,
+}
+
+#[derive(Clone, Default)]
+struct State {
+    now: Duration,
+    timers: DeltaList<Entry>,
+    ready: VecDeque<Ready>,
+    next_id: u64,
+    /* The task being run, true once it is cancelled from inside */
+    running: Option<(TaskId, bool)>,
+}
+
+impl State {
+    fn next_id(&mut self) -> TaskId {
+        let id = TaskId::from_u64(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn insert(&mut self, id: TaskId, deadline: Duration, job: Job) {
+        let entry = Entry {
+            id,
+            delay: deadline.saturating_sub(self.now),
+            deadline,
+            job,
+        };
+        delta_insert(&mut self.timers, entry);
+        delta_check(&self.timers, self.now);
+    }
+
+    /* Move timers due by now over to the ready queue, in deadline order */
+    fn expire(&mut self) {
+        while self
+            .timers
+            .front()
+            .is_some_and(|t| t.delay == Duration::ZERO)
+        {
+            let entry = self.timers.pop_front().unwrap();
+            self.ready.push_back(Ready {
+                id: Some(entry.id),
+                job: entry.job,
+            });
+        }
+    }
+
+    fn set_time(&mut self, now: Duration) {
+        delta_elapse(&mut self.timers, now.saturating_sub(self.now));
+        self.now = self.now.max(now);
+    }
+}
+
+/// A deterministic, single-threaded stand-in for `Scheduler`.
+///
+/// Work runs on the calling thread, only from `run_until_idle`, `step`
+/// and the `advance` calls, against a virtual clock starting at zero.
+/// Timeouts due at the same time run in the order they were scheduled,
+/// and ready work in FIFO order, so tests of timer-driven code replay
+/// exactly. Clones share the scheduler, for tasks to schedule more work.
+#[derive(Clone, Default)]
+pub struct SimScheduler {
+    state: Rc<RefCell<State>>,
+}
+
+impl SimScheduler {
+    pub fn new() -> SimScheduler {
+        SimScheduler::default()
+    }
+
+    /// Virtual time since the scheduler was created.
+    pub fn now(&self) -> Duration {
+        self.state.borrow().now
+    }
+
+    /// Queue `work` to run once, on the next `run_until_idle` or `step`.
+    /// It is `FnMut` so that every branch restored from a `snapshot` taken
+    /// before it ran gets to run it.
+    pub fn schedule<F: FnMut() + 'static>(&self, work: F) {
+        self.ready(shared(work));
+    }
+
+    /// Run `work` once `delay` has passed, `FnMut` like for `schedule`.
+    pub fn schedule_delayed<F: FnMut() + 'static>(&self, delay: Duration, work: F) -> TaskId {
+        self.delayed(delay, shared(work))
+    }
+
+    /* For the schedulers built on this one, which never snapshot it */
+    pub(crate) fn schedule_once<F: FnOnce() + 'static>(&self, work: F) {
+        self.ready(shared_once(work));
+    }
+
+    pub(crate) fn schedule_delayed_once<F: FnOnce() + 'static>(
+        &self,
+        delay: Duration,
+        work: F,
+    ) -> TaskId {
+        self.delayed(delay, shared_once(work))
+    }
+
+    fn ready(&self, work: Shared) {
+        self.state.borrow_mut().ready.push_back(Ready {
+            id: None,
+            job: Job::Once(work),
+        });
+    }
+
+    fn delayed(&self, delay: Duration, work: Shared) -> TaskId {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        let deadline = state.now.saturating_add(delay);
+        state.insert(id, deadline, Job::Once(work));
+        id
+    }
+
+    /// Run `work` every `interval`, the first time one interval from now.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero, which would never let time move on.
+    pub fn schedule_periodic<F: FnMut() + 'static>(&self, interval: Duration, work: F) -> TaskId {
+        assert!(
+            interval > Duration::ZERO,
+            "periodic interval must not be zero"
+        );
+
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        let anchor = state.now;
+        let job = Job::Periodic {
+            work: shared(work),
+            interval,
+            anchor,
+            ticks: 1,
+        };
+        state.insert(id, anchor.saturating_add(interval), job);
+        id
+    }
+
+    /// Stop `id` from running again, whether it is waiting, due or
+    /// running right now. False if it already ran or was cancelled.
+    pub fn cancel(&self, id: TaskId) -> bool {
+        let mut state = self.state.borrow_mut();
+        if delta_remove(&mut state.timers, |t| t.id == id).is_some() {
+            let now = state.now;
+            delta_check(&state.timers, now);
+            return true;
+        }
+
+        let ready = state.ready.len();
+        state.ready.retain(|r| r.id != Some(id));
+        if state.ready.len() != ready {
+            return true;
+        }
+
+        match state.running.as_mut() {
+            Some((running, cancelled)) if *running == id && !*cancelled => {
+                *cancelled = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// When the earliest timeout is due.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.state.borrow().timers.front().map(|t| t.deadline)
+    }
+
+    /// Timeouts waiting for their deadline, periodic ones counting once.
+    pub fn pending(&self) -> usize {
+        self.state.borrow().timers.len()
+    }
+
+    /// Capture the virtual time, pending timeouts and ready work, for
+    /// `restore` to branch off from later. Take it between runs, not from
+    /// inside a task.
+    ///
+    /// The work itself is shared, not copied: whatever a closure captured
+    /// is the same in every branch, and a closure runs again in each branch
+    /// that gets to it. Keep state to be branched outside the closures, or
+    /// have the closures reach it through handles the test swaps per
+    /// branch.
+    pub fn snapshot(&self) -> SimSnapshot {
+        let mut state = self.state.borrow().clone();
+        state.running = None;
+        SimSnapshot { state }
+    }
+
+    /// Go back to `snapshot`, dropping whatever was scheduled since. Task
+    /// ids continue from where they were when it was taken, so every
+    /// branch hands out the same ones.
+    pub fn restore(&self, snapshot: &SimSnapshot) {
+        *self.state.borrow_mut() = snapshot.state.clone();
+    }
+
+    /// Run ready work and whatever comes due without moving the clock, until
+    /// there is none. Returns how many tasks ran.
+    pub fn run_until_idle(&self) -> usize {
+        let mut ran = 0;
+        while self.run_one() {
+            ran += 1;
+        }
+        ran
+    }
+
+    /// Move the clock to the next deadline, if there is one, and run
+    /// everything that is then due. Returns whether the clock moved.
+    pub fn step(&self) -> bool {
+        self.run_until_idle();
+
+        let Some(deadline) = self.next_deadline() else {
+            return false;
+        };
+        self.state.borrow_mut().set_time(deadline);
+        self.run_until_idle();
+        true
+    }
+
+    /// Let `step` of virtual time pass, see `advance_to`.
+    pub fn advance_by(&self, step: Duration) -> usize {
+        self.advance_to(self.now().saturating_add(step))
+    }
+
+    /// Move the clock to `time`, on the same clock as `now`, running
+    /// everything due up to and including it before returning. The clock
+    /// stops at each deadline on the way, so tasks read the time they were
+    /// due at, and work they schedule inside the window runs too. Returns
+    /// how many tasks ran; times in the past only run what is ready.
+    pub fn advance_to(&self, time: Duration) -> usize {
+        let mut ran = self.run_until_idle();
+
+        while let Some(deadline) = self.next_deadline().filter(|d| *d <= time) {
+            self.state.borrow_mut().set_time(deadline);
+            ran += self.run_until_idle();
+        }
+
+        self.state.borrow_mut().set_time(time);
+        ran
+    }
+
+    /* Move the clock to `time` without running anything */
+    pub(crate) fn catch_up(&self, time: Duration) {
+        self.state.borrow_mut().set_time(time);
+    }
+
+    fn run_one(&self) -> bool {
+        let Ready { id, job } = {
+            let mut state = self.state.borrow_mut();
+            state.expire();
+            match state.ready.pop_front() {
+                Some(ready) => ready,
+                None => return false,
+            }
+        };
+
+        /* Not borrowed while it runs, it may well schedule more */
+        match job {
+            Job::Once(work) => (work.borrow_mut())(),
+            Job::Periodic {
+                work,
+                interval,
+                anchor,
+                ticks,
+            } => {
+                let id = id.expect("periodic work has an id");
+                self.state.borrow_mut().running = Some((id, false));
+                (work.borrow_mut())();
+
+                let mut state = self.state.borrow_mut();
+                if let Some((_, false)) = state.running.take() {
+                    let ticks = ticks + 1;
+                    let offset = interval.saturating_mul(ticks);
+                    let job = Job::Periodic {
+                        work,
+                        interval,
+                        anchor,
+                        ticks,
+                    };
+                    state.insert(id, anchor.saturating_add(offset), job);
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// A `SimScheduler` at one point in virtual time, see `snapshot`.
+#[derive(Clone)]
+pub struct SimSnapshot {
+    state: State,
+}
+
+impl SimSnapshot {
+    pub fn now(&self) -> Duration {
+        self.state.now
+    }
+
+    pub fn pending(&self) -> usize {
+        self.state.timers.len()
+    }
+}
+
+impl std::fmt::Debug for SimSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimSnapshot")
+            .field("now", &self.state.now)
+            .field("pending", &self.state.timers.len())
+            .field("ready", &self.state.ready.len())
+            .finish()
+    }
+}
+
+fn shared<F: FnMut() + 'static>(work: F) -> Shared {
+    Rc::new(RefCell::new(Box::new(work)))
+}
+
+fn shared_once<F: FnOnce() + 'static>(work: F) -> Shared {
+    let mut work = Some(work);
+    shared(move || {
+        if let Some(work) = work.take() {
+            work();
+        }
+    })
+}
+
+impl std::fmt::Debug for SimScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("SimScheduler")
+            .field("now", &state.now)
+            .field("pending", &state.timers.len())
+            .field("ready", &state.ready.len())
+            .finish()
+    }
+}
@@ -0,0 +1,198 @@
+/* Async submission with backpressure. A sink holds on to one job until the
+ * scheduler has room for it under Builder::max_pending, sleeping on a
+ * waker in the meantime. The Room observer the builder installs wakes
+ * sleeping sinks whenever a timeout fires or is cancelled, at the cost of
+ * an atomic load per event while none sleeps.
+ */
+use std::future::poll_fn;
+use std::sync::PoisonError;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::error::{Result, SchedError};
+use crate::handle::{self, Completion};
+use crate::observer::{CancelEvent, FireEvent, SchedulerObserver};
+use crate::scheduler::Scheduler;
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sync::Mutex;
+use crate::Work;
+
+/// Work to submit through a `ScheduleSink`.
+pub struct Job {
+    delay: Option<Duration>,
+    name: Option<&'static str>,
+    work: Work,
+}
+
+impl Job {
+    /// Run `work` right away, like `Scheduler::schedule`. Immediate work
+    /// never waits for room.
+    pub fn now<F>(work: F) -> Job
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        Job {
+            delay: None,
+            name: None,
+            work: handle::work_once(work),
+        }
+    }
+
+    /// Run `work` once `delay` has passed, like `Scheduler::schedule_delayed`.
+    pub fn after<F>(delay: Duration, work: F) -> Job
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        Job {
+            delay: Some(delay),
+            name: None,
+            work: handle::work_once(work),
+        }
+    }
+
+    /// Schedule it under `name`, see `Scheduler::named`.
+    pub fn named(mut self, name: &'static str) -> Job {
+        self.name = Some(name);
+        self
+    }
+}
+
+impl std::fmt::Debug for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Job")
+            .field("delay", &self.delay)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Feeds jobs to a scheduler from async code, see `Scheduler::sink`.
+/// Rather than failing with `TooManyTimers` once `Builder::max_pending`
+/// timeouts are pending, it waits for one to fire or be cancelled. With
+/// the `sink` feature it is a `futures::Sink<Job>`.
+pub struct ScheduleSink<'a> {
+    scheduler: &'a Scheduler,
+    /* Accepted by start_send, waiting for room */
+    job: Option<Job>,
+}
+
+impl<'a> ScheduleSink<'a> {
+    pub(crate) fn new(scheduler: &'a Scheduler) -> ScheduleSink<'a> {
+        ScheduleSink {
+            scheduler,
+            job: None,
+        }
+    }
+
+    /// Submit `job` once there is room for it.
+    pub async fn submit(&mut self, job: Job) -> Result<()> {
+        poll_fn(|cx| self.poll_flush_job(cx)).await?;
+        self.job = Some(job);
+        poll_fn(|cx| self.poll_flush_job(cx)).await
+    }
+
+    /* Ready once the held job, if any, is submitted */
+    fn poll_flush_job(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let Some(job) = self.job.take() else {
+            return Poll::Ready(Ok(()));
+        };
+        let Some(delay) = job.delay else {
+            return Poll::Ready(self.scheduler.run(job.name, None, None, job.work));
+        };
+
+        match self.scheduler.reserve() {
+            Ok(()) => {}
+            Err(SchedError::TooManyTimers) => {
+                self.scheduler.room().sleep(cx.waker());
+                /* Room may have been made before the waker was in */
+                if self.scheduler.reserve().is_err() {
+                    self.job = Some(job);
+                    return Poll::Pending;
+                }
+            }
+            Err(e) => return Poll::Ready(Err(e)),
+        }
+
+        let timeout = match self.scheduler.delayed(job.name, delay, job.work) {
+            Ok(timeout) => timeout,
+            Err(e) => {
+                self.scheduler.release();
+                return Poll::Ready(Err(e));
+            }
+        };
+        let submitted = self
+            .scheduler
+            .submit_reserved(timeout, Completion::<()>::new());
+        Poll::Ready(submitted.map(drop))
+    }
+}
+
+#[cfg(feature = "sink")]
+impl futures_sink::Sink<Job> for ScheduleSink<'_> {
+    type Error = SchedError;
+
+    fn poll_ready(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().poll_flush_job(cx)
+    }
+
+    fn start_send(self: std::pin::Pin<&mut Self>, job: Job) -> Result<()> {
+        self.get_mut().job = Some(job);
+        Ok(())
+    }
+
+    fn poll_flush(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().poll_flush_job(cx)
+    }
+
+    fn poll_close(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().poll_flush_job(cx)
+    }
+}
+
+impl std::fmt::Debug for ScheduleSink<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScheduleSink")
+            .field("job", &self.job)
+            .finish()
+    }
+}
+
+/* Wakers of sinks waiting for a pending timeout to go */
+#[derive(Default)]
+pub(crate) struct Room {
+    sleeping: Mutex<Vec<Waker>>,
+    count: AtomicUsize,
+}
+
+impl Room {
+    fn sleep(&self, waker: &Waker) {
+        let mut sleeping = self.sleeping.lock().unwrap_or_else(PoisonError::into_inner);
+        if !sleeping.iter().any(|w| w.will_wake(waker)) {
+            sleeping.push(waker.clone());
+        }
+        self.count.store(sleeping.len(), Ordering::SeqCst);
+    }
+
+    pub(crate) fn wake(&self) {
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        let sleeping = {
+            let mut sleeping = self.sleeping.lock().unwrap_or_else(PoisonError::into_inner);
+            self.count.store(0, Ordering::SeqCst);
+            std::mem::take(&mut *sleeping)
+        };
+        sleeping.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl SchedulerObserver for Room {
+    fn on_fire(&self, _event: &FireEvent) {
+        self.wake();
+    }
+
+    fn on_cancel(&self, _event: &CancelEvent) {
+        self.wake();
+    }
+}
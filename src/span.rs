@@ -0,0 +1,106 @@
+use std::time::Duration;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+use crate::context::TaskContext;
+use crate::{TaskId, Work};
+
+/* With the `tracing` feature, a span per task from schedule to its last
+ * run. The fire's lateness and the run's duration are recorded on it as
+ * they happen, periodic tasks overwrite them every tick. Without it this
+ * is empty and costs nothing. It also carries the OpenTelemetry context,
+ * see context.rs.
+ */
+#[derive(Clone)]
+pub(crate) struct TaskSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    context: TaskContext,
+}
+
+#[cfg(feature = "tracing")]
+impl TaskSpan {
+    pub(crate) fn new(id: TaskId, delay: Duration) -> TaskSpan {
+        TaskSpan {
+            span: tracing::info_span!(
+                "task",
+                id = %id,
+                name = tracing::field::Empty,
+                tags = tracing::field::Empty,
+                delay = ?delay,
+                lateness = tracing::field::Empty,
+                duration = tracing::field::Empty,
+            ),
+            context: TaskContext::capture(),
+        }
+    }
+
+    /* Immediate work has no id */
+    pub(crate) fn immediate() -> TaskSpan {
+        TaskSpan {
+            span: tracing::info_span!(
+                "task",
+                name = tracing::field::Empty,
+                tags = tracing::field::Empty,
+                delay = ?Duration::ZERO,
+                duration = tracing::field::Empty,
+            ),
+            context: TaskContext::capture(),
+        }
+    }
+
+    pub(crate) fn name(&self, name: &'static str) {
+        self.span.record("name", name);
+    }
+
+    pub(crate) fn tags(&self, tags: &str) {
+        self.span.record("tags", tags);
+    }
+
+    pub(crate) fn fired(&self, lateness: Duration) {
+        self.span
+            .record("lateness", tracing::field::debug(lateness));
+    }
+
+    /* Run `work` inside the span and record how long it took */
+    pub(crate) fn instrument(&self, mut work: Work) -> Work {
+        let span = self.span.clone();
+        self.context.attach(Box::new(move || {
+            let _entered = span.enter();
+            let start = Instant::now();
+            work();
+            span.record("duration", tracing::field::debug(start.elapsed()));
+        }))
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl TaskSpan {
+    #[inline(always)]
+    pub(crate) fn new(_id: TaskId, _delay: Duration) -> TaskSpan {
+        TaskSpan {
+            context: TaskContext::capture(),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn immediate() -> TaskSpan {
+        TaskSpan {
+            context: TaskContext::capture(),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn name(&self, _name: &'static str) {}
+
+    #[inline(always)]
+    pub(crate) fn tags(&self, _tags: &str) {}
+
+    #[inline(always)]
+    pub(crate) fn fired(&self, _lateness: Duration) {}
+
+    #[inline(always)]
+    pub(crate) fn instrument(&self, work: Work) -> Work {
+        self.context.attach(work)
+    }
+}
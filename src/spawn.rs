@@ -0,0 +1,121 @@
+/* Where the worker stage runs tasks when an async runtime is configured.
+ * The worker thread then only takes tasks off the queue and spawns them,
+ * the timekeeper keeps timing them.
+ */
+use std::future::Future;
+use std::pin::Pin;
+
+#[cfg(feature = "rayon")]
+use crate::pool::{Pool, PoolTask};
+
+/// A task handed to a `Spawner`, boxed so any runtime can take it.
+pub type Spawned = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// An async runtime to run tasks on instead of the worker thread, see
+/// `Builder::spawner`. It is the executor half of the scheduler, what the
+/// timekeeper sleeps on is the other, see `TimerBackend`. Without one
+/// tasks run on the worker thread, which is no `Spawner`: its panic
+/// policy, restarts and heartbeat need more than spawning and forgetting.
+pub trait Spawner: Send + Sync + 'static {
+    /// Run `task` to completion in the background.
+    fn spawn(&self, task: Spawned);
+
+    /// Run `task` near the memory of NUMA node `node`, the hint given with
+    /// `Named::numa_node`. Spawners that know nothing of nodes spawn it as
+    /// usual.
+    fn spawn_on_node(&self, task: Spawned, node: usize) {
+        let _ = node;
+        self.spawn(task)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Spawner for tokio::runtime::Handle {
+    fn spawn(&self, task: Spawned) {
+        drop(tokio::runtime::Handle::spawn(self, task));
+    }
+}
+
+/// Spawns onto async-std's global executor.
+#[cfg(feature = "async-std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStdSpawner;
+
+#[cfg(feature = "async-std")]
+impl Spawner for AsyncStdSpawner {
+    fn spawn(&self, task: Spawned) {
+        drop(async_std::task::spawn(task));
+    }
+}
+
+/// Spawns onto smol's global executor.
+#[cfg(feature = "smol")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmolSpawner;
+
+#[cfg(feature = "smol")]
+impl Spawner for SmolSpawner {
+    fn spawn(&self, task: Spawned) {
+        smol::spawn(task).detach();
+    }
+}
+
+/* An executor of the application's own, which it has to keep running */
+#[cfg(feature = "smol")]
+impl Spawner for std::sync::Arc<smol::Executor<'static>> {
+    fn spawn(&self, task: Spawned) {
+        smol::Executor::spawn(self, task).detach();
+    }
+}
+
+/// Runs tasks on a rayon thread pool, for CPU-bound work triggered by
+/// timers. Futures are polled on the pool too, each wake spawning the next
+/// poll.
+#[cfg(feature = "rayon")]
+#[derive(Clone, Debug, Default)]
+pub struct RayonSpawner {
+    /* Rayon's global pool if None */
+    pool: Option<std::sync::Arc<rayon::ThreadPool>>,
+    fifo: bool,
+}
+
+#[cfg(feature = "rayon")]
+impl RayonSpawner {
+    /// Spawn onto rayon's global pool.
+    pub fn global() -> RayonSpawner {
+        RayonSpawner::default()
+    }
+
+    pub fn new(pool: std::sync::Arc<rayon::ThreadPool>) -> RayonSpawner {
+        RayonSpawner {
+            pool: Some(pool),
+            fifo: false,
+        }
+    }
+
+    /// Start tasks in the order they were handed over, with `spawn_fifo`
+    /// instead of rayon's `spawn`, which runs a thread's newest first.
+    pub fn fifo(mut self, fifo: bool) -> RayonSpawner {
+        self.fifo = fifo;
+        self
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Pool for RayonSpawner {
+    fn run<F: FnOnce() + Send + 'static>(&self, run: F) {
+        match (&self.pool, self.fifo) {
+            (None, false) => rayon::spawn(run),
+            (None, true) => rayon::spawn_fifo(run),
+            (Some(pool), false) => pool.spawn(run),
+            (Some(pool), true) => pool.spawn_fifo(run),
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Spawner for RayonSpawner {
+    fn spawn(&self, task: Spawned) {
+        PoolTask::spawn(self, task);
+    }
+}
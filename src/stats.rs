@@ -0,0 +1,163 @@
+use crate::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::health::Heartbeats;
+use crate::histogram::AtomicHistogram;
+use crate::timings::Timings;
+
+/// Snapshot of scheduler diagnostics.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats {
+    /// Mean sleep overshoot measured at startup, if calibration ran.
+    pub calibrated_overshoot: Option<Duration>,
+    /// How far ahead of deadlines the timekeeper currently wakes up.
+    pub compensation_margin: Duration,
+    /// Total lateness of periodic ticks against their anchored schedule,
+    /// the drift re-arming from the fire time would have accumulated.
+    pub periodic_drift: Duration,
+    /// Timeouts submitted and neither fired nor cancelled.
+    pub pending: usize,
+    /// Work handed to the worker and not finished, the running task
+    /// included.
+    pub queued: usize,
+    /// Most timeouts pending at once since the scheduler was built.
+    pub peak_pending: usize,
+    /// Most work queued at once since the scheduler was built.
+    pub peak_queued: usize,
+    /// Like `peak_pending`, since the last `Scheduler::reset_peaks`.
+    pub recent_peak_pending: usize,
+    /// Like `peak_queued`, since the last `Scheduler::reset_peaks`.
+    pub recent_peak_queued: usize,
+    /// Expiries handed to the worker, every tick of a periodic timeout
+    /// counting once.
+    pub fired: u64,
+    /// Timeouts cancelled before they fired.
+    pub cancelled: u64,
+    /// Tasks that ran past `Builder::slow_task_threshold`.
+    pub slow_tasks: u64,
+    /// Fires later than `Builder::lateness_budget`, per name in
+    /// `Scheduler::task_timings`.
+    pub missed_deadlines: u64,
+    /// Share of the time since the scheduler was built that the worker
+    /// spent running tasks, from 0 to 1.
+    pub worker_utilization: f64,
+    /// Mean lateness of all fires so far.
+    pub mean_lateness: Duration,
+    /// Worst lateness of all fires so far.
+    pub max_lateness: Duration,
+}
+
+/* Live values shared with the scheduler threads */
+#[derive(Default)]
+pub(crate) struct Counters {
+    pub(crate) compensation_margin: AtomicU64,
+    /* Work handed to the worker and not yet finished */
+    pub(crate) in_flight: AtomicUsize,
+    /* Timeouts submitted and neither fired nor cancelled */
+    pub(crate) pending: AtomicUsize,
+    /* Highest of the two depths above, since start and since reset */
+    pub(crate) pending_peak: AtomicUsize,
+    pub(crate) queued_peak: AtomicUsize,
+    pub(crate) recent_pending_peak: AtomicUsize,
+    pub(crate) recent_queued_peak: AtomicUsize,
+    pub(crate) periodic_drift: AtomicU64,
+    pub(crate) fired: AtomicU64,
+    pub(crate) cancelled: AtomicU64,
+    pub(crate) slow_tasks: AtomicU64,
+    pub(crate) missed_deadlines: AtomicU64,
+    /* Nanoseconds the worker spent running tasks */
+    pub(crate) busy: AtomicU64,
+    /* Nanoseconds, over all fires */
+    pub(crate) lateness_total: AtomicU64,
+    pub(crate) lateness_max: AtomicU64,
+    pub(crate) lateness_histogram: AtomicHistogram,
+    pub(crate) execution_histogram: AtomicHistogram,
+    /* Execution time of named tasks */
+    pub(crate) task_timings: Timings,
+    pub(crate) heartbeats: Heartbeats,
+    /* The timekeeper failed to reserve room for another timeout */
+    pub(crate) store_full: AtomicBool,
+}
+
+impl Counters {
+    pub(crate) fn set_margin(&self, margin: Duration) {
+        self.compensation_margin
+            .store(margin.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn margin(&self) -> Duration {
+        Duration::from_nanos(self.compensation_margin.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn add_drift(&self, drift: Duration) {
+        self.periodic_drift
+            .fetch_add(drift.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn drift(&self) -> Duration {
+        Duration::from_nanos(self.periodic_drift.load(Ordering::Relaxed))
+    }
+
+    /* Hand one more piece of work to the worker */
+    pub(crate) fn queue(&self) {
+        let queued = self.in_flight.fetch_add(1, Ordering::AcqRel) + 1;
+        self.queued_peak.fetch_max(queued, Ordering::Relaxed);
+        self.recent_queued_peak.fetch_max(queued, Ordering::Relaxed);
+    }
+
+    /* After admitting a timeout, `pending` counting it */
+    pub(crate) fn note_pending(&self, pending: usize) {
+        self.pending_peak.fetch_max(pending, Ordering::Relaxed);
+        self.recent_pending_peak
+            .fetch_max(pending, Ordering::Relaxed);
+    }
+
+    /* Start the recent peaks over from the current depths */
+    pub(crate) fn reset_peaks(&self) {
+        self.recent_pending_peak
+            .store(self.pending.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.recent_queued_peak
+            .store(self.in_flight.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_fire(&self, lateness: Duration) {
+        let lateness = lateness.as_nanos() as u64;
+        self.fired.fetch_add(1, Ordering::Relaxed);
+        self.lateness_total.fetch_add(lateness, Ordering::Relaxed);
+        self.lateness_max.fetch_max(lateness, Ordering::Relaxed);
+        self.lateness_histogram
+            .record(Duration::from_nanos(lateness));
+    }
+
+    pub(crate) fn add_busy(&self, busy: Duration) {
+        self.busy
+            .fetch_add(busy.as_nanos() as u64, Ordering::Relaxed);
+        self.execution_histogram.record(busy);
+    }
+
+    pub(crate) fn mean_lateness(&self) -> Duration {
+        let fired = self.fired.load(Ordering::Relaxed);
+        match fired {
+            0 => Duration::ZERO,
+            fired => Duration::from_nanos(self.lateness_total.load(Ordering::Relaxed) / fired),
+        }
+    }
+
+    pub(crate) fn max_lateness(&self) -> Duration {
+        Duration::from_nanos(self.lateness_max.load(Ordering::Relaxed))
+    }
+
+    /* Busy share of `elapsed` */
+    pub(crate) fn utilization(&self, elapsed: Duration) -> f64 {
+        if elapsed == Duration::ZERO {
+            return 0.0;
+        }
+
+        let busy = Duration::from_nanos(self.busy.load(Ordering::Relaxed));
+        (busy.as_secs_f64() / elapsed.as_secs_f64()).min(1.0)
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        self.in_flight.load(Ordering::Acquire) == 0
+    }
+}
@@ -0,0 +1,168 @@
+/* Where DurableTimers keep their jobs. DurableTimers only ever put, remove
+ * and read back whole jobs, and keep their own index of what's pending, so
+ * a store is a map from JobId to job that is durable on every change. The
+ * built-in ones are FileStore, the journal in journal.rs, and MemoryStore,
+ * for tests and for timers that needn't outlive the process.
+ */
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+use crate::durable::{DurableJob, JobId};
+use crate::journal::{Journal, Record};
+
+/// Storage for `DurableTimers`, implement it to keep jobs in RocksDB,
+/// SQLite or anything else, see `DurableBuilder::open_store`.
+///
+/// A change must be durable once `put` or `remove` returns, it is only
+/// then that `DurableTimers` acknowledges it. Calls come one at a time.
+pub trait TimerStore: Send + 'static {
+    /// Keep `job`, replacing the one with its id if there is one.
+    fn put(&mut self, job: &DurableJob) -> io::Result<()>;
+
+    /// Forget job `id`, it fired or was cancelled. Nothing to do if it
+    /// isn't kept.
+    fn remove(&mut self, id: JobId) -> io::Result<()>;
+
+    /// The jobs due at `until` or before, in any order.
+    fn scan_due(&self, until: SystemTime) -> io::Result<Vec<DurableJob>>;
+
+    /// All the jobs kept, in any order.
+    fn snapshot(&self) -> io::Result<Vec<DurableJob>>;
+
+    /// An id above that of every job ever put, removed ones included, so
+    /// no id is handed out twice.
+    fn next_id(&self) -> io::Result<JobId>;
+}
+
+/// Keeps jobs in a journal file, what `DurableTimers::open` uses. Each
+/// change is appended and synced, the file is compacted as it fills up
+/// with jobs that are gone.
+pub struct FileStore {
+    journal: Journal,
+    jobs: BTreeMap<JobId, DurableJob>,
+}
+
+impl FileStore {
+    /// Replay the journal in `path`, created if missing. A last write torn
+    /// by a crash is cut off, anything else unreadable is an
+    /// `InvalidData` error.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FileStore> {
+        let (journal, jobs) = Journal::open(path.as_ref())?;
+        Ok(FileStore { journal, jobs })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.journal.path()
+    }
+
+    /// Fail syncing the directory after each of the next `count`
+    /// compactions, as a failing disk would.
+    #[cfg(feature = "fault-injection")]
+    pub fn fail_dir_syncs(mut self, count: usize) -> FileStore {
+        self.journal.fail_dir_syncs(count);
+        self
+    }
+}
+
+impl TimerStore for FileStore {
+    fn put(&mut self, job: &DurableJob) -> io::Result<()> {
+        self.journal.append(Record::Put(job))?;
+        self.jobs.insert(job.id, job.clone());
+        Ok(())
+    }
+
+    fn remove(&mut self, id: JobId) -> io::Result<()> {
+        if !self.jobs.contains_key(&id) {
+            return Ok(());
+        }
+
+        self.journal.append(Record::Remove(id))?;
+        self.jobs.remove(&id);
+        /* What was journaled stands either way */
+        if let Err(e) = self.journal.compact_if_due(self.jobs.values()) {
+            warn!("failed to compact {}: {}", self.path().display(), e);
+        }
+        Ok(())
+    }
+
+    fn scan_due(&self, until: SystemTime) -> io::Result<Vec<DurableJob>> {
+        Ok(due(&self.jobs, until))
+    }
+
+    fn snapshot(&self) -> io::Result<Vec<DurableJob>> {
+        Ok(self.jobs.values().cloned().collect())
+    }
+
+    fn next_id(&self) -> io::Result<JobId> {
+        Ok(self.journal.next_id())
+    }
+}
+
+impl std::fmt::Debug for FileStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileStore")
+            .field("path", &self.path())
+            .field("jobs", &self.jobs.len())
+            .finish()
+    }
+}
+
+/// Keeps jobs in memory, gone with the process. Clones share the jobs, so
+/// one kept aside can hand them to the `DurableTimers` opened after a
+/// restart played in a test.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    inner: Arc<Mutex<Memory>>,
+}
+
+#[derive(Debug, Default)]
+struct Memory {
+    jobs: BTreeMap<JobId, DurableJob>,
+    next_id: u64,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Memory> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl TimerStore for MemoryStore {
+    fn put(&mut self, job: &DurableJob) -> io::Result<()> {
+        let mut memory = self.lock();
+        memory.next_id = memory.next_id.max(job.id.as_u64() + 1);
+        memory.jobs.insert(job.id, job.clone());
+        Ok(())
+    }
+
+    fn remove(&mut self, id: JobId) -> io::Result<()> {
+        self.lock().jobs.remove(&id);
+        Ok(())
+    }
+
+    fn scan_due(&self, until: SystemTime) -> io::Result<Vec<DurableJob>> {
+        Ok(due(&self.lock().jobs, until))
+    }
+
+    fn snapshot(&self) -> io::Result<Vec<DurableJob>> {
+        Ok(self.lock().jobs.values().cloned().collect())
+    }
+
+    fn next_id(&self) -> io::Result<JobId> {
+        Ok(JobId::from_u64(self.lock().next_id))
+    }
+}
+
+fn due(jobs: &BTreeMap<JobId, DurableJob>, until: SystemTime) -> Vec<DurableJob> {
+    jobs.values()
+        .filter(|job| job.due <= until)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,19 @@
+/* Primitives the scheduler threads hand work and state over with. Built
+ * with `--cfg loom` they come from loom instead, so tests/loom.rs can
+ * model-check every interleaving of the timekeeper, worker and callers.
+ * Arc stays std's as loom's can't hold trait objects and its refcount is
+ * no handoff of ours.
+ *
+ * What is only there to be watched, statistics, health and event feeds,
+ * takes std's primitives directly even under loom. Nothing is handed over
+ * through them and loom would only have more state to explore.
+ */
+#[cfg(loom)]
+pub(crate) use loom::sync::{atomic, mpsc, Condvar, Mutex, MutexGuard};
+#[cfg(loom)]
+pub(crate) use loom::thread;
+
+#[cfg(not(loom))]
+pub(crate) use std::sync::{atomic, mpsc, Condvar, Mutex, MutexGuard};
+#[cfg(not(loom))]
+pub(crate) use std::thread;
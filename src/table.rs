@@ -0,0 +1,145 @@
+/* A timer table for firmware without a heap. The same ordering as
+ * TimerQueue, deadline first and ties in insertion order, kept in a fixed
+ * array instead of a delta list. Entries are sorted latest first so the
+ * next one to expire comes off the end without shifting the rest.
+ */
+use core::time::Duration;
+
+use crate::TaskId;
+
+#[derive(Clone, Copy)]
+struct Slot {
+    id: TaskId,
+    deadline: Duration,
+}
+
+impl Slot {
+    const EMPTY: Slot = Slot {
+        id: TaskId::from_u64(0),
+        deadline: Duration::ZERO,
+    };
+
+    fn order(&self) -> (Duration, TaskId) {
+        (self.deadline, self.id)
+    }
+}
+
+/// Up to `N` timers in statically allocated storage, for `no_std` targets
+/// without an allocator. Holds only task ids, what to run for each is up
+/// to the caller. Driven by explicit `tick`s like `TimerQueue`, on the
+/// same kind of caller-read clock.
+///
+/// `new` is const, so a table can live in a `static`.
+pub struct TimerTable<const N: usize> {
+    slots: [Slot; N],
+    len: usize,
+    now: Duration,
+    next_id: u64,
+}
+
+impl<const N: usize> TimerTable<N> {
+    pub const fn new(now: Duration) -> TimerTable<N> {
+        TimerTable {
+            slots: [Slot::EMPTY; N],
+            len: 0,
+            now,
+            next_id: 0,
+        }
+    }
+
+    /// Add a timer expiring `delay` after the last tick. None if all `N`
+    /// slots are taken.
+    pub fn insert(&mut self, delay: Duration) -> Option<TaskId> {
+        if self.len == N {
+            return None;
+        }
+
+        let id = TaskId::from_u64(self.next_id);
+        self.next_id += 1;
+        let new = Slot {
+            id,
+            deadline: self.now.saturating_add(delay),
+        };
+
+        /* Behind everything expiring later, ahead of what expires sooner */
+        let at = self.slots[..self.len]
+            .iter()
+            .position(|s| s.order() < new.order())
+            .unwrap_or(self.len);
+        self.slots.copy_within(at..self.len, at + 1);
+        self.slots[at] = new;
+        self.len += 1;
+        Some(id)
+    }
+
+    /// Take `id` out of the table, false if it expired or was removed.
+    pub fn remove(&mut self, id: TaskId) -> bool {
+        let Some(at) = self.slots[..self.len].iter().position(|s| s.id == id) else {
+            return false;
+        };
+
+        self.slots.copy_within(at + 1..self.len, at);
+        self.len -= 1;
+        true
+    }
+
+    /// When the earliest timer expires, for the caller to arm a hardware
+    /// timer with. May be before the last tick if that left expired timers
+    /// behind.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.len
+            .checked_sub(1)
+            .map(|last| self.slots[last].deadline)
+    }
+
+    /// Move time forward to `now` and return the ids of every timer that
+    /// expired, in deadline order. Times earlier than the last tick are
+    /// ignored.
+    pub fn tick(&mut self, now: Duration) -> Due<'_, N> {
+        self.now = self.now.max(now);
+        Due { table: self }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> core::fmt::Debug for TimerTable<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TimerTable")
+            .field("len", &self.len)
+            .field("capacity", &N)
+            .field("next_deadline", &self.next_deadline())
+            .finish()
+    }
+}
+
+/// Ids expired by a `TimerTable::tick`. Whatever is not iterated stays in
+/// the table, due immediately.
+pub struct Due<'a, const N: usize> {
+    table: &'a mut TimerTable<N>,
+}
+
+impl<const N: usize> Iterator for Due<'_, N> {
+    type Item = TaskId;
+
+    fn next(&mut self) -> Option<TaskId> {
+        let table = &mut *self.table;
+        let last = table.len.checked_sub(1)?;
+        if table.slots[last].deadline > table.now {
+            return None;
+        }
+
+        table.len = last;
+        Some(table.slots[last].id)
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(feature = "std")]
+use crate::sync::atomic::{AtomicU64, Ordering};
+
+/// Identifies a scheduled task for the lifetime of its scheduler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    pub(crate) const fn from_u64(id: u64) -> TaskId {
+        TaskId(id)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl core::fmt::Display for TaskId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub(crate) struct TaskIds {
+    next: AtomicU64,
+}
+
+#[cfg(feature = "std")]
+impl TaskIds {
+    pub(crate) fn next(&self) -> TaskId {
+        TaskId(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,87 @@
+/* Scheduler metrics through the metrics crate with the `metrics` feature,
+ * for whatever recorder the application installed (Prometheus exporter or
+ * otherwise). Without it every call here compiles to nothing.
+ */
+use std::time::Duration;
+
+use crate::stats::Counters;
+#[cfg(feature = "metrics")]
+use crate::sync::atomic::Ordering;
+
+#[cfg(feature = "metrics")]
+const PENDING: &str = "event_scheduler_pending_timers";
+#[cfg(feature = "metrics")]
+const QUEUE_DEPTH: &str = "event_scheduler_queue_depth";
+#[cfg(feature = "metrics")]
+const PENDING_PEAK: &str = "event_scheduler_pending_timers_peak";
+#[cfg(feature = "metrics")]
+const QUEUE_DEPTH_PEAK: &str = "event_scheduler_queue_depth_peak";
+#[cfg(feature = "metrics")]
+const FIRED: &str = "event_scheduler_fired_total";
+#[cfg(feature = "metrics")]
+const CANCELLED: &str = "event_scheduler_cancelled_total";
+#[cfg(feature = "metrics")]
+const LATENESS: &str = "event_scheduler_fire_lateness_seconds";
+#[cfg(feature = "metrics")]
+const EXECUTION: &str = "event_scheduler_execution_seconds";
+
+#[cfg(feature = "metrics")]
+pub(crate) fn describe() {
+    use metrics::Unit;
+
+    metrics::describe_gauge!(
+        PENDING,
+        "Timeouts submitted and neither fired nor cancelled"
+    );
+    metrics::describe_gauge!(QUEUE_DEPTH, "Work handed to the worker and not finished");
+    metrics::describe_gauge!(PENDING_PEAK, "Most timeouts pending at once");
+    metrics::describe_gauge!(QUEUE_DEPTH_PEAK, "Most work queued at once");
+    metrics::describe_counter!(FIRED, "Expiries handed to the worker");
+    metrics::describe_counter!(CANCELLED, "Timeouts cancelled before they fired");
+    metrics::describe_histogram!(LATENESS, Unit::Seconds, "How late timeouts fired");
+    metrics::describe_histogram!(EXECUTION, Unit::Seconds, "How long tasks ran");
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn gauges(counters: &Counters) {
+    metrics::gauge!(PENDING).set(counters.pending.load(Ordering::Relaxed) as f64);
+    metrics::gauge!(QUEUE_DEPTH).set(counters.in_flight.load(Ordering::Relaxed) as f64);
+    metrics::gauge!(PENDING_PEAK).set(counters.pending_peak.load(Ordering::Relaxed) as f64);
+    metrics::gauge!(QUEUE_DEPTH_PEAK).set(counters.queued_peak.load(Ordering::Relaxed) as f64);
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn fired(lateness: Duration) {
+    metrics::counter!(FIRED).increment(1);
+    metrics::histogram!(LATENESS).record(lateness);
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn cancelled() {
+    metrics::counter!(CANCELLED).increment(1);
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn executed(duration: Duration) {
+    metrics::histogram!(EXECUTION).record(duration);
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn describe() {}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn gauges(_counters: &Counters) {}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn fired(_lateness: Duration) {}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn cancelled() {}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn executed(_duration: Duration) {}
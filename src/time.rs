@@ -0,0 +1,319 @@
+/* Async timing on the scheduler's timekeeper, for any runtime. The free
+ * functions use a scheduler of their own, started on first use with the
+ * default configuration and never shut down.
+ */
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::error::SchedError;
+use crate::handle::TimeoutHandle;
+use crate::scheduler::Scheduler;
+
+fn default_scheduler() -> &'static Scheduler {
+    static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+    SCHEDULER.get_or_init(Scheduler::new)
+}
+
+/// Wait until `duration` has passed, timed by a default scheduler.
+pub fn sleep(duration: Duration) -> Result<Sleep, SchedError> {
+    default_scheduler().sleep(duration)
+}
+
+/// Tick every `period`, timed by a default scheduler.
+pub fn interval(period: Duration) -> Interval<'static> {
+    default_scheduler().interval(period)
+}
+
+/// Run `future` for at most `duration`, timed by a default scheduler.
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Result<WithTimeout<F>, SchedError> {
+    default_scheduler().timeout(duration, future)
+}
+
+/// A future resolving once its delay has passed, see `sleep`. Dropping it
+/// cancels the timeout. It also resolves if the scheduler shuts down
+/// first.
+#[must_use = "futures do nothing unless awaited"]
+pub struct Sleep {
+    timeout: Option<TimeoutHandle>,
+}
+
+impl Sleep {
+    pub(crate) fn new(scheduler: &Scheduler, duration: Duration) -> Result<Sleep, SchedError> {
+        Ok(Sleep {
+            timeout: Some(scheduler.schedule_delayed(duration, || {})?),
+        })
+    }
+
+    pub fn is_elapsed(&self) -> bool {
+        self.timeout.is_none()
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let Some(timeout) = self.timeout.as_mut() else {
+            return Poll::Ready(());
+        };
+        match Pin::new(timeout).poll(cx) {
+            Poll::Ready(_) => {
+                self.timeout = None;
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(timeout) = self.timeout.take() {
+            let _ = timeout.cancel();
+        }
+    }
+}
+
+impl std::fmt::Debug for Sleep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sleep")
+            .field("elapsed", &self.is_elapsed())
+            .finish()
+    }
+}
+
+/// A future that ran out of time, see `timeout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed(());
+
+impl std::fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// `future` or `Elapsed` if the deadline passes first, see `timeout`.
+/// Should the scheduler shut down first it waits for `future` alone.
+#[must_use = "futures do nothing unless awaited"]
+pub struct WithTimeout<F> {
+    future: Pin<Box<F>>,
+    deadline: Option<TimeoutHandle>,
+}
+
+impl<F: Future> WithTimeout<F> {
+    pub(crate) fn new(
+        scheduler: &Scheduler,
+        duration: Duration,
+        future: F,
+    ) -> Result<WithTimeout<F>, SchedError> {
+        Ok(WithTimeout {
+            future: Box::pin(future),
+            deadline: Some(scheduler.schedule_delayed(duration, || {})?),
+        })
+    }
+}
+
+impl<F: Future> Future for WithTimeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        let Some(deadline) = self.deadline.as_mut() else {
+            return Poll::Pending;
+        };
+        match Pin::new(deadline).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(())) => {
+                self.deadline = None;
+                Poll::Ready(Err(Elapsed(())))
+            }
+            Poll::Ready(Err(_)) => {
+                self.deadline = None;
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<F> Drop for WithTimeout<F> {
+    fn drop(&mut self) {
+        if let Some(deadline) = self.deadline.take() {
+            let _ = deadline.cancel();
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for WithTimeout<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WithTimeout")
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+/// What an `Interval` does about ticks that came due while nobody was
+/// taking them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissedTicks {
+    /// Catch up, yielding each of them right away.
+    #[default]
+    Burst,
+    /// Yield one now and time the following ticks from it.
+    Delay,
+    /// Yield the latest one and leave out those before it.
+    Skip,
+}
+
+/// One tick of an `Interval`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TickInfo {
+    /// Counting from zero, skipped ticks included.
+    pub tick: u64,
+    /// When it was due, on the scheduler's clock.
+    pub deadline: Duration,
+    /// Ticks left out right before this one, see `MissedTicks::Skip`.
+    pub missed: u64,
+}
+
+/// Ticks every period, the first one period after it was made, see
+/// `interval`. Ticks are anchored to the start, taking one late doesn't
+/// push back the rest unless `MissedTicks::Delay` says so. Ends once the
+/// scheduler shuts down, a tick the scheduler can't take for now, like
+/// with `SchedError::TooManyTimers`, is an error and tried again on the
+/// next poll. With the `stream` feature it is a `futures_core::Stream`.
+pub struct Interval<'a> {
+    scheduler: &'a Scheduler,
+    period: Duration,
+    missed_ticks: MissedTicks,
+    tick: u64,
+    deadline: Duration,
+    timeout: Option<TimeoutHandle>,
+    ended: bool,
+}
+
+impl<'a> Interval<'a> {
+    /* Panics on a zero period, like Scheduler::schedule_periodic */
+    pub(crate) fn new(scheduler: &'a Scheduler, period: Duration) -> Interval<'a> {
+        assert!(period > Duration::ZERO, "interval period must be nonzero");
+        Interval {
+            scheduler,
+            period,
+            missed_ticks: MissedTicks::default(),
+            tick: 0,
+            deadline: scheduler.now().saturating_add(period),
+            timeout: None,
+            ended: false,
+        }
+    }
+
+    pub fn missed_ticks(mut self, missed_ticks: MissedTicks) -> Interval<'a> {
+        self.missed_ticks = missed_ticks;
+        self
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Wait for the next tick, None once the scheduler has shut down, an
+    /// error while it can't take the tick's timeout.
+    pub async fn tick(&mut self) -> Option<Result<TickInfo, SchedError>> {
+        poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    pub fn poll_tick(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<TickInfo, SchedError>>> {
+        if self.ended {
+            return Poll::Ready(None);
+        }
+
+        if self.timeout.is_none() {
+            let delay = self.deadline.saturating_sub(self.scheduler.now());
+            match self.scheduler.schedule_delayed(delay, || {}) {
+                Ok(timeout) => self.timeout = Some(timeout),
+                Err(SchedError::ShutDown) => return self.end(),
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+        let Some(timeout) = self.timeout.as_mut() else {
+            return self.end();
+        };
+        match Pin::new(timeout).poll(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(_)) => return self.end(),
+            Poll::Ready(Ok(())) => self.timeout = None,
+        }
+
+        let now = self.scheduler.now();
+        let mut info = TickInfo {
+            tick: self.tick,
+            deadline: self.deadline,
+            missed: 0,
+        };
+        /* Ticks come due since this one, beside the one in progress */
+        let behind = (now.saturating_sub(self.deadline).as_nanos() / self.period.as_nanos()) as u64;
+
+        match self.missed_ticks {
+            MissedTicks::Burst => self.deadline = self.after(self.deadline, 1),
+            MissedTicks::Delay => self.deadline = now.saturating_add(self.period),
+            MissedTicks::Skip => {
+                info.tick += behind;
+                info.deadline = self.after(self.deadline, behind);
+                info.missed = behind;
+                self.deadline = self.after(info.deadline, 1);
+            }
+        }
+        self.tick = info.tick + 1;
+
+        Poll::Ready(Some(Ok(info)))
+    }
+
+    fn after(&self, deadline: Duration, ticks: u64) -> Duration {
+        let ticks = ticks.min(u32::MAX as u64) as u32;
+        deadline.saturating_add(self.period.saturating_mul(ticks))
+    }
+
+    fn end(&mut self) -> Poll<Option<Result<TickInfo, SchedError>>> {
+        self.ended = true;
+        self.timeout = None;
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Interval<'_> {
+    type Item = Result<TickInfo, SchedError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_tick(cx)
+    }
+}
+
+impl Drop for Interval<'_> {
+    fn drop(&mut self) {
+        if let Some(timeout) = self.timeout.take() {
+            let _ = timeout.cancel();
+        }
+    }
+}
+
+impl std::fmt::Debug for Interval<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Interval")
+            .field("period", &self.period)
+            .field("missed_ticks", &self.missed_ticks)
+            .field("tick", &self.tick)
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
@@ -0,0 +1,505 @@
+use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::backend::Intake;
+use crate::clock::{Clock, Step};
+use crate::deadline::MissHook;
+use crate::dedup::Keys;
+use crate::delta::{delta_check, delta_elapse, delta_insert, delta_try_reserve};
+use crate::error::SchedError;
+use crate::executor::{Invocation, RecordingExecutor};
+use crate::faults::Injector;
+use crate::fired::Handoff;
+use crate::health::Exit;
+use crate::latency::{FireSample, LatencyRing};
+use crate::observer::{CancelEvent, FireEvent, Observers};
+use crate::pending::{pending_info, PendingInfo};
+use crate::platform::TimerPeriod;
+use crate::power::{PowerMode, PowerState};
+use crate::stats::Counters;
+use crate::sync::atomic::Ordering;
+use crate::sync::mpsc::Sender;
+use crate::telemetry;
+use crate::timeout::{timeouts_rederive_wall, timeouts_remove, Timeout, TimeoutList};
+use crate::tsc::Stamp;
+use crate::worker::{Command, ErrorHook, Task};
+use crate::TaskId;
+
+/* Never wake more than this ahead of a deadline, whatever we measured */
+const MAX_MARGIN: Duration = Duration::from_millis(1);
+
+/* Moving estimate of how late recv_timeout hands control back to us.
+ * Sleeps are shortened by that amount and the remainder is waited out on
+ * the clock, so the deadline is checked right before dispatching.
+ */
+pub(crate) struct Compensation {
+    margin: Duration,
+    counters: Arc<Counters>,
+}
+
+impl Compensation {
+    pub(crate) fn new(seed: Duration, counters: Arc<Counters>) -> Compensation {
+        let margin = seed.min(MAX_MARGIN);
+        counters.set_margin(margin);
+
+        Compensation { margin, counters }
+    }
+
+    fn update(&mut self, overshoot: Duration) {
+        self.margin = (self.margin * 7 + overshoot) / 8;
+        self.margin = self.margin.min(MAX_MARGIN);
+        self.counters.set_margin(self.margin);
+    }
+}
+
+/* Wall-clock jumps larger than this, relative to monotonic time, are
+ * treated as steps rather than slewing
+ */
+const STEP_TOLERANCE: Duration = Duration::from_millis(10);
+
+/* How often to look for wall-clock steps while wall deadlines are pending */
+const WALL_RECHECK: Duration = Duration::from_secs(1);
+
+/* Spots wall-clock steps (NTP, manual changes, leap seconds) by comparing
+ * how far the wall clock moved against the monotonic clock.
+ */
+struct WallWatch {
+    mono: Instant,
+    wall: SystemTime,
+}
+
+impl WallWatch {
+    fn new() -> WallWatch {
+        WallWatch {
+            mono: Instant::now(),
+            wall: SystemTime::now(),
+        }
+    }
+
+    fn stepped(&mut self) -> bool {
+        let (mono, wall) = (Instant::now(), SystemTime::now());
+        let mono_elapsed = mono - self.mono;
+        let step = match wall.duration_since(self.wall) {
+            Ok(wall_elapsed) if wall_elapsed > mono_elapsed => wall_elapsed - mono_elapsed,
+            Ok(wall_elapsed) => mono_elapsed - wall_elapsed,
+            Err(e) => e.duration() + mono_elapsed,
+        };
+
+        self.mono = mono;
+        self.wall = wall;
+
+        step > STEP_TOLERANCE
+    }
+}
+
+/* Spots steps of clocks that can be stepped, by how far their reading
+ * moved against their real time. A step is no time passing, what is
+ * pending on relative delays moves along with it.
+ */
+struct StepWatch {
+    last: Option<(Duration, Duration)>,
+    tolerance: Duration,
+}
+
+impl StepWatch {
+    fn new(clock: &dyn Clock) -> StepWatch {
+        StepWatch {
+            last: clock.now_with_real_time(),
+            /* A manual clock only ever moves when told to */
+            tolerance: if clock.is_manual() {
+                Duration::ZERO
+            } else {
+                STEP_TOLERANCE
+            },
+        }
+    }
+
+    fn step(&mut self, clock: &dyn Clock) -> Option<Step> {
+        let (now, real) = clock.now_with_real_time()?;
+        let (then, then_real) = self.last.replace((now, real))?;
+        let passed = real.saturating_sub(then_real);
+        let step = match now.checked_sub(then) {
+            Some(moved) if moved > passed => Step::Forward(moved - passed),
+            Some(moved) => Step::Back(passed - moved),
+            None => Step::Back(then - now + passed),
+        };
+
+        match step {
+            Step::Forward(by) | Step::Back(by) if by > self.tolerance => Some(step),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) enum Message {
+    /* Boxed, Timeout is much the largest and every message has its size */
+    Add(Box<Timeout>),
+    Cancel(TaskId),
+    /* A manual clock moved, re-check the front deadline */
+    Tick,
+    /* Report what is pending */
+    Inspect(Sender<Vec<PendingInfo>>),
+    /* Drop whatever is pending and exit */
+    Shutdown,
+}
+
+pub(crate) fn timekeeper_thread(
+    mut notify_receiver: Intake,
+    clock: Arc<dyn Clock>,
+    counters: Arc<Counters>,
+    mut compensation: Option<Compensation>,
+    dispatch: Dispatch,
+    fallible_alloc: bool,
+    mut power: PowerState,
+) {
+    let mut list = TimeoutList::new();
+    let mut wall_watch = WallWatch::new();
+    let mut step_watch = StepWatch::new(clock.as_ref());
+    let mut timer_period = TimerPeriod::new();
+
+    /* Every submitter is gone, nothing new can arrive. Keep going until
+     * what is pending has fired.
+     */
+    let mut closed = false;
+
+    /* The time the first delay in the list is relative to */
+    let mut base = clock.now();
+
+    let heartbeat = &counters.heartbeats.timekeeper;
+    let _exit = Exit(heartbeat);
+
+    loop {
+        heartbeat.busy();
+        /* Keep room for the next timeout to arrive, so only ever failing
+         * to get it makes us reject one
+         */
+        telemetry::gauges(&counters);
+        let mode = power.update();
+        let precise = mode == PowerMode::Precise;
+
+        if fallible_alloc {
+            let full = delta_try_reserve(&mut list, 1).is_err();
+            counters.store_full.store(full, Ordering::Release);
+        }
+
+        let wall_pending = list.iter().any(|t| t.wall_deadline.is_some());
+        if wall_watch.stepped() && wall_pending {
+            let now = clock.now();
+            delta_elapse(&mut list, now.saturating_sub(base));
+            base = now;
+            timeouts_rederive_wall(&mut list, base, SystemTime::now());
+            delta_check(&list, base);
+        }
+
+        let mut timeout = match list.pop_front() {
+            Some(t) => t,
+            None if closed => return,
+            None => {
+                timer_period.update(None);
+                heartbeat.idle();
+                let message = notify_receiver.recv();
+                heartbeat.busy();
+                /* Nothing was pending to move along with a step */
+                let _ = step_watch.step(clock.as_ref());
+                match message {
+                    Ok(Message::Add(t)) => {
+                        base = clock.now();
+                        (*t).rebase(base)
+                    }
+                    Ok(Message::Cancel(_) | Message::Tick) => continue,
+                    Ok(Message::Inspect(reply)) => {
+                        let _ = reply.send(Vec::new());
+                        continue;
+                    }
+                    Ok(Message::Shutdown) | Err(_) => return,
+                }
+            }
+        };
+
+        let recheck = match (clock.recheck_interval(), wall_pending) {
+            (Some(recheck), true) => Some(recheck.min(WALL_RECHECK)),
+            (None, true) => Some(WALL_RECHECK),
+            (recheck, false) => recheck,
+        };
+
+        /* Raising the period is what Efficient saves the power of */
+        timer_period.update(precise.then_some(timeout.delay));
+
+        /* Whatever ran since `base` was read comes off the wait, a manual
+         * clock may even have been moved past the deadline already.
+         */
+        let mut deadline = base.saturating_add(timeout.delay);
+        let mut wake = dispatch.faults.wake_at(deadline);
+        let mut sleep_time = clock.now();
+        let compensation = compensation.as_mut().filter(|_| precise);
+        let margin = compensation.as_ref().map_or(Duration::ZERO, |c| c.margin);
+        let wait = wake.saturating_sub(sleep_time).saturating_sub(margin);
+
+        let stamp = Stamp::start(clock.as_ref(), sleep_time);
+        heartbeat.idle();
+        let woken = if wake <= sleep_time {
+            Err(RecvTimeoutError::Timeout)
+        } else if closed {
+            /* Nobody to listen to, just wait out the front deadline */
+            let skipped = clock.is_manual() && counters.is_idle() && clock.fast_forward(deadline);
+            if !skipped {
+                clock.sleep_until(sleep_time.saturating_add(wait), mode);
+            }
+            Err(RecvTimeoutError::Timeout)
+        } else if clock.is_manual() {
+            /* Real time means nothing to a manual clock. Nothing is running
+             * and nothing new arrived, so a fast-forwarding clock can jump,
+             * otherwise wait for it to be moved.
+             */
+            match notify_receiver.try_recv() {
+                Ok(message) => Ok(message),
+                Err(TryRecvError::Empty) if counters.is_idle() && clock.fast_forward(deadline) => {
+                    Err(RecvTimeoutError::Timeout)
+                }
+                Err(_) => notify_receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            }
+        } else {
+            match recheck {
+                /* Woke up only to re-read the clock, go around again */
+                Some(recheck) if recheck < wait => match notify_receiver.recv_timeout(recheck) {
+                    Err(RecvTimeoutError::Timeout) => Ok(Message::Tick),
+                    woken => woken,
+                },
+                _ => notify_receiver.recv_timeout(wait),
+            }
+        };
+
+        heartbeat.busy();
+
+        /* The clock was stepped while we waited. Wall deadlines follow it
+         * and are put right by the wall watch, the rest of the list is on
+         * relative delays and keeps its distance from now.
+         */
+        if let Some(step) = step_watch.step(clock.as_ref()) {
+            base = step.apply(base);
+            deadline = step.apply(deadline);
+            wake = step.apply(wake);
+            sleep_time = step.apply(sleep_time);
+            timeout.shift(step);
+            list.iter_mut().for_each(|t| t.shift(step));
+        }
+
+        match woken {
+            /* We didn't get to wait, let's reduce the time of this work
+             * by the amount of time waited so far.
+             */
+            Ok(message) => {
+                let now = clock.now();
+                list.push_front(timeout);
+                delta_elapse(&mut list, now.saturating_sub(base));
+                base = now;
+                match message {
+                    Message::Add(new_timeout) => {
+                        if fallible_alloc && delta_try_reserve(&mut list, 1).is_err() {
+                            dispatch.reject(*new_timeout);
+                        } else {
+                            delta_insert(&mut list, (*new_timeout).rebase(base));
+                            dispatch.faults.interrupted(deadline);
+                        }
+                    }
+                    Message::Cancel(id) => {
+                        if let Some(removed) = timeouts_remove(&mut list, id) {
+                            if dispatch.keys.release(id) {
+                                counters.pending.fetch_sub(1, Ordering::AcqRel);
+                            }
+                            counters.cancelled.fetch_add(1, Ordering::Relaxed);
+                            telemetry::cancelled();
+                            dispatch.observers.cancel(CancelEvent {
+                                task: id,
+                                name: removed.name,
+                                at: now,
+                            });
+                        }
+                    }
+                    Message::Tick => {}
+                    Message::Inspect(reply) => {
+                        let _ = reply.send(pending_info(&list));
+                    }
+                    Message::Shutdown => return,
+                }
+                delta_check(&list, base);
+            }
+
+            /* The intake closed before the deadline, don't fire early */
+            Err(RecvTimeoutError::Disconnected) => {
+                let now = clock.now();
+                list.push_front(timeout);
+                delta_elapse(&mut list, now.saturating_sub(base));
+                base = now;
+                closed = true;
+            }
+
+            /* Timed out, let's process the work and continue */
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(compensation) = compensation {
+                    let slept = clock.now().saturating_sub(sleep_time);
+                    compensation.update(slept.saturating_sub(wait));
+
+                    /* We woke early on purpose, wait out what is left */
+                    clock.sleep_until(wake, PowerMode::Precise);
+                }
+
+                let rearm = dispatch.fire(timeout, stamp.now(clock.as_ref()));
+
+                /* The rest of the list is relative to this deadline, take
+                 * the dispatch latency (or how far a manual clock jumped
+                 * past it) off the following sleeps.
+                 */
+                let now = clock.now();
+                delta_elapse(&mut list, now.saturating_sub(deadline));
+                base = now.max(deadline);
+
+                /* Periodic timeouts go back in for their next tick right
+                 * away, one already due takes its turn among the others
+                 */
+                if let Some(next) = rearm {
+                    delta_insert(&mut list, next.rebase(base));
+                }
+
+                /* Timeouts quantized into the same slot share this wakeup */
+                while list.front().is_some_and(|t| t.delay == Duration::ZERO) {
+                    let next = list.pop_front().unwrap();
+                    if let Some(next) = dispatch.fire(next, stamp.now(clock.as_ref())) {
+                        delta_insert(&mut list, next.rebase(base));
+                    }
+                }
+                delta_check(&list, base);
+            }
+        }
+    }
+}
+
+/* Hands expired timeouts over to the worker */
+pub(crate) struct Dispatch {
+    pub(crate) work_sender: Sender<Command>,
+    pub(crate) counters: Arc<Counters>,
+    pub(crate) latency: Arc<LatencyRing>,
+    pub(crate) keys: Arc<Keys>,
+    pub(crate) deadline_miss: Option<MissHook>,
+    pub(crate) lateness_budget: Option<Duration>,
+    pub(crate) on_error: Option<ErrorHook>,
+    pub(crate) observers: Observers,
+    pub(crate) faults: Injector,
+    pub(crate) executor: Option<RecordingExecutor>,
+    pub(crate) handoff: Arc<Handoff>,
+}
+
+impl Dispatch {
+    /* No room to store it, drop it as if cancelled */
+    fn reject(&self, timeout: Timeout) {
+        if self.keys.release(timeout.id) {
+            self.counters.pending.fetch_sub(1, Ordering::AcqRel);
+        }
+
+        match self.on_error.as_ref() {
+            Some(on_error) => on_error(SchedError::OutOfMemory),
+            None => error!("out of memory, dropped {}", timeout.label()),
+        }
+    }
+
+    fn fire(&self, timeout: Timeout, now: Duration) -> Option<Timeout> {
+        let (id, label, expected) = (timeout.id, timeout.label(), timeout.deadline);
+        let (tags, node) = (timeout.tags.clone(), timeout.node);
+        if timeout.is_periodic() {
+            self.counters.add_drift(now.saturating_sub(expected));
+        }
+
+        timeout.span.fired(now.saturating_sub(expected));
+        let (work, next) = timeout.expire();
+        let work = self.faults.sabotage(work);
+        let invocation = Invocation {
+            task: Some(id),
+            name: label.name,
+            scheduled_for: expected,
+            fired_at: now,
+        };
+        let work = match self.executor.as_ref() {
+            Some(executor) => executor.intercept(invocation, work),
+            None => self.handoff.intercept(&invocation, work),
+        };
+        if next.is_none() && self.keys.release(id) {
+            self.counters.pending.fetch_sub(1, Ordering::AcqRel);
+        }
+
+        let stall = self.faults.dispatch_delay();
+        if stall > Duration::ZERO {
+            std::thread::sleep(stall);
+        }
+
+        self.counters.queue();
+        if self
+            .work_sender
+            .send(Command::Run(Task {
+                work,
+                label,
+                tags,
+                node,
+            }))
+            .is_err()
+        {
+            /* No worker could be restarted, drop the work and stop re-arming */
+            self.counters.in_flight.fetch_sub(1, Ordering::AcqRel);
+            if next.is_some() && self.keys.release(id) {
+                self.counters.pending.fetch_sub(1, Ordering::AcqRel);
+            }
+            return None;
+        }
+
+        self.counters.add_fire(now.saturating_sub(expected));
+        telemetry::fired(now.saturating_sub(expected));
+        self.latency.record(FireSample {
+            expected,
+            actual: now,
+        });
+        self.observers.fire(FireEvent {
+            task: id,
+            name: label.name,
+            expected,
+            actual: now,
+        });
+
+        if now < expected {
+            warn!("{} fired {:?} early", label, expected - now);
+        }
+        #[cfg(feature = "debug-timing")]
+        if now >= expected {
+            trace!("{} fired {:?} late", label, now - expected);
+        }
+
+        if self
+            .lateness_budget
+            .is_some_and(|budget| now.saturating_sub(expected) > budget)
+        {
+            self.counters
+                .missed_deadlines
+                .fetch_add(1, Ordering::Relaxed);
+            if let Some(name) = label.name {
+                self.counters.task_timings.record_miss(name);
+            }
+        }
+
+        if let Some(deadline_miss) = self.deadline_miss.as_ref() {
+            deadline_miss.check(id, label.name, expected, now);
+        }
+
+        next
+    }
+}
+
+/* However the timekeeper exits, the worker runs what it was handed and
+ * stops. Other senders may still be around, so it can't rely on the
+ * channel disconnecting.
+ */
+impl Drop for Dispatch {
+    fn drop(&mut self) {
+        let _ = self.work_sender.send(Command::Stop);
+    }
+}
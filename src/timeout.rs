@@ -0,0 +1,323 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::clock::Step;
+use crate::delta::{delta_insert, delta_remove, Delayed, DeltaList};
+use crate::span::TaskSpan;
+use crate::sync::Mutex;
+use crate::{TaskId, Work};
+
+/* Pending timeouts in deadline order, each delay relative to the one
+ * before it.
+ */
+pub(crate) type TimeoutList = DeltaList<Timeout>;
+
+/* One-shot work is handed over as is. Periodic work stays with its timeout
+ * and every tick sends out a closure calling into it.
+ */
+pub(crate) enum Job {
+    Once(Work),
+    Periodic(Periodic),
+}
+
+/* Ticks are anchored to the first schedule, tick n is due at
+ * anchor + n * interval however late the previous ones fired.
+ */
+pub(crate) struct Periodic {
+    work: Arc<Mutex<Work>>,
+    interval: Duration,
+    anchor: Duration,
+    ticks: u64,
+}
+
+impl Periodic {
+    fn deadline(&self) -> Duration {
+        let offset = self.interval.as_nanos().saturating_mul(self.ticks as u128);
+        self.anchor.saturating_add(nanos_to_duration(offset))
+    }
+}
+
+pub(crate) struct Timeout {
+    pub(crate) id: TaskId,
+    pub(crate) job: Job,
+    pub(crate) delay: Duration,
+    /* The delay as scheduled, only kept to be reported */
+    #[cfg(feature = "debug-timing")]
+    pub(crate) dbg_init_ticks: Duration,
+    /* Absolute, on the scheduler's clock */
+    pub(crate) deadline: Duration,
+    /* Set for deadlines given in wall-clock time, which have to follow the
+     * wall clock when it is stepped.
+     */
+    pub(crate) wall_deadline: Option<SystemTime>,
+    pub(crate) name: Option<&'static str>,
+    /* As `key=value,...`, see Named::tags */
+    pub(crate) tags: Option<Arc<str>>,
+    /* See Named::numa_node */
+    pub(crate) node: Option<usize>,
+    pub(crate) span: TaskSpan,
+}
+
+/* How logs refer to a task */
+#[derive(Clone, Copy)]
+pub(crate) struct Label {
+    /* Immediate work has none */
+    pub(crate) id: Option<TaskId>,
+    pub(crate) name: Option<&'static str>,
+}
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.id, self.name) {
+            (Some(id), Some(name)) => write!(f, "timeout {} {}", id, name),
+            (Some(id), None) => write!(f, "timeout {}", id),
+            (None, Some(name)) => write!(f, "task {}", name),
+            (None, None) => write!(f, "task"),
+        }
+    }
+}
+
+impl Timeout {
+    pub(crate) fn new(
+        id: TaskId,
+        work: Work,
+        delay: Duration,
+        resolution: Duration,
+        current_time: Duration,
+    ) -> Timeout {
+        /* Round the deadline up to the next multiple of the resolution so
+         * timeouts landing in the same slot expire on the same wakeup.
+         */
+        let deadline = quantize(current_time.saturating_add(delay), resolution);
+        let delay = deadline - current_time;
+
+        Timeout {
+            id,
+            job: Job::Once(work),
+            delay,
+            #[cfg(feature = "debug-timing")]
+            dbg_init_ticks: delay,
+            deadline,
+            wall_deadline: None,
+            name: None,
+            tags: None,
+            node: None,
+            span: TaskSpan::new(id, delay),
+        }
+    }
+
+    pub(crate) fn at(
+        id: TaskId,
+        work: Work,
+        when: SystemTime,
+        resolution: Duration,
+        current_time: Duration,
+    ) -> Timeout {
+        let delay = wall_delay(when, SystemTime::now());
+
+        Timeout {
+            wall_deadline: Some(when),
+            ..Timeout::new(id, work, delay, resolution, current_time)
+        }
+    }
+
+    pub(crate) fn periodic(
+        id: TaskId,
+        work: Work,
+        interval: Duration,
+        current_time: Duration,
+    ) -> Timeout {
+        let periodic = Periodic {
+            work: Arc::new(Mutex::new(work)),
+            interval,
+            anchor: current_time,
+            ticks: 1,
+        };
+        let deadline = periodic.deadline();
+
+        Timeout {
+            id,
+            job: Job::Periodic(periodic),
+            delay: interval,
+            #[cfg(feature = "debug-timing")]
+            dbg_init_ticks: interval,
+            deadline,
+            wall_deadline: None,
+            name: None,
+            tags: None,
+            node: None,
+            span: TaskSpan::new(id, interval),
+        }
+    }
+
+    pub(crate) fn named(mut self, name: Option<&'static str>) -> Timeout {
+        if let Some(name) = name {
+            self.span.name(name);
+        }
+        self.name = name;
+        self
+    }
+
+    pub(crate) fn tagged(mut self, tags: Option<Arc<str>>) -> Timeout {
+        if let Some(tags) = tags.as_deref() {
+            self.span.tags(tags);
+        }
+        self.tags = tags;
+        self
+    }
+
+    pub(crate) fn on_node(mut self, node: Option<usize>) -> Timeout {
+        self.node = node;
+        self
+    }
+
+    pub(crate) fn label(&self) -> Label {
+        Label {
+            id: Some(self.id),
+            name: self.name,
+        }
+    }
+
+    /* Make the delay relative to `now` again. Time passes between taking
+     * a timeout's deadline and it reaching the timekeeper, a manual clock
+     * may even be advanced past it.
+     */
+    pub(crate) fn rebase(mut self, now: Duration) -> Timeout {
+        self.delay = self.deadline.saturating_sub(now);
+        self
+    }
+
+    /* The clock was stepped, keep the deadline as far from now as it was */
+    pub(crate) fn shift(&mut self, step: Step) {
+        self.deadline = step.apply(self.deadline);
+        if let Job::Periodic(periodic) = &mut self.job {
+            periodic.anchor = step.apply(periodic.anchor);
+        }
+    }
+
+    /* What it was scheduled with, as far as that is kept */
+    pub(crate) fn initial_delay(&self) -> Option<Duration> {
+        match &self.job {
+            Job::Periodic(periodic) => Some(periodic.interval),
+            #[cfg(feature = "debug-timing")]
+            Job::Once(_) => Some(self.dbg_init_ticks),
+            #[cfg(not(feature = "debug-timing"))]
+            Job::Once(_) => None,
+        }
+    }
+
+    /* The clock reading it was made from. Periodic timeouts are anchored
+     * there, one-shot ones have not been rebased yet.
+     */
+    pub(crate) fn created(&self) -> Duration {
+        match &self.job {
+            Job::Periodic(periodic) => periodic.anchor,
+            Job::Once(_) => self.deadline - self.delay,
+        }
+    }
+
+    pub(crate) fn is_periodic(&self) -> bool {
+        matches!(self.job, Job::Periodic(_))
+    }
+
+    /* Work to hand to the worker for this expiry, periodic timeouts come
+     * back armed for their next tick.
+     */
+    pub(crate) fn expire(self) -> (Work, Option<Timeout>) {
+        match self.job {
+            Job::Once(work) => (self.span.instrument(work), None),
+            Job::Periodic(mut periodic) => {
+                let shared = periodic.work.clone();
+                let work: Work = self.span.instrument(Box::new(move || {
+                    let mut work = shared.lock().unwrap_or_else(|e| e.into_inner());
+                    work()
+                }));
+
+                periodic.ticks += 1;
+                let next = Timeout {
+                    deadline: periodic.deadline(),
+                    job: Job::Periodic(periodic),
+                    ..self
+                };
+
+                (work, Some(next))
+            }
+        }
+    }
+}
+
+impl Delayed for Timeout {
+    fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    fn delay_mut(&mut self) -> &mut Duration {
+        &mut self.delay
+    }
+
+    fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /* Ids are handed out in submission order */
+    fn seq(&self) -> u64 {
+        self.id.as_u64()
+    }
+}
+
+fn wall_delay(when: SystemTime, wall_now: SystemTime) -> Duration {
+    when.duration_since(wall_now).unwrap_or(Duration::ZERO)
+}
+
+fn quantize(deadline: Duration, resolution: Duration) -> Duration {
+    if resolution == Duration::ZERO {
+        return deadline;
+    }
+
+    let resolution = resolution.as_nanos();
+    let slots = deadline.as_nanos().div_ceil(resolution);
+    nanos_to_duration(slots.saturating_mul(resolution))
+}
+
+/* Deadlines past what a Duration holds are as good as never */
+fn nanos_to_duration(nanos: u128) -> Duration {
+    match u64::try_from(nanos / 1_000_000_000) {
+        Ok(secs) => Duration::new(secs, (nanos % 1_000_000_000) as u32),
+        Err(_) => Duration::MAX,
+    }
+}
+
+/* Remove by id, the following timeout takes over its delay */
+pub(crate) fn timeouts_remove(list: &mut TimeoutList, id: TaskId) -> Option<Timeout> {
+    delta_remove(list, |t| t.id == id)
+}
+
+/* The wall clock was stepped, recompute where wall-clock deadlines sit
+ * relative to now and re-sort. `current_time` is where the front of the list
+ * is relative to.
+ */
+pub(crate) fn timeouts_rederive_wall(
+    list: &mut TimeoutList,
+    current_time: Duration,
+    wall_now: SystemTime,
+) {
+    let mut offset = Duration::ZERO;
+    let mut timeouts = Vec::with_capacity(list.len());
+
+    while let Some(mut t) = list.pop_front() {
+        offset += t.delay;
+
+        t.delay = match t.wall_deadline {
+            Some(when) => wall_delay(when, wall_now),
+            None => offset,
+        };
+        t.deadline = current_time.saturating_add(t.delay);
+
+        timeouts.push(t);
+    }
+
+    for t in timeouts {
+        delta_insert(list, t);
+    }
+}
@@ -0,0 +1,84 @@
+/* Execution time per task name, recorded by the worker, and missed
+ * deadlines, counted by the timekeeper. Statistics, so a std mutex, see
+ * sync.rs.
+ */
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::histogram::AtomicHistogram;
+
+/// How long the tasks scheduled under one name took and how often they
+/// fired late, see `Scheduler::task_timings`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskTimings {
+    pub name: &'static str,
+    /// Runs finished, panicked ones included.
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+    /// Duration 95% of runs stayed within, precise to 1/64 of it.
+    pub p95: Duration,
+    /// Fires later than `Builder::lateness_budget`.
+    pub missed: u64,
+}
+
+#[derive(Default)]
+struct Entry {
+    count: u64,
+    total: Duration,
+    max: Duration,
+    histogram: AtomicHistogram,
+    missed: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Timings {
+    by_name: Mutex<HashMap<&'static str, Entry>>,
+}
+
+impl Timings {
+    pub(crate) fn record(&self, name: &'static str, duration: Duration) {
+        let mut by_name = self.by_name.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = by_name.entry(name).or_default();
+        entry.count += 1;
+        entry.total = entry.total.saturating_add(duration);
+        entry.max = entry.max.max(duration);
+        entry.histogram.record(duration);
+    }
+
+    pub(crate) fn record_miss(&self, name: &'static str) {
+        let mut by_name = self.by_name.lock().unwrap_or_else(PoisonError::into_inner);
+        by_name.entry(name).or_default().missed += 1;
+    }
+
+    pub(crate) fn reset(&self) {
+        self.by_name
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /* Biggest total first, the likeliest hog to look at */
+    pub(crate) fn snapshot(&self) -> Vec<TaskTimings> {
+        let by_name = self.by_name.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut timings: Vec<TaskTimings> = by_name
+            .iter()
+            .map(|(&name, entry)| TaskTimings {
+                name,
+                count: entry.count,
+                total: entry.total,
+                max: entry.max,
+                p95: entry
+                    .histogram
+                    .snapshot()
+                    .percentile(95.0)
+                    .unwrap_or_default()
+                    .min(entry.max),
+                missed: entry.missed,
+            })
+            .collect();
+        timings.sort_by(|a, b| b.total.cmp(&a.total).then(a.name.cmp(b.name)));
+        timings
+    }
+}
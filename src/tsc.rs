@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use crate::clock::Clock;
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+mod imp {
+    use std::sync::OnceLock;
+    use std::time::{Duration, Instant};
+
+    const CALIBRATION: Duration = Duration::from_millis(10);
+
+    static NANOS_PER_TICK: OnceLock<f64> = OnceLock::new();
+
+    pub(crate) fn read() -> u64 {
+        unsafe { core::arch::x86_64::_rdtsc() }
+    }
+
+    /* Measured once against Instant, assumes an invariant TSC */
+    fn nanos_per_tick() -> f64 {
+        *NANOS_PER_TICK.get_or_init(|| {
+            let (start, ticks) = (Instant::now(), read());
+            while start.elapsed() < CALIBRATION {
+                std::hint::spin_loop();
+            }
+            let (elapsed, ticks) = (start.elapsed(), read() - ticks);
+
+            elapsed.as_nanos() as f64 / ticks as f64
+        })
+    }
+
+    pub(crate) fn calibrate() {
+        nanos_per_tick();
+    }
+
+    pub(crate) fn elapsed(since: u64) -> Duration {
+        let ticks = read().saturating_sub(since);
+        Duration::from_nanos((ticks as f64 * nanos_per_tick()) as u64)
+    }
+}
+
+/* Timestamps fire events relative to the start of a sleep. With the `tsc`
+ * feature the time since then is read off the TSC, which costs a few
+ * nanoseconds instead of a clock syscall per fired timeout.
+ */
+pub(crate) struct Stamp {
+    #[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+    tsc: Option<(Duration, u64)>,
+}
+
+impl Stamp {
+    pub(crate) fn start(clock: &dyn Clock, base: Duration) -> Stamp {
+        #[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+        {
+            /* A manual clock's time has nothing to do with the TSC */
+            let tsc = if clock.is_manual() {
+                None
+            } else {
+                Some((base, imp::read()))
+            };
+
+            Stamp { tsc }
+        }
+
+        #[cfg(not(all(feature = "tsc", target_arch = "x86_64")))]
+        {
+            let _ = (clock, base);
+            Stamp {}
+        }
+    }
+
+    pub(crate) fn now(&self, clock: &dyn Clock) -> Duration {
+        #[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+        if let Some((base, tsc)) = self.tsc {
+            return base + imp::elapsed(tsc);
+        }
+
+        clock.now()
+    }
+}
+
+/* Take the calibration hit when building the scheduler, not on first fire */
+pub(crate) fn calibrate() {
+    #[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+    imp::calibrate();
+}
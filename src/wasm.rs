@@ -0,0 +1,176 @@
+/* Timers for wasm32-unknown-unknown, which has neither threads nor an
+ * Instant to build a timekeeper on. A SimScheduler keeps the timeouts, on
+ * a clock read off performance.now, and one setTimeout is armed for the
+ * earliest deadline. When it fires, everything due runs and the next one
+ * is armed.
+ */
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use wasm_bindgen::prelude::*;
+
+use crate::sim::SimScheduler;
+use crate::TaskId;
+
+/* What setTimeout takes, about 24.8 days. Longer waits fire early, run
+ * nothing and arm again.
+ */
+const MAX_TIMEOUT_MS: u128 = i32::MAX as u128;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(callback: &Closure<dyn FnMut()>, millis: i32) -> JsValue;
+
+    #[wasm_bindgen(js_name = clearTimeout)]
+    fn clear_timeout(handle: &JsValue);
+
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// A scheduler on the JS event loop, for browser apps and other
+/// wasm32-unknown-unknown hosts without threads.
+///
+/// Work runs from `setTimeout` callbacks on the thread that created the
+/// scheduler, so it doesn't have to be `Send`, and nothing needs to be
+/// polled. Clones share the scheduler, for tasks to schedule more work.
+/// Timeouts still pending when the last clone is dropped never run.
+#[derive(Clone)]
+pub struct WasmScheduler {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    sim: SimScheduler,
+    started: f64,
+    armed: RefCell<Option<Armed>>,
+}
+
+struct Armed {
+    at: Duration,
+    handle: JsValue,
+    _callback: Closure<dyn FnMut()>,
+}
+
+impl WasmScheduler {
+    pub fn new() -> WasmScheduler {
+        WasmScheduler {
+            inner: Rc::new(Inner {
+                sim: SimScheduler::new(),
+                started: performance_now(),
+                armed: RefCell::new(None),
+            }),
+        }
+    }
+
+    /// Run `work` from the event loop as soon as it gets to it.
+    pub fn schedule<F: FnOnce() + 'static>(&self, work: F) {
+        self.inner.sim.schedule_once(work);
+        self.inner.wake_at(self.inner.elapsed());
+    }
+
+    pub fn schedule_delayed<F: FnOnce() + 'static>(&self, delay: Duration, work: F) -> TaskId {
+        let now = self.inner.elapsed();
+        self.inner.sim.catch_up(now);
+        let id = self.inner.sim.schedule_delayed_once(delay, work);
+        self.inner.wake_at(now.saturating_add(delay));
+        id
+    }
+
+    /// Run `work` every `interval`, the first time one interval from now.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero.
+    pub fn schedule_periodic<F: FnMut() + 'static>(&self, interval: Duration, work: F) -> TaskId {
+        let now = self.inner.elapsed();
+        self.inner.sim.catch_up(now);
+        let id = self.inner.sim.schedule_periodic(interval, work);
+        self.inner.wake_at(now.saturating_add(interval));
+        id
+    }
+
+    /// Stop `id` from running again. False if it already ran or was
+    /// cancelled.
+    pub fn cancel(&self, id: TaskId) -> bool {
+        /* A timeout armed for it fires to find nothing due */
+        self.inner.sim.cancel(id)
+    }
+
+    /// Timeouts waiting for their deadline, periodic ones counting once.
+    pub fn pending(&self) -> usize {
+        self.inner.sim.pending()
+    }
+}
+
+impl Inner {
+    fn elapsed(&self) -> Duration {
+        let millis = (performance_now() - self.started).max(0.0);
+        Duration::from_secs_f64(millis / 1000.0)
+    }
+
+    /* Make sure a setTimeout fires by `at`, one already armed earlier will
+     * do
+     */
+    fn wake_at(self: &Rc<Inner>, at: Duration) {
+        let mut armed = self.armed.borrow_mut();
+        if armed.as_ref().is_some_and(|armed| armed.at <= at) {
+            return;
+        }
+        if let Some(replaced) = armed.take() {
+            clear_timeout(&replaced.handle);
+        }
+
+        let inner: Weak<Inner> = Rc::downgrade(self);
+        let callback = Closure::<dyn FnMut()>::new(move || {
+            if let Some(inner) = inner.upgrade() {
+                inner.fire();
+            }
+        });
+        /* Rounded up, early wakeups would only find nothing due */
+        let wait = at.saturating_sub(self.elapsed()).as_micros().div_ceil(1000);
+        let handle = set_timeout(&callback, wait.min(MAX_TIMEOUT_MS) as i32);
+        *armed = Some(Armed {
+            at,
+            handle,
+            _callback: callback,
+        });
+    }
+
+    fn fire(self: &Rc<Inner>) {
+        /* Dropping the closure that is running is fine, wasm-bindgen frees
+         * it once the call returns
+         */
+        drop(self.armed.borrow_mut().take());
+
+        self.sim.advance_to(self.elapsed());
+        if let Some(deadline) = self.sim.next_deadline() {
+            self.wake_at(deadline);
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(armed) = self.armed.get_mut().take() {
+            clear_timeout(&armed.handle);
+        }
+    }
+}
+
+impl Default for WasmScheduler {
+    fn default() -> WasmScheduler {
+        WasmScheduler::new()
+    }
+}
+
+impl std::fmt::Debug for WasmScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmScheduler")
+            .field("pending", &self.pending())
+            .field("next_deadline", &self.inner.sim.next_deadline())
+            .finish()
+    }
+}
@@ -0,0 +1,346 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::backend::Submitter;
+use crate::clock::Wake;
+use crate::dump::WorkerState;
+use crate::error::{Result, SchedError};
+use crate::observer::{ExecuteEvent, Observers, StartEvent};
+use crate::platform;
+use crate::spawn::Spawner;
+use crate::stats::Counters;
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sync::mpsc::Receiver;
+use crate::sync::thread::{self, JoinHandle};
+use crate::sync::Mutex;
+use crate::telemetry;
+use crate::timeout::Label;
+use crate::Work;
+
+pub(crate) type ErrorHook = Arc<dyn Fn(SchedError) + Send + Sync + 'static>;
+
+/// What the scheduler does when a task panics. The panic hook reports it
+/// either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Carry on with the next task.
+    #[default]
+    CatchAndContinue,
+    /// Shut the scheduler down, as `Scheduler::shutdown` would.
+    CatchAndShutdown,
+    /// Shut down and resume the panic from `Scheduler::join`.
+    Propagate,
+}
+
+pub(crate) struct Task {
+    pub(crate) work: Work,
+    pub(crate) label: Label,
+    pub(crate) tags: Option<Arc<str>>,
+    pub(crate) node: Option<usize>,
+}
+
+/* How the worker treats the tasks it runs, as configured on the builder */
+pub(crate) struct Hooks {
+    pub(crate) on_error: Option<ErrorHook>,
+    pub(crate) panic_policy: PanicPolicy,
+    pub(crate) slow_task: Option<Duration>,
+    pub(crate) observers: Observers,
+    pub(crate) spawner: Option<Arc<dyn Spawner>>,
+    pub(crate) nice: Option<i32>,
+}
+
+pub(crate) enum Command {
+    Run(Task),
+    /* The timekeeper exited, nothing more is coming */
+    Stop,
+}
+
+/* The worker stage. It owns the receiving end rather than its thread, so
+ * work handed over while a worker thread dies waits in the channel for the
+ * replacement. Only if none can be spawned is the receiver dropped, and
+ * the timekeeper sees it gone.
+ *
+ * It is one thread however many CPUs there are, so a CPU quota has nothing
+ * to scale down. Pools are left to a Spawner and size themselves, rayon's
+ * global one by available_parallelism, which reads cgroup quotas.
+ */
+pub(crate) struct Worker {
+    receiver: Mutex<Option<Receiver<Command>>>,
+    counters: Arc<Counters>,
+    idle: Option<Wake>,
+    on_error: Option<ErrorHook>,
+    panic_policy: PanicPolicy,
+    slow_task: Option<Duration>,
+    /* To shut the scheduler down on a panic */
+    submitter: Submitter,
+    observers: Observers,
+    spawner: Option<Arc<dyn Spawner>>,
+    nice: Option<i32>,
+    restarts: AtomicUsize,
+    /* Kept for join to resume under PanicPolicy::Propagate */
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Worker {
+    pub(crate) fn start(
+        receiver: Receiver<Command>,
+        counters: Arc<Counters>,
+        idle: Option<Wake>,
+        submitter: Submitter,
+        hooks: Hooks,
+    ) -> Arc<Worker> {
+        let worker = Arc::new(Worker {
+            receiver: Mutex::new(Some(receiver)),
+            counters,
+            idle,
+            on_error: hooks.on_error,
+            panic_policy: hooks.panic_policy,
+            slow_task: hooks.slow_task,
+            submitter,
+            observers: hooks.observers,
+            spawner: hooks.spawner,
+            nice: hooks.nice,
+            restarts: AtomicUsize::new(0),
+            panic: Mutex::new(None),
+            thread: Mutex::new(None),
+        });
+        worker.spawn(false);
+        worker
+    }
+
+    /* Held across the spawn so a thread dying right away can't install its
+     * replacement before its own handle is in
+     */
+    fn spawn(self: &Arc<Worker>, restarted: bool) {
+        if restarted {
+            self.restarts.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut slot = self.thread.lock().unwrap_or_else(PoisonError::into_inner);
+        let worker = self.clone();
+        match thread::Builder::new().spawn(move || worker.run(restarted)) {
+            Ok(thread) => *slot = Some(thread),
+            Err(_) => {
+                self.counters.heartbeats.worker.dead();
+                self.receiver
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take();
+            }
+        }
+    }
+
+    /* Runs until told to stop or every sender is gone, i.e. the scheduler
+     * shut down
+     */
+    fn run(self: Arc<Worker>, restarted: bool) {
+        if let Some(nice) = self.nice {
+            platform::set_nice(nice);
+        }
+        if restarted {
+            /* Reported from here, a panic while the old thread unwinds
+             * would abort
+             */
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| match self.on_error.as_ref() {
+                Some(on_error) => on_error(SchedError::WorkerPanicked),
+                None => error!("worker thread panicked, restarted it"),
+            }));
+        }
+
+        let mut restart = Restart {
+            worker: &self,
+            busy: false,
+        };
+
+        loop {
+            self.counters.heartbeats.worker.idle();
+            let command = match self
+                .receiver
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .as_ref()
+            {
+                Some(receiver) => receiver.recv(),
+                None => break,
+            };
+            let task = match command {
+                Ok(Command::Run(task)) => task,
+                Ok(Command::Stop) | Err(_) => break,
+            };
+
+            /* On a runtime this thread only hands tasks on, they run and
+             * finish there
+             */
+            if let Some(spawner) = self.spawner.as_ref() {
+                let worker = self.clone();
+                let node = task.node;
+                let task = Box::pin(async move {
+                    let (label, result) = worker.execute(task);
+                    worker.finish();
+                    telemetry::gauges(&worker.counters);
+                    worker.settle(label, result);
+                });
+                match node {
+                    Some(node) => spawner.spawn_on_node(task, node),
+                    None => spawner.spawn(task),
+                }
+                continue;
+            }
+
+            restart.busy = true;
+            let (label, result) = self.execute(task);
+            restart.busy = false;
+
+            self.finish();
+            telemetry::gauges(&self.counters);
+
+            if self.settle(label, result) {
+                break;
+            }
+        }
+    }
+
+    /* A panicking task must not take the worker down, whatever the policy
+     * says about the tasks after it. State it left half-updated is up to
+     * the tasks, see Shared.
+     */
+    fn execute(&self, task: Task) -> (Label, std::thread::Result<()>) {
+        let Task {
+            mut work,
+            label,
+            tags,
+            node: _,
+        } = task;
+
+        self.counters.heartbeats.worker.busy();
+        platform::task_run(label, tags.as_deref());
+        self.observers.start(StartEvent {
+            task: label.id,
+            name: label.name,
+        });
+        let start = Instant::now();
+        let result = panic::catch_unwind(AssertUnwindSafe(&mut work));
+        drop(work);
+        let elapsed = start.elapsed();
+        self.counters.add_busy(elapsed);
+        if let Some(name) = label.name {
+            self.counters.task_timings.record(name, elapsed);
+        }
+        if self.slow_task.is_some_and(|threshold| elapsed > threshold) {
+            self.counters.slow_tasks.fetch_add(1, Ordering::Relaxed);
+            /* Still counting the one that just ran */
+            let behind = self.counters.in_flight.load(Ordering::Relaxed) - 1;
+            warn!(
+                "{} ran for {:?}, {} queued behind it",
+                label, elapsed, behind
+            );
+        }
+        telemetry::executed(elapsed);
+        self.observers.execute(ExecuteEvent {
+            task: label.id,
+            name: label.name,
+            duration: elapsed,
+            panicked: result.is_err(),
+        });
+
+        (label, result)
+    }
+
+    /* Apply the panic policy, true if the worker should stop */
+    fn settle(&self, label: Label, result: std::thread::Result<()>) -> bool {
+        let Err(panic) = result else {
+            return false;
+        };
+        error!("{} panicked", label);
+        match self.panic_policy {
+            PanicPolicy::CatchAndContinue => false,
+            PanicPolicy::CatchAndShutdown => {
+                self.submitter.close();
+                false
+            }
+            PanicPolicy::Propagate => {
+                *self.panic.lock().unwrap_or_else(PoisonError::into_inner) = Some(panic);
+                self.submitter.close();
+                true
+            }
+        }
+    }
+
+    pub(crate) fn state(&self) -> WorkerState {
+        WorkerState {
+            busy: self.counters.heartbeats.worker.is_busy(),
+            queued: self.counters.in_flight.load(Ordering::Relaxed),
+            restarts: self.restarts.load(Ordering::Relaxed),
+        }
+    }
+
+    fn finish(&self) {
+        /* Let a fast-forwarding clock know it may move on */
+        if self.counters.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            if let Some(idle) = self.idle.as_ref() {
+                idle();
+            }
+        }
+    }
+
+    /* Wait for the last worker thread, following restarts. Resumes a task
+     * panic kept under PanicPolicy::Propagate.
+     */
+    pub(crate) fn join(&self) -> Result<()> {
+        loop {
+            let thread = self
+                .thread
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            let Some(thread) = thread else {
+                break;
+            };
+
+            /* A dying thread installs its replacement before it exits */
+            if thread.join().is_err()
+                && self
+                    .thread
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .is_none()
+            {
+                return Err(SchedError::WorkerPanicked);
+            }
+        }
+
+        let panic = self
+            .panic
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        match panic {
+            Some(panic) => panic::resume_unwind(panic),
+            None => Ok(()),
+        }
+    }
+}
+
+/* Catches a worker thread dying from something catch_unwind does not
+ * cover, like a task panicking on drop, and spawns a replacement
+ */
+struct Restart<'a> {
+    worker: &'a Arc<Worker>,
+    /* A task was running, it is finished either way */
+    busy: bool,
+}
+
+impl Drop for Restart<'_> {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            return;
+        }
+
+        if self.busy {
+            self.worker.finish();
+        }
+        self.worker.spawn(true);
+    }
+}
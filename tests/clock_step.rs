@@ -0,0 +1,33 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use event_scheduler::{MockClock, Scheduler};
+
+const MS: Duration = Duration::from_millis(1);
+
+#[test]
+fn relative_delays_keep_their_distance_when_the_clock_is_set_back() {
+    let clock = MockClock::new();
+    let scheduler = Scheduler::builder().clock(clock.clone()).build();
+    let (sender, receiver) = channel();
+
+    for (name, delay) in [("first", MS * 100), ("second", MS * 200)] {
+        let sender = sender.clone();
+        scheduler
+            .schedule_delayed(delay, move || sender.send(name).unwrap())
+            .unwrap();
+    }
+    /* Answered by the timekeeper once it has both */
+    assert_eq!(scheduler.pending().len(), 2);
+
+    clock.advance(MS * 100);
+    assert_eq!(receiver.recv().unwrap(), "first");
+
+    /* Set back to 40ms with 100ms still to go, due at 140ms now */
+    clock.set(MS * 40);
+    clock.advance(MS * 99);
+    assert!(receiver.recv_timeout(MS * 100).is_err());
+    clock.advance(MS);
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok("second"));
+    scheduler.join().unwrap();
+}
@@ -0,0 +1,33 @@
+/* What the randomized tests share, a generator and the runs they make */
+
+/* xorshift64, reproducible without pulling in a crate */
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/* 500 runs of 400 steps, each seeded apart. `start` sets a run up, `step`
+ * takes it one random step further.
+ */
+pub fn random_runs<S>(
+    mut start: impl FnMut(&mut Rng) -> S,
+    mut step: impl FnMut(&mut S, &mut Rng),
+) {
+    for seed in 1..=500u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let mut run = start(&mut rng);
+        for _ in 0..400 {
+            step(&mut run, &mut rng);
+        }
+    }
+}
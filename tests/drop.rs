@@ -0,0 +1,63 @@
+/* Dropping a scheduler without shutting it down stops its timekeeper,
+ * whatever it waits on. Its pending timeouts are dropped with it.
+ */
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::Duration;
+
+use event_scheduler::{Backend, Builder, SchedError, TimerBackend, Wake};
+
+fn dropped_while_pending(builder: Builder) {
+    let scheduler = builder.build();
+    let handle = scheduler
+        .schedule_delayed(Duration::from_secs(3600), || ())
+        .unwrap();
+    drop(scheduler);
+
+    let done = handle.wait_timeout(Duration::from_secs(5));
+    assert_eq!(done, Some(Err(SchedError::Cancelled)));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn timerfd_timekeeper_stops_on_drop() {
+    dropped_while_pending(Builder::new().backend(Backend::TimerFd));
+}
+
+#[test]
+fn channel_timekeeper_stops_on_drop() {
+    dropped_while_pending(Builder::new().backend(Backend::Channel));
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+#[test]
+fn io_uring_timekeeper_stops_on_drop() {
+    dropped_while_pending(Builder::new().backend(Backend::IoUring));
+}
+
+/* Wakeups are a channel of one, more of them than waits fold into one */
+struct Custom {
+    wakeups: Receiver<()>,
+    sender: SyncSender<()>,
+}
+
+impl TimerBackend for Custom {
+    fn wait(&mut self, timeout: Option<Duration>) {
+        let _ = match timeout {
+            Some(timeout) => self.wakeups.recv_timeout(timeout).ok(),
+            None => self.wakeups.recv().ok(),
+        };
+    }
+
+    fn waker(&self) -> Wake {
+        let sender = self.sender.clone();
+        Box::new(move || {
+            let _ = sender.try_send(());
+        })
+    }
+}
+
+#[test]
+fn custom_timekeeper_stops_on_drop() {
+    let (sender, wakeups) = mpsc::sync_channel(1);
+    dropped_while_pending(Builder::new().timer_backend(Custom { wakeups, sender }));
+}
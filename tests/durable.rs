@@ -0,0 +1,364 @@
+/* DurableTimers across a restart, played by dropping one scheduler and
+ * opening the same file with another.
+ */
+#![cfg(feature = "durable")]
+
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "fault-injection")]
+use event_scheduler::FileStore;
+use event_scheduler::{
+    DurableJob, DurableTimers, JobId, MemoryStore, Missed, Scheduler, TimerStore,
+};
+
+fn path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "event_scheduler-{}-{}.timers",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn pending_jobs_fire_after_a_restart() {
+    let path = path("restart");
+    {
+        let scheduler = Arc::new(Scheduler::new());
+        let timers = DurableTimers::open(&scheduler, &path).unwrap();
+        timers
+            .schedule_delayed("greet", Duration::from_millis(20), "hello")
+            .unwrap();
+        let cancelled = timers
+            .schedule_delayed("greet", Duration::from_millis(20), "bye")
+            .unwrap();
+        timers
+            .schedule_delayed("report", Duration::from_secs(3600), [1, 2, 3])
+            .unwrap();
+        assert!(timers.cancel(cancelled).unwrap());
+        assert!(!timers.cancel(cancelled).unwrap());
+        /* Gone before any handler could run them */
+    }
+
+    let scheduler = Arc::new(Scheduler::new());
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    let pending = timers.pending();
+    assert_eq!(pending.len(), 2);
+    assert_eq!(pending[0].kind, "greet");
+    assert_eq!(pending[0].payload, b"hello");
+    assert_eq!(pending[1].payload, [1, 2, 3]);
+
+    let (ran, runs) = channel();
+    timers
+        .handle("greet", move |payload| ran.send(payload.to_vec()).unwrap())
+        .unwrap();
+    assert_eq!(runs.recv_timeout(Duration::from_secs(5)).unwrap(), b"hello");
+
+    /* Taken out once the handler returned, new ids don't reuse old ones */
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(timers.pending().len(), 1);
+    let id = timers
+        .schedule_delayed("report", Duration::from_secs(3600), "")
+        .unwrap();
+    assert!(id > pending[1].id);
+    drop(timers);
+
+    let reopened = DurableTimers::open(&scheduler, &path).unwrap();
+    assert_eq!(reopened.pending().len(), 2);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn ids_of_jobs_gone_are_not_handed_out_again() {
+    let path = path("ids");
+    let scheduler = Arc::new(Scheduler::new());
+    let mut gone = Vec::new();
+    for _ in 0..100 {
+        let timers = DurableTimers::open(&scheduler, &path).unwrap();
+        assert!(timers.pending().is_empty());
+        let id = timers
+            .schedule_delayed("later", Duration::from_secs(3600), "")
+            .unwrap();
+        assert!(gone.iter().all(|&old| old < id));
+        timers.cancel(id).unwrap();
+        gone.push(id);
+    }
+
+    let store = MemoryStore::new();
+    let timers = DurableTimers::open_store(&scheduler, store.clone()).unwrap();
+    let cancelled = timers
+        .schedule_delayed("later", Duration::from_secs(3600), "")
+        .unwrap();
+    timers.cancel(cancelled).unwrap();
+    drop(timers);
+    let timers = DurableTimers::open_store(&scheduler, store).unwrap();
+    let id = timers
+        .schedule_delayed("later", Duration::from_secs(3600), "")
+        .unwrap();
+    assert!(id > cancelled);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn jobs_due_while_down_fire_right_away() {
+    let path = path("overdue");
+    {
+        let scheduler = Arc::new(Scheduler::new());
+        let timers = DurableTimers::open(&scheduler, &path).unwrap();
+        let past = SystemTime::now() - Duration::from_secs(60);
+        timers.schedule_at("missed", past, "late").unwrap();
+    }
+
+    let scheduler = Arc::new(Scheduler::new());
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    let (ran, runs) = channel();
+    timers
+        .handle("missed", move |payload| ran.send(payload.to_vec()).unwrap())
+        .unwrap();
+    assert_eq!(runs.recv_timeout(Duration::from_secs(5)).unwrap(), b"late");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn kinds_are_words() {
+    let path = path("kinds");
+    let scheduler = Arc::new(Scheduler::new());
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    assert!(timers
+        .schedule_delayed("two words", Duration::ZERO, "")
+        .is_err());
+    assert!(timers.schedule_delayed("", Duration::ZERO, "").is_err());
+    assert!(timers.pending().is_empty());
+}
+
+#[test]
+fn jobs_that_fail_to_arm_are_not_kept() {
+    let path = path("unarmed");
+    let scheduler = Arc::new(Scheduler::builder().max_pending(1).build());
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    timers.handle("later", |_| ()).unwrap();
+    timers
+        .schedule_delayed("later", Duration::from_secs(3600), "")
+        .unwrap();
+    assert!(timers
+        .schedule_delayed("later", Duration::from_secs(3600), "")
+        .is_err());
+    let far = timers.schedule_delayed("later", Duration::MAX, "");
+    assert_eq!(far.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(timers.pending().len(), 1);
+    drop(timers);
+
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    assert_eq!(timers.pending().len(), 1);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn torn_last_line_is_dropped_corruption_before_it_is_not() {
+    let path = path("torn");
+    {
+        let scheduler = Arc::new(Scheduler::new());
+        let timers = DurableTimers::open(&scheduler, &path).unwrap();
+        for _ in 0..2 {
+            timers
+                .schedule_delayed("later", Duration::from_secs(3600), "")
+                .unwrap();
+        }
+    }
+    let journaled = std::fs::read(&path).unwrap();
+
+    /* Cut off mid-append */
+    let mut torn = journaled.clone();
+    torn.extend_from_slice(b"S 2 1700000000000000000 la");
+    std::fs::write(&path, &torn).unwrap();
+    let scheduler = Arc::new(Scheduler::new());
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    assert_eq!(timers.pending().len(), 2);
+    assert_eq!(std::fs::read(&path).unwrap(), journaled);
+    drop(timers);
+
+    let mut corrupt = journaled.clone();
+    corrupt[2] ^= 1;
+    std::fs::write(&path, &corrupt).unwrap();
+    let opened = DurableTimers::open(&scheduler, &path);
+    assert_eq!(opened.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn journal_stays_compact() {
+    let path = path("compact");
+    let scheduler = Arc::new(Scheduler::new());
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    let kept = timers
+        .schedule_delayed("later", Duration::from_secs(3600), "kept")
+        .unwrap();
+    for _ in 0..500 {
+        let id = timers
+            .schedule_delayed("later", Duration::from_secs(3600), "")
+            .unwrap();
+        timers.cancel(id).unwrap();
+    }
+    let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+    assert!(lines < 100, "{} lines", lines);
+    drop(timers);
+
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    let pending = timers.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, kept);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn missed_jobs_fire_or_skip_per_policy() {
+    let path = path("missed");
+    {
+        let scheduler = Arc::new(Scheduler::new());
+        let timers = DurableTimers::open(&scheduler, &path).unwrap();
+        let now = SystemTime::now();
+        timers
+            .schedule_at("job", now - Duration::from_secs(1), "recent")
+            .unwrap();
+        timers
+            .schedule_at("job", now - Duration::from_secs(3600), "stale")
+            .unwrap();
+        timers
+            .schedule_at("job", now + Duration::from_secs(3600), "future")
+            .unwrap();
+    }
+
+    let scheduler = Arc::new(Scheduler::new());
+    let timers = DurableTimers::builder()
+        .missed(Missed::FireWithin(Duration::from_secs(60)))
+        .open(&scheduler, &path)
+        .unwrap();
+    let mut missed: Vec<&[u8]> = timers.missed().iter().map(|job| &job.payload[..]).collect();
+    missed.sort();
+    assert_eq!(missed, [&b"recent"[..], b"stale"]);
+    let pending: Vec<Vec<u8>> = timers
+        .pending()
+        .into_iter()
+        .map(|job| job.payload)
+        .collect();
+    assert_eq!(pending, [b"recent".to_vec(), b"future".to_vec()]);
+    drop(timers);
+
+    /* Skipping is journaled, the stale job is gone for good */
+    let timers = DurableTimers::builder()
+        .missed(Missed::Skip)
+        .open(&scheduler, &path)
+        .unwrap();
+    assert_eq!(timers.missed().len(), 1);
+    assert_eq!(timers.pending().len(), 1);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn memory_store_carries_jobs_across_a_restart() {
+    let store = MemoryStore::new();
+    {
+        let scheduler = Arc::new(Scheduler::new());
+        let timers = DurableTimers::open_store(&scheduler, store.clone()).unwrap();
+        timers
+            .schedule_delayed("greet", Duration::from_millis(20), "hello")
+            .unwrap();
+    }
+
+    let scheduler = Arc::new(Scheduler::new());
+    let timers = DurableTimers::open_store(&scheduler, store.clone()).unwrap();
+    let (ran, runs) = channel();
+    timers
+        .handle("greet", move |payload| ran.send(payload.to_vec()).unwrap())
+        .unwrap();
+    assert_eq!(runs.recv_timeout(Duration::from_secs(5)).unwrap(), b"hello");
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(store.snapshot().unwrap().is_empty());
+}
+
+/* Refuses writes once full, like a disk would */
+struct Bounded {
+    jobs: MemoryStore,
+    room: usize,
+}
+
+impl TimerStore for Bounded {
+    fn put(&mut self, job: &DurableJob) -> std::io::Result<()> {
+        if self.jobs.snapshot()?.len() >= self.room {
+            return Err(std::io::Error::other("full"));
+        }
+        self.jobs.put(job)
+    }
+
+    fn remove(&mut self, id: JobId) -> std::io::Result<()> {
+        self.jobs.remove(id)
+    }
+
+    fn scan_due(&self, until: SystemTime) -> std::io::Result<Vec<DurableJob>> {
+        self.jobs.scan_due(until)
+    }
+
+    fn snapshot(&self) -> std::io::Result<Vec<DurableJob>> {
+        self.jobs.snapshot()
+    }
+
+    fn next_id(&self) -> std::io::Result<JobId> {
+        self.jobs.next_id()
+    }
+}
+
+#[test]
+fn jobs_the_store_refuses_are_not_scheduled() {
+    let scheduler = Arc::new(Scheduler::new());
+    let store = Bounded {
+        jobs: MemoryStore::new(),
+        room: 1,
+    };
+    let timers = DurableTimers::open_store(&scheduler, store).unwrap();
+    let kept = timers
+        .schedule_delayed("later", Duration::from_secs(3600), "")
+        .unwrap();
+    assert!(timers
+        .schedule_delayed("later", Duration::from_secs(3600), "")
+        .is_err());
+    assert!(timers.cancel(kept).unwrap());
+    timers
+        .schedule_delayed("later", Duration::from_secs(3600), "")
+        .unwrap();
+    assert_eq!(timers.pending().len(), 1);
+}
+
+#[cfg(feature = "fault-injection")]
+#[test]
+fn appends_after_a_failed_compaction_are_kept() {
+    let path = path("dirsync");
+    {
+        let scheduler = Arc::new(Scheduler::new());
+        let store = FileStore::open(&path).unwrap().fail_dir_syncs(1);
+        let timers = DurableTimers::open_store(&scheduler, store).unwrap();
+        let lines = || std::fs::read_to_string(&path).unwrap().lines().count();
+        /* Until the compaction that fails to sync renamed a shorter file in */
+        let mut before = 0;
+        while lines() >= before {
+            before = lines();
+            let id = timers
+                .schedule_delayed("later", Duration::from_secs(3600), "")
+                .unwrap();
+            timers.cancel(id).unwrap();
+        }
+        timers
+            .schedule_delayed("later", Duration::from_secs(3600), "kept")
+            .unwrap();
+    }
+
+    let scheduler = Arc::new(Scheduler::new());
+    let timers = DurableTimers::open(&scheduler, &path).unwrap();
+    let pending = timers.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].payload, b"kept");
+    let _ = std::fs::remove_file(&path);
+}
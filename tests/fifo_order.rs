@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use event_scheduler::{
+    Backend, Invocation, MockClock, RecordingExecutor, Scheduler, SimScheduler, TimerQueue,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+fn names(invocations: &[Invocation]) -> Vec<&'static str> {
+    invocations.iter().map(|i| i.name.unwrap()).collect()
+}
+
+#[test]
+fn equal_deadlines_fire_in_submission_order() {
+    let clock = MockClock::new();
+    let executor = RecordingExecutor::new();
+    let scheduler = Scheduler::builder()
+        .clock(clock.clone())
+        .executor(executor.clone())
+        .build();
+
+    let order = ["e", "b", "d", "a", "c"];
+    for name in order {
+        scheduler
+            .named(name)
+            .schedule_delayed(ms(10), || {})
+            .unwrap();
+    }
+    /* Everything is in before the clock moves */
+    assert_eq!(scheduler.pending().len(), order.len());
+
+    clock.advance(ms(10));
+    assert!(executor.wait_for(order.len(), TIMEOUT));
+    assert_eq!(names(&executor.invocations()), order);
+}
+
+#[test]
+fn quantized_deadlines_fire_in_submission_order() {
+    let clock = MockClock::new();
+    let executor = RecordingExecutor::new();
+    let scheduler = Scheduler::builder()
+        .clock(clock.clone())
+        .resolution(ms(10))
+        .executor(executor.clone())
+        .build();
+
+    /* All round up to the same 10ms slot */
+    for (name, delay) in [("a", 9), ("b", 1), ("c", 5), ("d", 10), ("e", 3)] {
+        scheduler
+            .named(name)
+            .schedule_delayed(ms(delay), || {})
+            .unwrap();
+    }
+    assert_eq!(scheduler.pending().len(), 5);
+
+    clock.advance(ms(10));
+    assert!(executor.wait_for(5, TIMEOUT));
+    assert_eq!(names(&executor.invocations()), ["a", "b", "c", "d", "e"]);
+}
+
+#[test]
+fn periodic_ticks_keep_their_place_among_equal_deadlines() {
+    let clock = MockClock::new();
+    let executor = RecordingExecutor::new();
+    let scheduler = Scheduler::builder()
+        .clock(clock.clone())
+        .executor(executor.clone())
+        .build();
+
+    scheduler
+        .named("before")
+        .schedule_delayed(ms(20), || {})
+        .unwrap();
+    scheduler
+        .named("tick")
+        .schedule_periodic(ms(10), || {})
+        .unwrap();
+    scheduler
+        .named("after")
+        .schedule_delayed(ms(20), || {})
+        .unwrap();
+    assert_eq!(scheduler.pending().len(), 3);
+
+    clock.advance(ms(10));
+    assert!(executor.wait_for(1, TIMEOUT));
+    clock.advance(ms(10));
+    assert!(executor.wait_for(4, TIMEOUT));
+
+    /* The second tick was re-armed after "after" was scheduled, but was
+     * submitted before it
+     */
+    assert_eq!(
+        names(&executor.invocations()),
+        ["tick", "before", "tick", "after"]
+    );
+}
+
+#[test]
+fn every_backend_breaks_ties_in_submission_order() {
+    let backends = [
+        Backend::Channel,
+        #[cfg(target_os = "linux")]
+        Backend::TimerFd,
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        Backend::IoUring,
+        #[cfg(windows)]
+        Backend::WaitableTimer,
+    ];
+
+    const NAMES: [&str; 16] = [
+        "t0", "t1", "t2", "t3", "t4", "t5", "t6", "t7", "t8", "t9", "t10", "t11", "t12", "t13",
+        "t14", "t15",
+    ];
+
+    for backend in backends {
+        let executor = RecordingExecutor::new();
+        let scheduler = Scheduler::builder()
+            .backend(backend)
+            .resolution(ms(50))
+            .executor(executor.clone())
+            .build();
+
+        /* Mostly the same slot, shuffled delays within it */
+        for (i, name) in NAMES.iter().enumerate() {
+            let delay = ms(20 + (i as u64 * 7) % 16);
+            scheduler
+                .named(name)
+                .schedule_delayed(delay, || {})
+                .unwrap();
+        }
+
+        assert!(executor.wait_for(NAMES.len(), TIMEOUT), "{:?}", backend);
+        let invocations = executor.invocations();
+
+        /* Submission order is name order, ids are handed out in it too */
+        let mut expected = invocations.clone();
+        expected.sort_by_key(|i| (i.scheduled_for, i.task));
+        assert_eq!(invocations, expected, "{:?}", backend);
+        assert!(invocations
+            .windows(2)
+            .any(|w| w[0].scheduled_for == w[1].scheduled_for));
+    }
+}
+
+#[test]
+fn timer_queue_and_sim_break_ties_in_insertion_order() {
+    let mut queue = TimerQueue::new(Duration::ZERO);
+    for (item, delay) in [(0, 5), (1, 3), (2, 5), (3, 3), (4, 5)] {
+        queue.insert(ms(delay), item);
+    }
+    assert_eq!(queue.tick(ms(5)).collect::<Vec<_>>(), [1, 3, 0, 2, 4]);
+
+    let sim = SimScheduler::new();
+    let fired = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    for (item, delay) in [(0, 5), (1, 3), (2, 5), (3, 3), (4, 5)] {
+        let fired = fired.clone();
+        sim.schedule_delayed(ms(delay), move || fired.borrow_mut().push(item));
+    }
+    sim.advance_by(ms(5));
+    assert_eq!(*fired.borrow(), [1, 3, 0, 2, 4]);
+}
@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use event_scheduler::golden::{
+    differential, Divergence, Fire, QueueTarget, Reference, SchedulerTarget, SimTarget, Target,
+    Workload,
+};
+use event_scheduler::{Backend, Scheduler};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn reference_fires_in_due_order_ties_in_schedule_order() {
+    let mut reference = Reference::new();
+    reference.schedule(0, ms(20), false);
+    reference.schedule(1, ms(10), true);
+    reference.schedule(2, ms(20), false);
+    reference.schedule(3, ms(5), false);
+    reference.cancel(3);
+
+    let fired = reference.advance_to(ms(25));
+    let fired = fired.iter().map(|f| (f.id, f.due)).collect::<Vec<_>>();
+    assert_eq!(fired, [(1, ms(10)), (0, ms(20)), (1, ms(20)), (2, ms(20))]);
+    assert!(reference.advance_to(ms(29)).is_empty());
+    assert_eq!(reference.advance_to(ms(30))[0].due, ms(30));
+}
+
+#[test]
+fn workloads_are_reproducible() {
+    let workload = Workload::random(7, 100, ms(1));
+    assert_eq!(workload, Workload::random(7, 100, ms(1)));
+    assert_ne!(workload, Workload::random(8, 100, ms(1)));
+    assert_eq!(
+        differential(&workload, &mut Reference::new(), Duration::ZERO),
+        Ok(())
+    );
+}
+
+#[test]
+fn timer_queue_matches_the_reference() {
+    for seed in 0..300 {
+        let workload = Workload::random(seed, 200, ms(1));
+        let result = differential(&workload, &mut QueueTarget::new(), Duration::ZERO);
+        assert_eq!(result, Ok(()), "seed {}", seed);
+    }
+}
+
+#[test]
+fn sim_matches_the_reference() {
+    for seed in 0..300 {
+        let workload = Workload::random(seed, 200, ms(1));
+        let result = differential(&workload, &mut SimTarget::new(), Duration::ZERO);
+        assert_eq!(result, Ok(()), "seed {}", seed);
+    }
+}
+
+#[test]
+fn scheduler_on_virtual_time_matches_the_reference() {
+    for seed in 0..30 {
+        let workload = Workload::random(seed, 100, ms(1));
+        let mut target = SchedulerTarget::virtual_time(Scheduler::builder());
+        let result = differential(&workload, &mut target, Duration::ZERO);
+        assert_eq!(result, Ok(()), "seed {}", seed);
+    }
+}
+
+#[test]
+fn every_backend_matches_the_reference_in_real_time() {
+    let backends = [
+        Backend::Channel,
+        #[cfg(target_os = "linux")]
+        Backend::TimerFd,
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        Backend::IoUring,
+        #[cfg(windows)]
+        Backend::WaitableTimer,
+    ];
+
+    /* A fire may be up to 25ms late, what a loaded machine running the
+     * other tests alongside can take to schedule the timekeeper and worker
+     * threads. It has to stay below the 50ms unit so no two times of the
+     * workload come within the tolerance of each other and swap places.
+     */
+    let workload = Workload::random(1, 20, ms(50));
+    for backend in backends {
+        let mut target = SchedulerTarget::real_time(Scheduler::builder().backend(backend));
+        let result = differential(&workload, &mut target, ms(25));
+        assert_eq!(result, Ok(()), "{:?}", backend);
+    }
+}
+
+/* Breaks ties the wrong way round */
+struct Lifo(Reference);
+
+impl Target for Lifo {
+    fn schedule(&mut self, id: u64, delay: Duration, periodic: bool) {
+        self.0.schedule(id, delay, periodic);
+    }
+
+    fn cancel(&mut self, id: u64) {
+        self.0.cancel(id);
+    }
+
+    fn advance_to(&mut self, now: Duration) -> Vec<Fire> {
+        let mut fired = self.0.advance_to(now);
+        fired.sort_by_key(|f| (f.due, std::cmp::Reverse(f.id)));
+        fired
+    }
+}
+
+#[test]
+fn harness_catches_a_wrong_tie_break() {
+    let workload = Workload::random(3, 200, ms(1));
+    let mut target = Lifo(Reference::new());
+    let result = differential(&workload, &mut target, Duration::ZERO);
+    assert!(
+        matches!(result, Err(Divergence::OutOfOrder { .. })),
+        "{:?}",
+        result
+    );
+}
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use event_scheduler::{Dedup, SchedError, Scheduler};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[test]
+fn replacing_a_key_at_max_pending_reuses_its_room() {
+    let scheduler = Scheduler::builder().max_pending(2).build();
+    scheduler.schedule_delayed(HOUR, || ()).unwrap();
+    let first = scheduler
+        .schedule_delayed_keyed("key", HOUR, Dedup::ReplaceExisting, || 1)
+        .unwrap();
+
+    for round in 2..100 {
+        scheduler
+            .schedule_delayed_keyed("key", HOUR, Dedup::ReplaceExisting, move || round)
+            .unwrap();
+    }
+    assert_eq!(first.wait().unwrap_err(), SchedError::Cancelled);
+    assert_eq!(scheduler.pending().len(), 2);
+
+    /* Still no room for another key */
+    let other = scheduler.schedule_delayed_keyed("other", HOUR, Dedup::ReplaceExisting, || 0);
+    assert_eq!(other.unwrap_err(), SchedError::TooManyTimers);
+}
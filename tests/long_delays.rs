@@ -0,0 +1,164 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use event_scheduler::{Clock, MockClock, Scheduler, TimerQueue};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+const YEAR: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+#[test]
+fn timer_queue_orders_long_delays() {
+    let mut queue = TimerQueue::new(Duration::ZERO);
+    queue.insert(YEAR * 100, "century");
+    queue.insert(DAY * 7, "week");
+    queue.insert(Duration::MAX, "never");
+    queue.insert(YEAR, "year");
+
+    assert_eq!(queue.next_deadline(), Some(DAY * 7));
+    assert_eq!(queue.tick(YEAR).collect::<Vec<_>>(), ["week", "year"]);
+    assert_eq!(queue.tick(YEAR * 99).count(), 0);
+    assert_eq!(queue.tick(YEAR * 100).collect::<Vec<_>>(), ["century"]);
+    assert_eq!(queue.next_deadline(), Some(Duration::MAX));
+    assert_eq!(queue.tick(Duration::MAX).collect::<Vec<_>>(), ["never"]);
+}
+
+#[test]
+fn timer_queue_saturates_past_duration_max() {
+    let mut queue = TimerQueue::new(YEAR);
+    queue.insert(Duration::MAX, "never");
+    queue.insert(Duration::MAX - YEAR, "almost");
+
+    assert_eq!(queue.next_deadline(), Some(Duration::MAX));
+    assert_eq!(
+        queue.tick(Duration::MAX).collect::<Vec<_>>(),
+        ["never", "almost"]
+    );
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn delayed_work_fires_after_years() {
+    let clock = MockClock::auto_advance();
+    let scheduler = Scheduler::builder()
+        .clock(clock.clone())
+        .resolution(Duration::from_secs(1))
+        .build();
+    let (sender, receiver) = channel();
+
+    /* Keep the clock from jumping to the first deadline before the others
+     * are in
+     */
+    let (release, gate) = channel();
+    scheduler.schedule(move || gate.recv().unwrap()).unwrap();
+
+    for years in [10, 1, 5] {
+        let sender = sender.clone();
+        scheduler
+            .schedule_delayed(YEAR * years, move || sender.send(years).unwrap())
+            .unwrap();
+    }
+    release.send(()).unwrap();
+
+    assert_eq!(receiver.iter().take(3).collect::<Vec<_>>(), [1, 5, 10]);
+    assert_eq!(clock.now(), YEAR * 10);
+    scheduler.join().unwrap();
+}
+
+#[test]
+fn periodic_with_yearly_interval_stays_anchored() {
+    let clock = MockClock::auto_advance();
+    let scheduler = Scheduler::builder().clock(clock.clone()).build();
+    let (sender, receiver) = channel();
+
+    let ticks = clock.clone();
+    let handle = scheduler
+        .schedule_periodic(YEAR, move || sender.send(ticks.now()).unwrap())
+        .unwrap();
+
+    let fired: Vec<_> = receiver.iter().take(4).collect();
+    handle.cancel().unwrap();
+
+    assert_eq!(fired, [YEAR, YEAR * 2, YEAR * 3, YEAR * 4]);
+    scheduler.join().unwrap();
+}
+
+#[test]
+fn duration_max_never_fires_and_cancels() {
+    let scheduler = Scheduler::builder()
+        .resolution(Duration::from_millis(1))
+        .build();
+    let (sender, receiver) = channel();
+
+    let never = sender.clone();
+    let handle = scheduler
+        .schedule_delayed(Duration::MAX, move || never.send("never").unwrap())
+        .unwrap();
+    scheduler
+        .schedule_delayed(Duration::from_millis(10), move || {
+            sender.send("soon").unwrap()
+        })
+        .unwrap();
+
+    assert_eq!(receiver.recv().unwrap(), "soon");
+    assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+    handle.cancel().unwrap();
+    scheduler.join().unwrap();
+}
+
+#[test]
+fn waiting_duration_max_for_a_handle_waits_it_out() {
+    let scheduler = Scheduler::new();
+    let handle = scheduler
+        .schedule_delayed(Duration::from_millis(10), || "soon")
+        .unwrap();
+
+    assert_eq!(handle.wait_timeout(Duration::MAX), Some(Ok("soon")));
+    scheduler.join().unwrap();
+}
+
+#[test]
+fn zero_periodic_intervals_are_refused() {
+    let scheduler = Scheduler::new();
+
+    let plain = panic::catch_unwind(AssertUnwindSafe(|| {
+        scheduler.schedule_periodic(Duration::ZERO, || {})
+    }));
+    let named = panic::catch_unwind(AssertUnwindSafe(|| {
+        scheduler
+            .named("zero")
+            .schedule_periodic(Duration::ZERO, || {})
+    }));
+
+    assert!(plain.is_err() && named.is_err());
+    assert!(scheduler.pending().is_empty());
+    scheduler.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn kernel_timers_clamp_long_waits() {
+    use event_scheduler::Backend;
+
+    let backends = [
+        Backend::TimerFd,
+        #[cfg(feature = "io-uring")]
+        Backend::IoUring,
+    ];
+
+    for backend in backends {
+        let scheduler = Scheduler::builder().backend(backend).build();
+        let (sender, receiver) = channel();
+
+        let never = sender.clone();
+        scheduler
+            .schedule_delayed(Duration::MAX, move || never.send("never").unwrap())
+            .unwrap();
+        scheduler
+            .schedule_delayed(YEAR * 1000, move || sender.send("millennium").unwrap())
+            .unwrap();
+
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+        scheduler.join().unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+//! Model checks of the timekeeper/worker handoff, run with
+//! `RUSTFLAGS="--cfg loom" cargo test --release --test loom`.
+#![cfg(loom)]
+
+use std::time::Duration;
+
+use event_scheduler::{MockClock, Scheduler};
+use loom::sync::atomic::{AtomicUsize, Ordering};
+use loom::sync::mpsc::channel;
+use loom::sync::Arc;
+
+fn model<F>(f: F)
+where
+    F: Fn() + Sync + Send + 'static,
+{
+    let mut builder = loom::model::Builder::new();
+    builder.preemption_bound.get_or_insert(2);
+    builder.check(f);
+}
+
+fn scheduler(clock: MockClock) -> Scheduler {
+    Scheduler::builder().clock(clock).latency_history(0).build()
+}
+
+/* The auto-advancing clock may only jump once the worker is idle. If the
+ * worker's idle wake raced the timekeeper's idle check the delayed work
+ * would never fire and recv would deadlock.
+ */
+#[test]
+fn fast_forward_waits_for_running_work() {
+    model(|| {
+        let scheduler = scheduler(MockClock::auto_advance());
+        let (sender, receiver) = channel();
+
+        let immediate = sender.clone();
+        scheduler
+            .schedule(move || immediate.send(0).unwrap())
+            .unwrap();
+        scheduler
+            .schedule_delayed(Duration::from_millis(10), move || sender.send(1).unwrap())
+            .unwrap();
+
+        assert_eq!(receiver.recv().unwrap(), 0);
+        assert_eq!(receiver.recv().unwrap(), 1);
+        scheduler.join().unwrap();
+    });
+}
+
+/* Advancing by hand wakes the timekeeper through on_advance, whenever it
+ * happens relative to the timeout reaching the timekeeper.
+ */
+#[test]
+fn advance_wakes_timekeeper() {
+    model(|| {
+        let clock = MockClock::new();
+        let scheduler = scheduler(clock.clone());
+        let (sender, receiver) = channel();
+
+        scheduler
+            .schedule_delayed(Duration::from_millis(10), move || sender.send(()).unwrap())
+            .unwrap();
+
+        let driver = loom::thread::spawn(move || clock.advance(Duration::from_millis(10)));
+
+        receiver.recv().unwrap();
+        driver.join().unwrap();
+        scheduler.join().unwrap();
+    });
+}
+
+/* Cancelling races the fire, the work runs at most once and shutting down
+ * afterwards never hangs.
+ */
+#[test]
+fn cancel_races_fire() {
+    model(|| {
+        let scheduler = scheduler(MockClock::auto_advance());
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let counted = runs.clone();
+        let handle = scheduler
+            .schedule_delayed(Duration::from_millis(10), move || {
+                counted.fetch_add(1, Ordering::Relaxed);
+            })
+            .unwrap();
+
+        let canceller = loom::thread::spawn(move || {
+            let _ = handle.cancel();
+        });
+
+        canceller.join().unwrap();
+        scheduler.join().unwrap();
+        assert!(runs.load(Ordering::Relaxed) <= 1);
+    });
+}
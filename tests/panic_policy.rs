@@ -0,0 +1,76 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use event_scheduler::{PanicPolicy, SchedError, Scheduler};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn scheduler(policy: PanicPolicy) -> Scheduler {
+    Scheduler::builder().panic_policy(policy).build()
+}
+
+/* Run a panicking task, then one reporting whether the worker got to it */
+fn panic_then_report(scheduler: &Scheduler) -> Receiver<()> {
+    let (sender, receiver) = channel();
+    scheduler.schedule(|| panic!("task panicked")).unwrap();
+    let _ = scheduler.schedule(move || sender.send(()).unwrap());
+    receiver
+}
+
+fn wait_for_shutdown(scheduler: &Scheduler) {
+    let start = Instant::now();
+    while !scheduler.is_shut_down() {
+        assert!(start.elapsed() < TIMEOUT, "scheduler did not shut down");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn catch_and_continue_runs_later_tasks() {
+    let scheduler = scheduler(PanicPolicy::CatchAndContinue);
+    let receiver = panic_then_report(&scheduler);
+
+    receiver.recv_timeout(TIMEOUT).unwrap();
+    assert!(!scheduler.is_shut_down());
+    assert_eq!(scheduler.join(), Ok(()));
+}
+
+#[test]
+fn catch_and_shutdown_stops_the_scheduler() {
+    let scheduler = scheduler(PanicPolicy::CatchAndShutdown);
+    let pending = scheduler
+        .schedule_delayed(Duration::from_secs(60 * 60), || ())
+        .unwrap();
+    scheduler.schedule(|| panic!("task panicked")).unwrap();
+
+    wait_for_shutdown(&scheduler);
+    assert_eq!(scheduler.schedule(|| ()), Err(SchedError::ShutDown));
+    assert_eq!(
+        scheduler.schedule_delayed(Duration::ZERO, || ()).err(),
+        Some(SchedError::ShutDown)
+    );
+    assert_eq!(pending.cancel(), Err(SchedError::ShutDown));
+    assert_eq!(scheduler.join(), Ok(()));
+}
+
+#[test]
+fn propagate_resumes_the_panic_from_join() {
+    let scheduler = scheduler(PanicPolicy::Propagate);
+    scheduler.schedule(|| panic!("task panicked")).unwrap();
+
+    wait_for_shutdown(&scheduler);
+    let panic = panic::catch_unwind(AssertUnwindSafe(|| scheduler.join())).unwrap_err();
+    assert_eq!(panic.downcast_ref::<&str>(), Some(&"task panicked"));
+}
+
+#[test]
+fn delayed_task_panics_follow_the_policy() {
+    let scheduler = scheduler(PanicPolicy::CatchAndShutdown);
+    scheduler
+        .schedule_delayed(Duration::from_millis(1), || panic!("task panicked"))
+        .unwrap();
+
+    wait_for_shutdown(&scheduler);
+    assert_eq!(scheduler.join(), Ok(()));
+}
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use event_scheduler::{CallKind, RecordedCall, Recording};
+
+#[test]
+fn delays_past_u64_nanoseconds_read_back() {
+    let recording = Recording {
+        calls: vec![RecordedCall {
+            at: Duration::from_secs(1),
+            task: None,
+            name: Some("never".into()),
+            kind: CallKind::Once {
+                delay: Duration::MAX,
+            },
+        }],
+    };
+
+    let mut text = Vec::new();
+    recording.write_to(&mut text).unwrap();
+    assert_eq!(Recording::read_from(&text[..]).unwrap(), recording);
+}
@@ -0,0 +1,25 @@
+/* Branching a SimScheduler from a snapshot replays what had yet to run */
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use event_scheduler::SimScheduler;
+
+#[test]
+fn one_shot_work_runs_in_every_branch() {
+    let sim = SimScheduler::new();
+    let hits = Rc::new(Cell::new(0));
+
+    let counter = hits.clone();
+    sim.schedule_delayed(Duration::from_millis(10), move || {
+        counter.set(counter.get() + 1)
+    });
+    let snapshot = sim.snapshot();
+
+    for branch in 1..=2 {
+        sim.restore(&snapshot);
+        assert_eq!(sim.advance_by(Duration::from_millis(10)), 1);
+        assert_eq!(hits.get(), branch);
+        assert_eq!(sim.pending(), 0);
+    }
+}
@@ -0,0 +1,46 @@
+/* Spawners can come from outside the crate, here a thread per task */
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use event_scheduler::{Scheduler, Spawned, Spawner};
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+struct Threads;
+
+impl Spawner for Threads {
+    fn spawn(&self, mut task: Spawned) {
+        thread::spawn(move || {
+            let waker = Arc::new(Unpark(thread::current())).into();
+            let mut cx = Context::from_waker(&waker);
+            while task.as_mut().poll(&mut cx) == Poll::Pending {
+                thread::park();
+            }
+        });
+    }
+}
+
+#[test]
+fn tasks_run_on_a_spawner_of_our_own() {
+    let scheduler = Scheduler::builder().spawner(Threads).build();
+    let (sender, receiver) = channel();
+
+    scheduler
+        .schedule_delayed(Duration::from_millis(10), move || {
+            sender.send(thread::current().id()).unwrap()
+        })
+        .unwrap();
+
+    let ran_on = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_ne!(ran_on, thread::current().id());
+    scheduler.join().unwrap();
+}
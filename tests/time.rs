@@ -0,0 +1,45 @@
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use event_scheduler::{SchedError, Scheduler};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+fn full() -> Scheduler {
+    let scheduler = Scheduler::builder().max_pending(1).build();
+    scheduler.schedule_delayed(HOUR, || ()).unwrap();
+    scheduler
+}
+
+#[test]
+fn sleep_reports_a_full_scheduler() {
+    assert_eq!(full().sleep(HOUR).unwrap_err(), SchedError::TooManyTimers);
+}
+
+#[test]
+fn timeout_reports_a_full_scheduler() {
+    let timeout = full().timeout(HOUR, std::future::pending::<()>());
+    assert_eq!(timeout.unwrap_err(), SchedError::TooManyTimers);
+}
+
+#[test]
+fn interval_retries_once_the_scheduler_has_room() {
+    let scheduler = Scheduler::builder().max_pending(1).build();
+    let blocker = scheduler.schedule_delayed(HOUR, || ()).unwrap();
+    let mut interval = scheduler.interval(Duration::from_millis(10));
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let full = interval.poll_tick(&mut cx);
+    assert_eq!(full, Poll::Ready(Some(Err(SchedError::TooManyTimers))));
+    blocker.cancel().unwrap();
+    while interval.poll_tick(&mut cx).is_ready() {
+        std::thread::yield_now();
+    }
+    let tick = loop {
+        if let Poll::Ready(tick) = interval.poll_tick(&mut cx) {
+            break tick;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    assert_eq!(tick.unwrap().unwrap().tick, 0);
+}
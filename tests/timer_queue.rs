@@ -0,0 +1,207 @@
+/* TimerQueue against a model of what it promises: a list sorted by
+ * deadline, ties in insertion order. Every short sequence of operations
+ * over a small alphabet is tried, then long random ones.
+ */
+use std::time::Duration;
+
+use event_scheduler::{TaskId, TimerQueue};
+
+mod common;
+use common::{random_runs, Rng};
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Insert(Duration),
+    /* Index into every id handed out, expired or not */
+    Remove(usize),
+    /* Pop at most one item, `advance` after the time the model is at */
+    Pop(Duration),
+    /* Tick to `advance` after the model's time and drain what expired */
+    Drain(Duration),
+    /* Ask for a time before the last one */
+    Rewind(Duration),
+}
+
+#[derive(Default)]
+struct Model {
+    now: Duration,
+    seq: u64,
+    pending: Vec<(Duration, u64, TaskId)>,
+}
+
+struct Harness {
+    queue: TimerQueue<u64>,
+    model: Model,
+    ids: Vec<TaskId>,
+}
+
+impl Harness {
+    fn new(now: Duration) -> Harness {
+        Harness {
+            queue: TimerQueue::new(now),
+            model: Model {
+                now,
+                ..Model::default()
+            },
+            ids: Vec::new(),
+        }
+    }
+
+    fn expected_expired(&mut self) -> Option<u64> {
+        match self.model.pending.first() {
+            Some(&(deadline, seq, _)) if deadline <= self.model.now => {
+                self.model.pending.remove(0);
+                Some(seq)
+            }
+            _ => None,
+        }
+    }
+
+    fn apply(&mut self, op: Op) {
+        match op {
+            Op::Insert(delay) => {
+                let seq = self.model.seq;
+                self.model.seq += 1;
+
+                let id = self.queue.insert(delay, seq);
+                assert!(!self.ids.contains(&id), "id {} handed out twice", id);
+                self.ids.push(id);
+
+                let deadline = self.model.now.saturating_add(delay);
+                let at = self
+                    .model
+                    .pending
+                    .iter()
+                    .position(|&(d, _, _)| d > deadline)
+                    .unwrap_or(self.model.pending.len());
+                self.model.pending.insert(at, (deadline, seq, id));
+            }
+            Op::Remove(i) => {
+                let Some(&id) = self.ids.get(i) else {
+                    return;
+                };
+                let expected = self
+                    .model
+                    .pending
+                    .iter()
+                    .position(|&(_, _, p)| p == id)
+                    .map(|at| self.model.pending.remove(at).1);
+                assert_eq!(self.queue.remove(id), expected, "remove {}", id);
+            }
+            Op::Pop(advance) => {
+                self.model.now = self.model.now.saturating_add(advance);
+                let now = self.model.now;
+                assert_eq!(self.queue.pop_expired(now), self.expected_expired());
+            }
+            Op::Drain(advance) => {
+                self.model.now = self.model.now.saturating_add(advance);
+                let fired = self.queue.tick(self.model.now).collect::<Vec<_>>();
+                let expected = std::iter::from_fn(|| self.expected_expired()).collect::<Vec<_>>();
+                assert_eq!(fired, expected, "drain at {:?}", self.model.now);
+            }
+            Op::Rewind(back) => {
+                /* Nothing moves backwards, but what is already due stays due */
+                let now = self.model.now.saturating_sub(back);
+                assert_eq!(self.queue.pop_expired(now), self.expected_expired());
+            }
+        }
+
+        self.check();
+    }
+
+    fn check(&self) {
+        let pending = &self.model.pending;
+        assert_eq!(self.queue.len(), pending.len());
+        assert_eq!(self.queue.is_empty(), pending.is_empty());
+        assert_eq!(self.queue.next_deadline(), pending.first().map(|p| p.0));
+        assert_eq!(
+            self.queue
+                .peek()
+                .map(|(id, deadline, &seq)| (deadline, seq, id)),
+            pending.first().copied()
+        );
+    }
+}
+
+fn alphabet() -> Vec<Op> {
+    let ms = Duration::from_millis;
+    vec![
+        Op::Insert(ms(0)),
+        Op::Insert(ms(1)),
+        Op::Insert(ms(2)),
+        Op::Insert(Duration::MAX),
+        Op::Remove(0),
+        Op::Remove(2),
+        Op::Pop(ms(0)),
+        Op::Pop(ms(1)),
+        Op::Drain(ms(2)),
+        Op::Drain(Duration::MAX),
+        Op::Rewind(ms(1)),
+    ]
+}
+
+/* Run every sequence of `len` ops, sharing prefixes by replaying them */
+fn exhaust(ops: &[Op], prefix: &mut Vec<Op>, len: usize) {
+    if prefix.len() == len {
+        let mut harness = Harness::new(Duration::from_millis(5));
+        for &op in prefix.iter() {
+            harness.apply(op);
+        }
+        return;
+    }
+
+    for &op in ops {
+        prefix.push(op);
+        exhaust(ops, prefix, len);
+        prefix.pop();
+    }
+}
+
+#[test]
+fn every_short_sequence_matches_the_model() {
+    let ops = alphabet();
+    for len in 0..=5 {
+        exhaust(&ops, &mut Vec::new(), len);
+    }
+}
+
+fn random_op(rng: &mut Rng, issued: usize) -> Op {
+    let micros = |rng: &mut Rng| Duration::from_micros(rng.below(64));
+    match rng.below(12) {
+        0..=3 => Op::Insert(micros(rng)),
+        4 => Op::Insert(Duration::from_secs(rng.next())),
+        5 | 6 => Op::Remove(rng.below(issued as u64 + 1) as usize),
+        7 | 8 => Op::Pop(micros(rng)),
+        9 => Op::Drain(micros(rng)),
+        10 => Op::Drain(Duration::from_secs(rng.below(1 << 40))),
+        _ => Op::Rewind(micros(rng)),
+    }
+}
+
+#[test]
+fn random_sequences_match_the_model() {
+    random_runs(
+        |rng| Harness::new(Duration::from_secs(rng.below(1 << 20))),
+        |harness, rng| {
+            let op = random_op(rng, harness.ids.len());
+            harness.apply(op);
+        },
+    );
+}
+
+#[test]
+fn equal_deadlines_expire_in_insertion_order() {
+    let mut queue = TimerQueue::new(Duration::ZERO);
+    let ms = Duration::from_millis;
+    queue.insert(ms(2), "c");
+    queue.insert(ms(1), "a");
+    queue.insert(ms(2), "d");
+    queue.insert(ms(1), "b");
+
+    assert_eq!(
+        queue.peek().map(|(_, at, &item)| (at, item)),
+        Some((ms(1), "a"))
+    );
+    assert_eq!(queue.pop_expired(ms(0)), None);
+    assert_eq!(queue.tick(ms(5)).collect::<Vec<_>>(), ["a", "b", "c", "d"]);
+}
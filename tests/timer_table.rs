@@ -0,0 +1,96 @@
+/* TimerTable against TimerQueue, which it promises to order the same way,
+ * over random operations on a table small enough to fill up.
+ */
+use std::time::Duration;
+
+use event_scheduler::{TaskId, TimerQueue, TimerTable};
+
+mod common;
+use common::{random_runs, Rng};
+
+const CAPACITY: usize = 8;
+
+struct Run {
+    table: TimerTable<CAPACITY>,
+    queue: TimerQueue<TaskId>,
+    now: Duration,
+    ids: Vec<TaskId>,
+}
+
+fn start(rng: &mut Rng) -> Run {
+    let start = Duration::from_micros(rng.below(1 << 20));
+    Run {
+        table: TimerTable::new(start),
+        queue: TimerQueue::new(start),
+        now: start,
+        ids: Vec::new(),
+    }
+}
+
+fn step(run: &mut Run, rng: &mut Rng) {
+    let Run {
+        table,
+        queue,
+        now,
+        ids,
+    } = run;
+
+    match rng.below(8) {
+        0..=2 => {
+            let delay = Duration::from_micros(rng.below(64));
+            match table.insert(delay) {
+                Some(id) => {
+                    assert_eq!(queue.insert(delay, id), id);
+                    ids.push(id);
+                }
+                None => assert_eq!(queue.len(), CAPACITY),
+            }
+        }
+        3 => {
+            let delay = Duration::MAX;
+            if let Some(id) = table.insert(delay) {
+                assert_eq!(queue.insert(delay, id), id);
+                ids.push(id);
+            }
+        }
+        4 | 5 => {
+            let Some(&id) = ids.get(rng.below(ids.len() as u64 + 1) as usize) else {
+                return;
+            };
+            assert_eq!(table.remove(id), queue.remove(id).is_some());
+        }
+        6 => {
+            *now += Duration::from_micros(rng.below(64));
+            let due = table.tick(*now).collect::<Vec<TaskId>>();
+            assert_eq!(due, queue.tick(*now).collect::<Vec<_>>());
+        }
+        _ => {
+            /* Nothing moves backwards, but what is already due stays due */
+            let then = now.saturating_sub(Duration::from_micros(rng.below(64)));
+            assert_eq!(table.tick(then).next(), queue.pop_expired(then));
+        }
+    }
+
+    assert_eq!(table.len(), queue.len());
+    assert_eq!(table.is_empty(), queue.is_empty());
+    assert_eq!(table.next_deadline(), queue.next_deadline());
+}
+
+#[test]
+fn random_sequences_match_timer_queue() {
+    random_runs(start, step);
+}
+
+#[test]
+fn a_full_table_refuses_inserts_until_a_timer_goes() {
+    let ms = Duration::from_millis;
+    let mut table = TimerTable::<2>::new(Duration::ZERO);
+    let first = table.insert(ms(2)).unwrap();
+    let second = table.insert(ms(1)).unwrap();
+    assert_eq!(table.insert(ms(3)), None);
+    assert_eq!(table.capacity(), 2);
+
+    assert_eq!(table.tick(ms(1)).collect::<Vec<_>>(), [second]);
+    let third = table.insert(ms(1)).unwrap();
+    assert_eq!(table.tick(ms(2)).collect::<Vec<_>>(), [first, third]);
+}